@@ -0,0 +1,49 @@
+//! times building lists one `cons` at a time and walking them with `cdr`, at doubling lengths, so
+//! the time taken doubling along with the length shows both are linear. run with
+//! `cargo bench --bench lists`
+
+use std::time::{Duration, Instant};
+
+use lisp::{Backend, Interpreter};
+
+const DEFINITIONS: &str = "
+(define (build i acc) (if (= i 0) acc (build (- i 1) (cons i acc))))
+(define (walk xs n) (if (empty? xs) n (walk (cdr xs) (+ n 1))))";
+
+const BENCHES: [(&str, &str); 3] = [
+    ("cons", "(length (build {} '()))"),
+    ("cdr", "(walk xs 0)"),
+    ("range", "(length (range 0 {}))"),
+];
+
+/// the fastest of a few runs of `source`, which should evaluate to `expected`
+fn time(interpreter: &mut Interpreter, source: &str, expected: &str) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            let res = interpreter.eval_to_string(source);
+            let elapsed = start.elapsed();
+
+            assert_eq!(res, expected, "{}", source);
+            elapsed
+        })
+        .min()
+        .expect("there is at least one run")
+}
+
+fn main() {
+    for backend in [Backend::TreeWalker, Backend::Bytecode] {
+        println!("{:?}", backend);
+
+        for (name, template) in BENCHES.iter() {
+            for len in [25_000, 50_000, 100_000, 200_000] {
+                let mut interpreter = Interpreter::with_backend(backend);
+                interpreter.eval_str(DEFINITIONS).expect("the definitions evaluate");
+                interpreter.eval_str(&format!("(define xs (build {} '()))", len)).expect("the list is built");
+
+                let elapsed = time(&mut interpreter, &template.replace("{}", &len.to_string()), &len.to_string());
+                println!("  {:<6} {:>7} {:>10.2?}", name, len, elapsed);
+            }
+        }
+    }
+}
@@ -0,0 +1,1543 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::console::{self, Console};
+use crate::env::LangEnv;
+use crate::eval::{apply, eval, force, is_callable, is_truthy};
+use crate::exp::{plural, Arity, LangErr, LangExp, LangLambda, LangMap, LangRecord, Native, NativeFn, Params};
+use crate::image;
+use crate::interpreter::eval_source;
+use crate::json;
+use crate::limits;
+use crate::list::LangList;
+use crate::lock::Lock;
+use crate::num::Num;
+use crate::optimizer;
+use crate::profile::Profiler;
+use crate::random;
+use crate::reader::{check_macro_prefix, parse_number, ReadTable, ReaderMacro};
+use crate::symbol::SymbolId;
+use crate::testing::Tests;
+use crate::threads::{self, Channel, Threads};
+use crate::trace::Tracer;
+
+/// library functions defined in lisp on top of the builtins
+const PRELUDE: &str = include_str!("prelude.lisp");
+
+/// the width `pprint` fits its output in when not told otherwise
+const PPRINT_WIDTH: usize = 80;
+
+/// how to call each builtin and what it does, shown by `(doc name)`
+const NATIVE_DOCS: &[(&str, &str, &str)] = &[
+    ("+", "(+ nums...)", "the sum of the numbers, 0 without any"),
+    ("-", "(- num nums...)", "subtracts the rest from the first number, or negates a single one"),
+    ("*", "(* nums...)", "the product of the numbers, 1 without any"),
+    ("/", "(/ num nums...)", "divides the first number by the rest, or takes the reciprocal of a single one"),
+    ("mod", "(mod a b)", "the remainder of dividing a by b"),
+    ("pow", "(pow base exponent)", "base raised to the exponent"),
+    ("sqrt", "(sqrt num)", "the square root of a non-negative number, as a float"),
+    ("min", "(min num nums...)", "the smallest of the numbers"),
+    ("max", "(max num nums...)", "the largest of the numbers"),
+    ("abs", "(abs num)", "the absolute value"),
+    ("floor", "(floor num)", "the largest integer not above num"),
+    ("ceil", "(ceil num)", "the smallest integer not below num"),
+    ("round", "(round num)", "the nearest integer, halfway cases away from zero"),
+    ("truncate", "(truncate num)", "num with its fractional part dropped"),
+    ("exact->inexact", "(exact->inexact num)", "num as a float"),
+    ("=", "(= num nums...)", "whether all the numbers are equal"),
+    ("<", "(< num nums...)", "whether the numbers are strictly increasing"),
+    (">", "(> num nums...)", "whether the numbers are strictly decreasing"),
+    ("<=", "(<= num nums...)", "whether the numbers never decrease"),
+    (">=", "(>= num nums...)", "whether the numbers never increase"),
+    ("equal?", "(equal? a b)", "whether a and b have the same structure and contents"),
+    ("eq?", "(eq? a b)", "whether a and b are the same value, not just equal copies"),
+    ("not", "(not x)", "true for false, false for everything else"),
+    ("number?", "(number? x)", "whether x is an integer or a float"),
+    ("integer?", "(integer? x)", "whether x is an integer"),
+    ("string?", "(string? x)", "whether x is a string"),
+    ("char?", "(char? x)", "whether x is a character"),
+    ("symbol?", "(symbol? x)", "whether x is a symbol"),
+    ("list?", "(list? x)", "whether x is a list, empty or not"),
+    ("null?", "(null? x)", "whether x is the empty list"),
+    ("vector?", "(vector? x)", "whether x is a vector"),
+    ("map?", "(map? x)", "whether x is a hash map"),
+    ("bool?", "(bool? x)", "whether x is true or false"),
+    ("fn?", "(fn? x)", "whether x can be called, a builtin or a lambda"),
+    ("list", "(list xs...)", "a list of the arguments"),
+    ("cons", "(cons x list)", "list with x in front"),
+    ("car", "(car list)", "the first element of a non-empty list"),
+    ("cdr", "(cdr list)", "everything but the first element of a non-empty list"),
+    ("length", "(length list)", "the number of elements"),
+    ("append", "(append lists...)", "the elements of all the lists in one list"),
+    ("reverse", "(reverse list)", "the elements in reverse order"),
+    ("nth", "(nth index list)", "the element at index, counting from 0"),
+    ("vector", "(vector xs...)", "a new vector of the arguments"),
+    ("vector-ref", "(vector-ref vector index)", "the element at index, counting from 0"),
+    ("vector-set!", "(vector-set! vector index x)", "replaces the element at index in place"),
+    ("vector-length", "(vector-length vector)", "the number of elements"),
+    ("vector->list", "(vector->list vector)", "the elements as a list"),
+    ("hash-map", "(hash-map keys-and-values...)", "a map from each key to the value after it"),
+    ("get", "(get map key default?)", "the value for key, or default (the empty list if not given) when it is missing"),
+    ("assoc", "(assoc map keys-and-values...)", "a copy of map with each key bound to the value after it"),
+    ("dissoc", "(dissoc map keys...)", "a copy of map without the keys"),
+    ("contains?", "(contains? map key)", "whether map has a value for key"),
+    ("keys", "(keys map)", "the keys as a list, in no particular order"),
+    ("vals", "(vals map)", "the values as a list, in the same order as `keys`"),
+    ("str", "(str xs...)", "the arguments printed one after another into a string, strings without quotes"),
+    ("string-length", "(string-length string)", "the number of characters"),
+    ("substring", "(substring string start end?)", "the characters from start up to, but not including, end"),
+    ("string-split", "(string-split string separator)", "the parts between the separators, or the single characters for an empty separator"),
+    ("string-join", "(string-join list separator?)", "the elements printed into one string with separator between them"),
+    ("string-upcase", "(string-upcase string)", "string in upper case"),
+    ("string-downcase", "(string-downcase string)", "string in lower case"),
+    ("string->list", "(string->list string)", "the characters of string as a list"),
+    ("list->string", "(list->string chars)", "a string of the characters in a list"),
+    ("char->int", "(char->int char)", "the unicode code point of a character"),
+    ("int->char", "(int->char n)", "the character with unicode code point n"),
+    ("string->number", "(string->number string)", "the number string spells, or false if it isn't one"),
+    ("number->string", "(number->string num)", "num printed as a string"),
+    ("json-parse", "(json-parse string)", "the value of a JSON document: objects become maps with string keys, arrays lists and null the empty list"),
+    ("json-encode", "(json-encode x)", "x written as JSON; lists and vectors become arrays, maps objects keyed by strings or symbols"),
+    ("record?", "(record? x)", "whether x is an instance of a `defstruct` type"),
+    ("%record-new", "(%record-new prototype fields...)", "a new record of the prototype's type, used by the constructors `defstruct` defines"),
+    ("%record-is?", "(%record-is? prototype x)", "whether x is a record of the prototype's type, used by the predicates `defstruct` defines"),
+    ("%record-get", "(%record-get prototype index record)", "a field of a record, used by the accessors `defstruct` defines"),
+    ("%record-set!", "(%record-set! prototype index record value)", "replaces a field of a record, used by the setters `defstruct` defines"),
+    ("%memoized", "(%memoized f cache args)", "f called with args, or the value cache holds for them, used by the functions `memoize` returns"),
+    ("force", "(force promise)", "the value of a promise made by `delay`, evaluated the first time it is forced; other values are returned as they are"),
+    ("promise?", "(promise? x)", "whether x is a promise made by `delay`"),
+    ("gensym", "(gensym prefix?)", "a fresh symbol, distinct from every other symbol even if it prints the same"),
+    ("trace", "(trace fs...)", "logs every later call of the functions with its arguments and value, returning the names of all traced functions"),
+    ("untrace", "(untrace fs...)", "stops logging calls of the functions, or of all functions without arguments"),
+    ("now-ms", "(now-ms)", "the milliseconds since the start of 1970, UTC"),
+    ("random", "(random)", "a random float from 0 up to, but not including, 1"),
+    ("rand-int", "(rand-int n)", "a random integer from 0 up to, but not including, n"),
+    ("spawn", "(spawn f)", "calls f without arguments on a new thread, returning a handle to `join`; the thread shares the global definitions"),
+    ("join", "(join thread)", "waits for a thread started by `spawn` to finish and returns its value, or raises the error it failed with"),
+    ("chan", "(chan)", "a new channel, an unbounded queue of values any thread can send to and receive from"),
+    ("send!", "(send! channel x)", "puts x at the back of the channel's queue and returns it"),
+    ("recv!", "(recv! channel)", "takes the value at the front of the channel's queue, waiting for one to be sent if it is empty, failing when every other thread is done or waiting too"),
+    ("seed-random", "(seed-random n)", "restarts the numbers `random` and `rand-int` return, the same seed giving the same ones every run"),
+    ("raise", "(raise x)", "signals x as an error, which `try` can catch"),
+    ("error", "(error message irritants...)", "raises the message followed by the printed irritants"),
+    ("pprint", "(pprint x width?)", "writes x followed by a newline, spread over lines and indented where it doesn't fit in width columns, 80 if not given"),
+    ("print", "(print xs...)", "writes the arguments to the output, stdout unless the embedder says otherwise, separated by spaces"),
+    ("println", "(println xs...)", "like `print`, followed by a newline"),
+    ("format", "(format template args...)", "template with each ~a replaced by the next argument as `print` shows it, each ~s by the next argument as the reader writes it, ~% by a newline and ~~ by a tilde"),
+    ("printf", "(printf template args...)", "writes what `format` returns to the output"),
+    ("read-line", "(read-line)", "the next line of input, stdin unless the embedder says otherwise, without its newline, or the empty list at the end of input"),
+    ("slurp", "(slurp path)", "the contents of a file as a string"),
+    ("spit", "(spit path string)", "writes string to a file, replacing what was there"),
+    ("save-image", "(save-image path)", "writes the session's own definitions to a file `lisp --image path` starts from, returning the names it couldn't write"),
+    ("getenv", "(getenv name)", "the value of an environment variable as a string, or the empty list when it isn't set"),
+    ("setenv", "(setenv name value)", "sets an environment variable of this process and the commands it runs"),
+    ("sh", "(sh command)", "runs command with the system shell, returning a map of its \"status\", \"out\" and \"err\"; the status is the empty list when it was killed"),
+    ("exit", "(exit code?)", "stops the interpreter, the `lisp` binary exits with code, 0 by default"),
+    ("map", "(map f lists...)", "f applied to the elements of the lists side by side, stopping at the shortest"),
+    ("filter", "(filter pred list)", "the elements pred returns a truthy value for"),
+    ("fold", "(fold f init list)", "combines the elements from the left by calling (f acc x), starting from init"),
+    ("reduce", "(reduce f init? list)", "like `fold`, starting from the first element when init isn't given"),
+    ("set-macro-character", "(set-macro-character prefix f)", "makes the reader call f on the form after prefix, a character or string, and read what f returns in its place; applies from the next top-level form on"),
+    ("eval", "(eval form)", "evaluates form at the top level"),
+    ("optimize", "(optimize form)", "form as the optimizer rewrites it for the top level, the way files run by `lisp` are"),
+    ("apply", "(apply f args... list)", "calls f with the args followed by the elements of list"),
+    ("run-tests", "(run-tests)", "runs the tests declared with `deftest`, printing which failed and why, and returns whether they all passed"),
+    ("memoize", "(memoize f)", "a function calling f once for each list of arguments equal to an earlier one, then returning the value it gave"),
+];
+
+/// the signature and description of a builtin
+pub(crate) fn native_doc(name: &str) -> Option<(&'static str, &'static str)> {
+    NATIVE_DOCS.iter().find(|(native, _, _)| *native == name).map(|(_, signature, doc)| (*signature, *doc))
+}
+
+/// a global environment with the builtins and the prelude
+pub(crate) fn default_env() -> LangEnv {
+    let mut data: HashMap<String, (Arity, NativeFn)> = HashMap::new();
+
+    data.insert(
+        "+".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let sum = parse_list_of_nums(args)?.into_iter().fold(Num::Int(0), Num::add);
+
+                Ok(sum.into_exp())
+            }
+        ),
+    );
+
+    data.insert(
+        "-".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let nums = parse_list_of_nums(args)?;
+                let first = nums[0];
+
+                if nums.len() == 1 {
+                    return Ok(first.neg().into_exp());
+                }
+
+                let sum_of_rest = nums[1..].iter().copied().fold(Num::Int(0), Num::add);
+
+                Ok(first.sub(sum_of_rest).into_exp())
+            }
+        ),
+    );
+
+    data.insert(
+        "*".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let product = parse_list_of_nums(args)?.into_iter().fold(Num::Int(1), Num::mul);
+
+                Ok(product.into_exp())
+            }
+        ),
+    );
+
+    data.insert(
+        "/".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let nums = parse_list_of_nums(args)?;
+                let first = nums[0];
+
+                // `(/ x)` is the reciprocal of x, like in scheme
+                let (dividend, divisors) = if nums.len() == 1 {
+                    (Num::Int(1), &nums[..])
+                } else {
+                    (first, &nums[1..])
+                };
+
+                divisors.iter()
+                    .try_fold(dividend, |quotient, a| quotient.div(*a))
+                    .map(Num::into_exp)
+            }
+        ),
+    );
+
+    data.insert(
+        "mod".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let nums = parse_list_of_nums(args)?;
+
+                nums[0].modulo(nums[1]).map(Num::into_exp)
+            }
+        ),
+    );
+
+    data.insert(
+        "pow".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let nums = parse_list_of_nums(args)?;
+
+                Ok(nums[0].pow(nums[1]).into_exp())
+            }
+        ),
+    );
+
+    data.insert(
+        "sqrt".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let x = parse_list_of_nums(args)?[0].as_f64();
+
+                if x < 0.0 {
+                    return Err(LangErr::Reason("sqrt expects a non-negative number".to_string()));
+                }
+
+                Ok(LangExp::Number(x.sqrt()))
+            }
+        ),
+    );
+
+    data.insert("min".to_string(), (Arity::AtLeast(1), |args, _| fold_non_empty(args, Ordering::Less)));
+    data.insert("max".to_string(), (Arity::AtLeast(1), |args, _| fold_non_empty(args, Ordering::Greater)));
+
+    data.insert("abs".to_string(), (Arity::Exact(1), |args, _| Ok(parse_list_of_nums(args)?[0].abs().into_exp())));
+
+    // rounding turns floats into integers
+    data.insert("floor".to_string(), (Arity::Exact(1), |args, _| Ok(parse_list_of_nums(args)?[0].to_integer(f64::floor).into_exp())));
+    data.insert("ceil".to_string(), (Arity::Exact(1), |args, _| Ok(parse_list_of_nums(args)?[0].to_integer(f64::ceil).into_exp())));
+    data.insert("round".to_string(), (Arity::Exact(1), |args, _| Ok(parse_list_of_nums(args)?[0].to_integer(f64::round).into_exp())));
+    data.insert("truncate".to_string(), (Arity::Exact(1), |args, _| Ok(parse_list_of_nums(args)?[0].to_integer(f64::trunc).into_exp())));
+
+    data.insert("exact->inexact".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Number(parse_list_of_nums(args)?[0].as_f64()))));
+
+    data.insert("=".to_string(), (Arity::AtLeast(1), |args, _| ensure_monotonic(args, |o| o == Ordering::Equal)));
+    data.insert("<".to_string(), (Arity::AtLeast(1), |args, _| ensure_monotonic(args, |o| o == Ordering::Less)));
+    data.insert(">".to_string(), (Arity::AtLeast(1), |args, _| ensure_monotonic(args, |o| o == Ordering::Greater)));
+    data.insert("<=".to_string(), (Arity::AtLeast(1), |args, _| ensure_monotonic(args, |o| o != Ordering::Greater)));
+    data.insert(">=".to_string(), (Arity::AtLeast(1), |args, _| ensure_monotonic(args, |o| o != Ordering::Less)));
+
+    // `=` compares numbers by value, these work on any values
+    data.insert(
+        "equal?".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Bool(args[0] == args[1]))
+            }
+        ),
+    );
+
+    data.insert(
+        "eq?".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Bool(args[0].is_identical(&args[1])))
+            }
+        ),
+    );
+
+    data.insert(
+        "not".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Bool(!is_truthy(&args[0])))
+            }
+        ),
+    );
+
+    data.insert("number?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Int(_) | LangExp::Number(_))))));
+    data.insert("integer?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Int(_))))));
+    data.insert("string?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Str(_))))));
+    data.insert("char?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Char(_))))));
+    data.insert("symbol?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Symbol(_))))));
+    data.insert("list?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::List(_))))));
+    data.insert("null?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::List(list) if list.is_empty())))));
+    data.insert("vector?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Vector(_))))));
+    data.insert("map?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Map(_))))));
+    data.insert("bool?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Bool(_))))));
+    data.insert("record?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Record(_))))));
+    data.insert("promise?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(matches!(&args[0], LangExp::Promise(_))))));
+    // macros are left out, they can't be called like functions
+    data.insert("fn?".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Bool(is_callable(&args[0])))));
+
+    data.insert("list".to_string(), (Arity::Any, |args, _| Ok(LangExp::list(args.to_vec()))));
+
+    data.insert(
+        "cons".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::List(parse_list(&args[1])?.cons(args[0].clone())))
+            }
+        ),
+    );
+
+    data.insert(
+        "car".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                parse_list(&args[0])?
+                    .first()
+                    .cloned()
+                    .ok_or(LangErr::Reason("car of an empty list".to_string()))
+            }
+        ),
+    );
+
+    data.insert(
+        "cdr".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                match parse_list(&args[0])?.rest() {
+                    Some(rest) => Ok(LangExp::List(rest)),
+                    None => Err(LangErr::Reason("cdr of an empty list".to_string()))
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "length".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Int(parse_list(&args[0])?.len() as i64))
+            }
+        ),
+    );
+
+    data.insert(
+        "append".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let mut res = vec![];
+
+                for arg in args {
+                    res.extend_from_slice(parse_list(arg)?);
+                }
+
+                Ok(LangExp::list(res))
+            }
+        ),
+    );
+
+    data.insert(
+        "reverse".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::list(parse_list(&args[0])?.iter().rev().cloned().collect()))
+            }
+        ),
+    );
+
+    data.insert(
+        "nth".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let n = parse_index(&args[0])?;
+                let list = parse_list(&args[1])?;
+
+                list.get(n).cloned().ok_or(LangErr::Reason(format!(
+                    "index {} out of range for a list of length {}", n, list.len()
+                )))
+            }
+        ),
+    );
+
+    data.insert("vector".to_string(), (Arity::Any, |args, _| Ok(LangExp::Vector(Arc::new(Lock::new(args.to_vec()))))));
+
+    data.insert(
+        "vector-ref".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let items = parse_vector(&args[0])?.borrow();
+                let i = parse_index(&args[1])?;
+
+                items.get(i).cloned().ok_or(LangErr::Reason(format!(
+                    "index {} out of range for a vector of length {}", i, items.len()
+                )))
+            }
+        ),
+    );
+
+    data.insert(
+        "vector-set!".to_string(),
+        (
+            Arity::Exact(3),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let mut items = parse_vector(&args[0])?.borrow_mut();
+                let i = parse_index(&args[1])?;
+                let len = items.len();
+
+                let slot = items.get_mut(i).ok_or(LangErr::Reason(format!(
+                    "index {} out of range for a vector of length {}", i, len
+                )))?;
+                *slot = args[2].clone();
+
+                Ok(args[2].clone())
+            }
+        ),
+    );
+
+    data.insert(
+        "vector-length".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Int(parse_vector(&args[0])?.borrow().len() as i64))
+            }
+        ),
+    );
+
+    data.insert(
+        "vector->list".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::list(parse_vector(&args[0])?.borrow().clone()))
+            }
+        ),
+    );
+
+    data.insert(
+        "hash-map".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                if !args.len().is_multiple_of(2) {
+                    return Err(LangErr::Reason("hash-map expects an even number of arguments".to_string()));
+                }
+
+                let map: LangMap = args
+                    .chunks(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect();
+
+                Ok(LangExp::Map(Arc::new(map)))
+            }
+        ),
+    );
+
+    data.insert(
+        "get".to_string(),
+        (
+            Arity::Between(2, 3),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // (get m k) or (get m k default), a missing key without a default yields ()
+                match args {
+                    [m, k] => Ok(parse_map(m)?.get(k).cloned().unwrap_or(LangExp::nil())),
+                    [m, k, default] => Ok(parse_map(m)?.get(k).cloned().unwrap_or_else(|| default.clone())),
+                    _ => unreachable!("the arity is checked before the call")
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "assoc".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let (m, pairs) = (&args[0], &args[1..]);
+
+                if !pairs.len().is_multiple_of(2) {
+                    return Err(LangErr::Reason("assoc expects a map followed by keys and values".to_string()));
+                }
+
+                let mut map = parse_map(m)?.clone();
+
+                for pair in pairs.chunks(2) {
+                    map.insert(pair[0].clone(), pair[1].clone());
+                }
+
+                Ok(LangExp::Map(Arc::new(map)))
+            }
+        ),
+    );
+
+    data.insert(
+        "dissoc".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let (m, keys) = (&args[0], &args[1..]);
+
+                let mut map = parse_map(m)?.clone();
+
+                for k in keys {
+                    map.remove(k);
+                }
+
+                Ok(LangExp::Map(Arc::new(map)))
+            }
+        ),
+    );
+
+    data.insert(
+        "contains?".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Bool(parse_map(&args[0])?.contains_key(&args[1])))
+            }
+        ),
+    );
+
+    data.insert(
+        "keys".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::list(parse_map(&args[0])?.keys().cloned().collect()))
+            }
+        ),
+    );
+
+    data.insert(
+        "vals".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::list(parse_map(&args[0])?.values().cloned().collect()))
+            }
+        ),
+    );
+
+    data.insert(
+        "str".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // strings are joined as they are, anything else as it would print
+                Ok(LangExp::Str(args.iter().map(to_plain_string).collect::<String>().into()))
+            }
+        ),
+    );
+
+    data.insert(
+        "string-length".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Int(parse_str(&args[0])?.chars().count() as i64))
+            }
+        ),
+    );
+
+    data.insert(
+        "substring".to_string(),
+        (
+            Arity::Between(2, 3),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // (substring s start) or (substring s start end), counting characters from 0
+                let (s, start, end) = match args {
+                    [s, start] => {
+                        let s = parse_str(s)?;
+                        (s, parse_index(start)?, s.chars().count())
+                    }
+                    [s, start, end] => (parse_str(s)?, parse_index(start)?, parse_index(end)?),
+                    _ => unreachable!("the arity is checked before the call")
+                };
+
+                let len = s.chars().count();
+
+                if start > end || end > len {
+                    return Err(LangErr::Reason(format!("substring range {}..{} is out of bounds for a string of length {}", start, end, len)));
+                }
+
+                Ok(LangExp::Str(s.chars().skip(start).take(end - start).collect::<String>().into()))
+            }
+        ),
+    );
+
+    data.insert(
+        "string-split".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let s = parse_str(&args[0])?;
+                let sep = parse_str(&args[1])?;
+
+                // an empty separator splits into single characters
+                let parts: Vec<LangExp> = if sep.is_empty() {
+                    s.chars().map(|c| LangExp::Str(c.to_string().into())).collect()
+                } else {
+                    s.split(sep).map(|part| LangExp::Str(part.to_string().into())).collect()
+                };
+
+                Ok(LangExp::list(parts))
+            }
+        ),
+    );
+
+    data.insert(
+        "string-join".to_string(),
+        (
+            Arity::Between(1, 2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // (string-join list) or (string-join list sep)
+                let (list, sep) = match args {
+                    [list] => (parse_list(list)?, ""),
+                    [list, sep] => (parse_list(list)?, parse_str(sep)?),
+                    _ => unreachable!("the arity is checked before the call")
+                };
+
+                let parts: Vec<String> = list.iter().map(to_plain_string).collect();
+
+                Ok(LangExp::Str(parts.join(sep).into()))
+            }
+        ),
+    );
+
+    data.insert(
+        "string-upcase".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Str(parse_str(&args[0])?.to_uppercase().into()))
+            }
+        ),
+    );
+
+    data.insert(
+        "string-downcase".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Str(parse_str(&args[0])?.to_lowercase().into()))
+            }
+        ),
+    );
+
+    data.insert(
+        "string->list".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::list(parse_str(&args[0])?.chars().map(LangExp::Char).collect()))
+            }
+        ),
+    );
+
+    data.insert(
+        "list->string".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let chars = parse_list(&args[0])?
+                    .iter()
+                    .map(parse_char)
+                    .collect::<Result<String, LangErr>>()?;
+
+                Ok(LangExp::Str(chars.into()))
+            }
+        ),
+    );
+
+    data.insert("char->int".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Int(parse_char(&args[0])? as i64))));
+
+    data.insert(
+        "int->char".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let code = match &args[0] {
+                    LangExp::Int(code) => *code,
+                    other => return Err(LangErr::type_error("an integer", other))
+                };
+
+                u32::try_from(code)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .map(LangExp::Char)
+                    .ok_or_else(|| LangErr::Reason(format!("no character has the code point {}", code)))
+            }
+        ),
+    );
+
+    data.insert(
+        "string->number".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // false when the string isn't a number literal
+                Ok(parse_number(parse_str(&args[0])?.trim()).unwrap_or(LangExp::Bool(false)))
+            }
+        ),
+    );
+
+    data.insert(
+        "number->string".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Str(parse_list_of_nums(args)?[0].into_exp().to_string().into()))
+            }
+        ),
+    );
+
+    data.insert("json-parse".to_string(), (Arity::Exact(1), |args, _| json::parse(parse_str(&args[0])?)));
+
+    data.insert("json-encode".to_string(), (Arity::Exact(1), |args, _| Ok(LangExp::Str(json::encode(&args[0])?.into()))));
+
+    data.insert(
+        "%record-new".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let kind = parse_record(&args[0])?.kind.clone();
+
+                Ok(LangExp::Record(Arc::new(LangRecord { kind, fields: Lock::new(args[1..].to_vec()) })))
+            }
+        ),
+    );
+
+    data.insert(
+        "%record-is?".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let kind = &parse_record(&args[0])?.kind;
+
+                Ok(LangExp::Bool(matches!(&args[1], LangExp::Record(record) if Arc::ptr_eq(&record.kind, kind))))
+            }
+        ),
+    );
+
+    data.insert(
+        "%record-get".to_string(),
+        (
+            Arity::Exact(3),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let record = parse_record_of(&args[0], &args[2])?;
+                let index = parse_index(&args[1])?;
+
+                record.fields.borrow().get(index).cloned().ok_or(LangErr::Reason(format!("no field {} in {}", index, args[2])))
+            }
+        ),
+    );
+
+    data.insert(
+        "%record-set!".to_string(),
+        (
+            Arity::Exact(4),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let record = parse_record_of(&args[0], &args[2])?;
+                let index = parse_index(&args[1])?;
+
+                match record.fields.borrow_mut().get_mut(index) {
+                    Some(field) => *field = args[3].clone(),
+                    None => return Err(LangErr::Reason(format!("no field {} in {}", index, args[2])))
+                }
+
+                Ok(args[3].clone())
+            }
+        ),
+    );
+
+    data.insert("run-tests".to_string(), (Arity::Exact(0), |_, env| env.tests.run(env)));
+
+    data.insert(
+        "memoize".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                if !is_callable(&args[0]) {
+                    return Err(LangErr::type_error("a function", &args[0]));
+                }
+
+                // a lambda around %memoized, like the functions defstruct defines. it finds f and
+                // the cache, a vector holding a map from argument lists to values, in its scope
+                let (f, cache, rest) = (SymbolId::intern("f"), SymbolId::intern("cache"), SymbolId::intern("args"));
+
+                let scope = LangEnv::new_child(&env.global());
+                scope.insert(f, args[0].clone());
+                scope.insert(cache, LangExp::Vector(Arc::new(Lock::new(vec![LangExp::Map(Arc::new(LangMap::default()))]))));
+
+                let call = ["%memoized", "f", "cache", "args"].iter().map(|name| LangExp::Symbol(SymbolId::intern(name))).collect();
+
+                Ok(LangExp::Lambda(Arc::new(LangLambda {
+                    params: Arc::new(Params { required: vec![], optional: vec![], rest: Some(rest) }),
+                    body: Arc::new(vec![LangExp::list(call)]),
+                    env: scope,
+                    name: None,
+                    doc: match &args[0] {
+                        LangExp::Lambda(lambda) => lambda.doc.clone(),
+                        _ => None
+                    },
+                    code: Arc::new(OnceLock::new()),
+                })))
+            }
+        ),
+    );
+
+    data.insert(
+        "%memoized".to_string(),
+        (
+            Arity::Exact(3),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let cache = parse_vector(&args[1])?;
+
+                if let Some(LangExp::Map(map)) = cache.borrow().first() {
+                    if let Some(value) = map.get(&args[2]) {
+                        return Ok(value.clone());
+                    }
+                }
+
+                // the cache isn't locked during the call, which may well call the memoized function again
+                let value = apply(&args[0], parse_list(&args[2])?, env)?;
+
+                if let Some(LangExp::Map(map)) = cache.borrow_mut().first_mut() {
+                    // the map is only ever referenced from the cache, so it is updated in place
+                    Arc::make_mut(map).insert(args[2].clone(), value.clone());
+                }
+
+                Ok(value)
+            }
+        ),
+    );
+
+    data.insert("force".to_string(), (Arity::Exact(1), |args, _| force(&args[0])));
+
+    data.insert("spawn".to_string(), (Arity::Exact(1), |args, env| threads::spawn(&args[0], env)));
+
+    data.insert(
+        "join".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                match &args[0] {
+                    LangExp::Thread(thread) => thread.join(env),
+                    other => Err(LangErr::type_error("a thread", other))
+                }
+            }
+        ),
+    );
+
+    data.insert("chan".to_string(), (Arity::Exact(0), |_, env| Ok(LangExp::Channel(Arc::new(Channel::new(&env.threads))))));
+
+    data.insert(
+        "send!".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                parse_channel(&args[0])?.send(args[1].clone());
+
+                Ok(args[1].clone())
+            }
+        ),
+    );
+
+    data.insert("recv!".to_string(), (Arity::Exact(1), |args, env| parse_channel(&args[0])?.recv(env)));
+
+    data.insert(
+        "gensym".to_string(),
+        (
+            Arity::Between(0, 1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let prefix = match args {
+                    [prefix] => parse_str(prefix)?,
+                    _ => "g"
+                };
+
+                Ok(LangExp::Symbol(SymbolId::gensym(prefix)))
+            }
+        ),
+    );
+
+    data.insert(
+        "trace".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let names = args.iter().map(parse_traceable).collect::<Result<Vec<SymbolId>, LangErr>>()?;
+                env.tracer.update(names, true);
+
+                Ok(traced_names(env))
+            }
+        ),
+    );
+
+    data.insert(
+        "untrace".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let names = args.iter().map(parse_traceable).collect::<Result<Vec<SymbolId>, LangErr>>()?;
+                // without arguments, everything stops being traced
+                env.tracer.update(names, false);
+
+                Ok(traced_names(env))
+            }
+        ),
+    );
+
+    data.insert(
+        "now-ms".to_string(),
+        (
+            Arity::Exact(0),
+            |_args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                let elapsed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|e| LangErr::Reason(format!("now-ms: the clock is before 1970: {}", e)))?;
+
+                Ok(LangExp::Int(elapsed.as_millis() as i64))
+            }
+        ),
+    );
+
+    data.insert("random".to_string(), (Arity::Exact(0), |_, _| Ok(LangExp::Number(random::next_f64()))));
+
+    data.insert(
+        "rand-int".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                match &args[0] {
+                    LangExp::Int(n) if *n > 0 => Ok(LangExp::Int(random::below(*n as u64) as i64)),
+                    other => Err(LangErr::type_error("a positive integer", other))
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "seed-random".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                match &args[0] {
+                    LangExp::Int(n) => random::seed(*n as u64),
+                    other => return Err(LangErr::type_error("an integer", other))
+                }
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "raise".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Err(LangErr::Raised(args[0].clone()))
+            }
+        ),
+    );
+
+    data.insert(
+        "error".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // (error "message" irritants...) raises the message followed by the printed irritants
+                let parts: Vec<String> = args.iter().map(to_plain_string).collect();
+
+                Err(LangErr::Raised(LangExp::Str(parts.join(" ").into())))
+            }
+        ),
+    );
+
+    data.insert(
+        "pprint".to_string(),
+        (
+            Arity::Between(1, 2),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let width = match args.get(1) {
+                    Some(width) => parse_index(width)?,
+                    None => PPRINT_WIDTH
+                };
+
+                write_out(env, &(args[0].to_pretty_string(width) + "\n"))?;
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "print".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                write_out(env, &args.iter().map(to_plain_string).collect::<Vec<String>>().join(" "))?;
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "println".to_string(),
+        (
+            Arity::Any,
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                write_out(env, &(args.iter().map(to_plain_string).collect::<Vec<String>>().join(" ") + "\n"))?;
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "set-macro-character".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let prefix = match &args[0] {
+                    LangExp::Char(c) => c.to_string(),
+                    LangExp::Str(s) => s.to_string(),
+                    other => return Err(LangErr::type_error("a character or string", other))
+                };
+
+                check_macro_prefix(&prefix)?;
+
+                if !is_callable(&args[1]) {
+                    return Err(LangErr::type_error("a function", &args[1]));
+                }
+
+                env.readtable.borrow_mut().set(&prefix, ReaderMacro::Lisp(args[1].clone(), env.clone()));
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "format".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                Ok(LangExp::Str(format_template(parse_str(&args[0])?, &args[1..])?.into()))
+            }
+        ),
+    );
+
+    data.insert(
+        "printf".to_string(),
+        (
+            Arity::AtLeast(1),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                write_out(env, &format_template(parse_str(&args[0])?, &args[1..])?)?;
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "read-line".to_string(),
+        (
+            Arity::Exact(0),
+            |_args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("read-line")?;
+
+                let line = (console::lock(&env.console).input)();
+
+                // the end of input reads as ()
+                match line {
+                    Ok(None) => Ok(LangExp::nil()),
+                    Ok(Some(line)) => Ok(LangExp::Str(line.into())),
+                    Err(e) => Err(LangErr::Reason(format!("read-line: {}", e)))
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "slurp".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("slurp")?;
+
+                let path = parse_str(&args[0])?;
+
+                fs::read_to_string(path)
+                    .map(|source| LangExp::Str(source.into()))
+                    .map_err(|e| LangErr::Reason(format!("slurp: cannot read {}: {}", path, e)))
+            }
+        ),
+    );
+
+    data.insert(
+        "spit".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                // (spit path content) replaces the file, content that isn't a string is written as it prints
+                limits::check_io("spit")?;
+
+                let path = parse_str(&args[0])?;
+
+                fs::write(path, to_plain_string(&args[1]))
+                    .map_err(|e| LangErr::Reason(format!("spit: cannot write {}: {}", path, e)))?;
+
+                Ok(LangExp::nil())
+            }
+        ),
+    );
+
+    data.insert(
+        "save-image".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("save-image")?;
+
+                image::save(env, parse_str(&args[0])?)
+            }
+        ),
+    );
+
+    data.insert(
+        "getenv".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("getenv")?;
+
+                match std::env::var_os(parse_str(&args[0])?) {
+                    Some(value) => Ok(LangExp::Str(value.to_string_lossy().into())),
+                    None => Ok(LangExp::nil())
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "setenv".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("setenv")?;
+
+                let (name, value) = (parse_str(&args[0])?, parse_str(&args[1])?);
+
+                // these would make set_var panic
+                if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                    return Err(LangErr::Reason(format!("setenv: cannot set {} to {}", args[0], args[1])));
+                }
+
+                std::env::set_var(name, value);
+
+                Ok(args[1].clone())
+            }
+        ),
+    );
+
+    data.insert(
+        "sh".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("sh")?;
+
+                let command = parse_str(&args[0])?;
+                let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+
+                let output = Command::new(shell)
+                    .args([flag, command])
+                    .output()
+                    .map_err(|e| LangErr::Reason(format!("sh: cannot run {}: {}", command, e)))?;
+
+                let status = output.status.code().map_or(LangExp::nil(), |code| LangExp::Int(code as i64));
+                let text = |bytes: &[u8]| LangExp::Str(String::from_utf8_lossy(bytes).into());
+
+                let mut res = LangMap::default();
+                res.insert(LangExp::Str("status".into()), status);
+                res.insert(LangExp::Str("out".into()), text(&output.stdout));
+                res.insert(LangExp::Str("err".into()), text(&output.stderr));
+
+                Ok(LangExp::Map(Arc::new(res)))
+            }
+        ),
+    );
+
+    data.insert(
+        "exit".to_string(),
+        (
+            Arity::Between(0, 1),
+            |args: &[LangExp], _env: &LangEnv| -> Result<LangExp, LangErr> {
+                limits::check_io("exit")?;
+
+                // an error that nothing catches, so the embedder decides what exiting means
+                match args.first() {
+                    None => Err(LangErr::Exit(0)),
+                    Some(LangExp::Int(code)) => Err(LangErr::Exit(i32::try_from(*code).unwrap_or(1))),
+                    Some(code) => Err(LangErr::type_error("an integer", code))
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "map".to_string(),
+        (
+            Arity::AtLeast(2),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let f = &args[0];
+                let lists = args[1..].iter().map(parse_list).collect::<Result<Vec<_>, LangErr>>()?;
+
+                // with several lists, f gets one element of each and the shortest list wins
+                let len = lists.iter().map(|list| list.len()).min().unwrap_or(0);
+
+                (0..len)
+                    .map(|i| apply(f, &lists.iter().map(|list| list[i].clone()).collect::<Vec<LangExp>>(), env))
+                    .collect::<Result<Vec<LangExp>, LangErr>>()
+                    .map(LangExp::list)
+            }
+        ),
+    );
+
+    data.insert(
+        "filter".to_string(),
+        (
+            Arity::Exact(2),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                let mut res = vec![];
+
+                for x in parse_list(&args[1])? {
+                    if is_truthy(&apply(&args[0], std::slice::from_ref(x), env)?) {
+                        res.push(x.clone());
+                    }
+                }
+
+                Ok(LangExp::list(res))
+            }
+        ),
+    );
+
+    data.insert(
+        "fold".to_string(),
+        (
+            Arity::Exact(3),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                fold_list(&args[0], args[1].clone(), parse_list(&args[2])?, env)
+            }
+        ),
+    );
+
+    data.insert(
+        "reduce".to_string(),
+        (
+            Arity::Between(2, 3),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                match args {
+                    // without an initial value the first element is used
+                    [f, list] => match parse_list(list)?.split_first() {
+                        Some((first, rest)) => fold_list(f, first.clone(), rest, env),
+                        None => Err(LangErr::Reason("reduce of an empty list needs an initial value".to_string()))
+                    },
+                    [f, init, list] => fold_list(f, init.clone(), parse_list(list)?, env),
+                    _ => unreachable!("the arity is checked before the call")
+                }
+            }
+        ),
+    );
+
+    data.insert(
+        "eval".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                // like a form typed at the top level, the caller's local bindings are not visible
+                eval(&args[0], &env.global())
+            }
+        ),
+    );
+
+    data.insert(
+        "optimize".to_string(),
+        (
+            Arity::Exact(1),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                // for the top level like `eval`, which is where an optimized form would run
+                Ok(optimizer::optimize(&args[0], &env.global()))
+            }
+        ),
+    );
+
+    data.insert(
+        "apply".to_string(),
+        (
+            Arity::AtLeast(2),
+            |args: &[LangExp], env: &LangEnv| -> Result<LangExp, LangErr> {
+                // (apply f a b '(c d)) calls (f a b c d)
+                match args {
+                    [f, init @ .., last] => {
+                        let mut call_args = init.to_vec();
+                        call_args.extend_from_slice(parse_list(last)?);
+
+                        apply(f, &call_args, env)
+                    }
+                    _ => unreachable!("the arity is checked before the call")
+                }
+            }
+        ),
+    );
+
+    let data = data
+        .into_iter()
+        .map(|(k, (arity, f))| {
+            let name = SymbolId::intern(&k);
+            (name, LangExp::Func(Native { name, arity, f }))
+        })
+        .collect();
+
+    let env = LangEnv {
+        data: Arc::new(Lock::new(data)),
+        outer: None,
+        loaded: Arc::new(Lock::new(HashSet::new())),
+        console: Arc::new(Mutex::new(Console::stdio())),
+        tracer: Arc::new(Tracer::default()),
+        profiler: Arc::new(Profiler::default()),
+        readtable: Arc::new(Lock::new(ReadTable::default())),
+        tests: Arc::new(Tests::default()),
+        threads: Arc::new(Threads::default()),
+    };
+
+    if let Err(e) = eval_source(PRELUDE, &env) {
+        panic!("the prelude failed to evaluate: prelude.lisp:{}", e.render(PRELUDE));
+    }
+
+    env
+}
+
+/// calls `(f acc x)` for every element from left to right
+fn fold_list(f: &LangExp, init: LangExp, list: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    list.iter().try_fold(init, |acc, x| apply(f, &[acc, x.clone()], env))
+}
+
+fn parse_list(exp: &LangExp) -> Result<&LangList, LangErr> {
+    match exp {
+        LangExp::List(list) => Ok(list),
+        _ => Err(LangErr::type_error("a list", exp))
+    }
+}
+
+fn parse_char(exp: &LangExp) -> Result<char, LangErr> {
+    match exp {
+        LangExp::Char(c) => Ok(*c),
+        _ => Err(LangErr::type_error("a character", exp))
+    }
+}
+
+fn parse_str(exp: &LangExp) -> Result<&str, LangErr> {
+    match exp {
+        LangExp::Str(s) => Ok(s),
+        _ => Err(LangErr::type_error("a string", exp))
+    }
+}
+
+/// the text of a string without quotes, a character by itself, or how any other value prints
+fn to_plain_string(exp: &LangExp) -> String {
+    match exp {
+        LangExp::Str(s) => s.to_string(),
+        LangExp::Char(c) => c.to_string(),
+        _ => exp.to_string()
+    }
+}
+
+/// fills in the directives of a `format` template with `args`, which have to be used up exactly
+fn format_template(template: &str, args: &[LangExp]) -> Result<String, LangErr> {
+    let mut res = String::new();
+    let mut args = args.iter();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(directive @ ('a' | 'A' | 's' | 'S')) => {
+                let arg = args.next().ok_or_else(|| {
+                    LangErr::Reason(format!("format: no argument left for ~{}", directive))
+                })?;
+
+                match directive {
+                    'a' | 'A' => res.push_str(&to_plain_string(arg)),
+                    _ => res.push_str(&arg.to_string())
+                }
+            }
+            Some('%') => res.push('\n'),
+            Some('~') => res.push('~'),
+            Some(other) => return Err(LangErr::Reason(format!("format: unknown directive ~{}", other))),
+            None => return Err(LangErr::Reason("format: the template ends with a lone ~".to_string()))
+        }
+    }
+
+    match args.len() {
+        0 => Ok(res),
+        left => Err(LangErr::Reason(format!("format: {} more than the template has directives for", plural(left, "argument"))))
+    }
+}
+
+/// hands text to the interpreter's output sink, stdout unless the embedder set another one
+pub(crate) fn write_out(env: &LangEnv, text: &str) -> Result<(), LangErr> {
+    (console::lock(&env.console).output)(text).map_err(|e| LangErr::Reason(format!("cannot write output: {}", e)))
+}
+
+fn parse_vector(exp: &LangExp) -> Result<&Lock<Vec<LangExp>>, LangErr> {
+    match exp {
+        LangExp::Vector(items) => Ok(items),
+        _ => Err(LangErr::type_error("a vector", exp))
+    }
+}
+
+fn parse_record(exp: &LangExp) -> Result<&LangRecord, LangErr> {
+    match exp {
+        LangExp::Record(record) => Ok(record),
+        _ => Err(LangErr::type_error("a record", exp))
+    }
+}
+
+/// `exp` as a record of the same type as `prototype`
+fn parse_record_of<'a>(prototype: &LangExp, exp: &'a LangExp) -> Result<&'a LangRecord, LangErr> {
+    let kind = &parse_record(prototype)?.kind;
+
+    match exp {
+        LangExp::Record(record) if Arc::ptr_eq(&record.kind, kind) => Ok(record),
+        _ => Err(LangErr::Type { expected: format!("a {}", kind.name), got: exp.type_name().to_string() })
+    }
+}
+
+/// the name a function is traced under, which is the name it was defined with
+fn parse_traceable(exp: &LangExp) -> Result<SymbolId, LangErr> {
+    match exp {
+        LangExp::Func(native) => Ok(native.name),
+        LangExp::Lambda(lambda) => match lambda.name {
+            Some(name) => Ok(name),
+            None => Err(LangErr::Reason("only functions with a name can be traced, define it first".to_string()))
+        },
+        _ => Err(LangErr::type_error("a function", exp))
+    }
+}
+
+/// the traced names as a sorted list of symbols
+fn traced_names(env: &LangEnv) -> LangExp {
+    LangExp::list(env.tracer.names().into_iter().map(LangExp::Symbol).collect())
+}
+
+fn parse_channel(exp: &LangExp) -> Result<&Channel, LangErr> {
+    match exp {
+        LangExp::Channel(channel) => Ok(channel),
+        _ => Err(LangErr::type_error("a channel", exp))
+    }
+}
+
+fn parse_map(exp: &LangExp) -> Result<&LangMap, LangErr> {
+    match exp {
+        LangExp::Map(map) => Ok(map),
+        _ => Err(LangErr::type_error("a map", exp))
+    }
+}
+
+/// a number usable as a position, i.e. a non-negative whole number
+fn parse_index(exp: &LangExp) -> Result<usize, LangErr> {
+    match exp {
+        LangExp::Int(i) if *i >= 0 => Ok(*i as usize),
+        _ => Err(LangErr::type_error("a non-negative integer", exp))
+    }
+}
+
+/// true when `holds` is satisfied by every adjacent pair, so `(< 1 2 3)` reads as 1 < 2 < 3.
+/// unordered pairs (anything compared with NaN) never satisfy it
+fn ensure_monotonic(args: &[LangExp], holds: fn(Ordering) -> bool) -> Result<LangExp, LangErr> {
+    let nums = parse_list_of_nums(args)?;
+
+    Ok(LangExp::Bool(nums.windows(2).all(|pair| pair[0].compare(pair[1]).is_some_and(holds))))
+}
+
+/// picks the number that compares as `wanted` against all others, as used by `min` and `max`
+fn fold_non_empty(args: &[LangExp], wanted: Ordering) -> Result<LangExp, LangErr> {
+    let nums = parse_list_of_nums(args)?;
+    let res = nums[1..].iter().fold(nums[0], |acc, a| if a.compare(acc) == Some(wanted) { *a } else { acc });
+
+    Ok(res.into_exp())
+}
+
+fn parse_list_of_nums(args: &[LangExp]) -> Result<Vec<Num>, LangErr> {
+    args
+        .iter()
+        .map(Num::from_exp)
+        .collect()
+}
@@ -0,0 +1,127 @@
+use std::fs;
+
+use lisp::{Interpreter, LangExp};
+
+const HELP: &str = "\
+:help          show this list
+:quit          leave the repl
+:env           list the global bindings and the type of each
+:load <file>   evaluate a file in the current session
+:reset         forget every definition and start from the builtins again
+:type <expr>   evaluate an expression and show the type of its value
+:profile on    start counting and timing calls, :profile off stops
+:profile       show the calls profiled so far, :profile clear forgets them
+:width <n>     spread results wider than n columns over several lines
+$1, $2, ...    the results of the expressions evaluated so far, $_ the last one";
+
+/// how results are printed before the value: continuation lines are indented to line up with it
+const RESULT_PREFIX: &str = "// => ";
+
+/// preferences set with commands that last for the rest of the session
+pub struct Settings {
+    /// results longer than this are pretty-printed
+    pub width: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings { width: 80 }
+    }
+}
+
+/// a result as the repl shows it, on one line if it fits in the width and spread over several if not
+pub fn show_result(res: &LangExp, settings: &Settings) -> String {
+    let width = settings.width.saturating_sub(RESULT_PREFIX.len());
+    let indent = format!("\n{}", " ".repeat(RESULT_PREFIX.len()));
+
+    format!("{}{}", RESULT_PREFIX, res.to_pretty_string(width).replace('\n', &indent))
+}
+
+/// what the repl should do after a command
+pub enum Outcome {
+    Continue,
+    /// the environment was reset, so what the repl bound in it is gone too
+    Reset,
+    Quit,
+}
+
+/// runs a `:command` typed at the prompt, `input` being everything after the colon
+pub fn run_command(input: &str, interpreter: &mut Interpreter, settings: &mut Settings) -> Outcome {
+    let input = input.trim();
+    let (name, arg) = match input.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (input, "")
+    };
+
+    match (name, arg) {
+        ("quit" | "q", "") => return Outcome::Quit,
+        ("help" | "h", "") => println!("{}", HELP),
+        ("env", "") => list_env(interpreter),
+        ("reset", "") => {
+            interpreter.reset();
+            println!("// environment reset");
+            return Outcome::Reset;
+        }
+        ("load", path) if !path.is_empty() => load(path, interpreter, settings),
+        ("width", width) => match width.parse() {
+            Ok(width) => {
+                settings.width = width;
+                println!("// results wider than {} columns are pretty-printed", width);
+            }
+            Err(_) => println!("// :width needs a number of columns")
+        },
+        ("profile", "") => print!("{}", interpreter.profile_report()),
+        ("profile", "on") => {
+            interpreter.set_profiling(true);
+            println!("// profiling on");
+        }
+        ("profile", "off") => {
+            interpreter.set_profiling(false);
+            println!("// profiling off");
+        }
+        ("profile", "clear") => {
+            interpreter.clear_profile();
+            println!("// profile cleared");
+        }
+        ("profile", _) => println!("// :profile takes on, off or clear, or nothing to show the report"),
+        ("type", exp) if !exp.is_empty() => match interpreter.eval_str(exp) {
+            Ok(res) => println!("// => {}", res.type_name()),
+            Err(e) => println!("// => {}", e.render(exp))
+        },
+        ("quit" | "q" | "help" | "h" | "env" | "reset", _) => println!("// :{} takes no argument", name),
+        ("load" | "type", _) => println!("// :{} needs an argument, see :help", name),
+        _ => println!("// unknown command :{}, see :help", name)
+    }
+
+    Outcome::Continue
+}
+
+fn list_env(interpreter: &Interpreter) {
+    let env = interpreter.env();
+    let mut names: Vec<_> = env.names().into_iter().map(|name| (name.to_string(), name)).collect();
+    names.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (name, id) in names {
+        if let Some(value) = env.get(id) {
+            println!("{}: {}", name, value.type_name());
+        }
+    }
+}
+
+fn load(path: &str, interpreter: &mut Interpreter, settings: &Settings) {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("// could not read {}: {}", path, e);
+            return;
+        }
+    };
+
+    match interpreter.eval_str(&source) {
+        Ok(res) => println!("{}", show_result(&res, settings)),
+        Err(e) => match e.path() {
+            Some(_) => println!("// => {}", e.render(&source)),
+            None => println!("// => {}:{}", path, e.render(&source))
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::env::LangEnv;
+use crate::eval::{parse_let_bindings, parse_params, split_docstring, SPECIAL_FORMS};
+use crate::exp::{LangExp, Params};
+use crate::list::LangList;
+use crate::stack;
+use crate::symbol::{self, SymbolId};
+
+/// one instruction of the stack machine in `vm`
+pub(crate) enum Op {
+    /// push a value
+    Const(LangExp),
+    /// push the value bound to a symbol
+    Get(SymbolId),
+    /// push the function bound to the head symbol of a call. if it has become a macro since the
+    /// call was compiled, evaluate the whole call with the tree-walker instead, push its value and
+    /// jump past the call
+    GetHead(SymbolId, LangExp, usize),
+    /// pop a value and bind it in the current scope, then push the name like `define` returns it
+    Define(SymbolId),
+    /// pop a value and rebind an existing symbol with it, then push the value again
+    Set(SymbolId),
+    Pop,
+    Jump(usize),
+    /// pop the test value and jump when it is falsy
+    JumpIfFalse(usize),
+    /// jump when the top of the stack is falsy, keeping it as the result; pop it otherwise
+    JumpIfFalseKeep(usize),
+    /// jump when the top of the stack is truthy, keeping it as the result; pop it otherwise
+    JumpIfTrueKeep(usize),
+    /// push a closure over the current scope
+    Lambda(Arc<LambdaTemplate>),
+    /// pop that many values into a fresh vector
+    Vector(usize),
+    /// pop the arguments and the function, then call it. the call form is kept for backtraces
+    Call(usize, LangExp),
+    /// like `Call`, but a lambda replaces the current frame instead of pushing a new one
+    TailCall(usize, LangExp),
+    /// pop one value per name and bind them in a new scope nested in the current one
+    EnterScope(Arc<Vec<SymbolId>>),
+    LeaveScope,
+    /// evaluate a form with the tree-walker, for everything the compiler doesn't handle itself.
+    /// set when the form is in tail position, where a call it makes takes the place of the frame's
+    Fallback(LangExp, bool),
+    Return,
+}
+
+/// compiled code for a top-level form or a lambda body
+pub(crate) struct Chunk {
+    pub(crate) code: Vec<Op>,
+    /// the innermost list each op was compiled from, which errors the op raises are pointed at
+    pub(crate) forms: Vec<Option<LangList>>,
+}
+
+/// the parts of a `lambda` form that every closure created from it shares
+pub(crate) struct LambdaTemplate {
+    pub(crate) params: Arc<Params>,
+    pub(crate) body: Arc<Vec<LangExp>>,
+    pub(crate) doc: Option<Arc<str>>,
+    pub(crate) code: Arc<OnceLock<Arc<Chunk>>>,
+}
+
+/// compiles a single form. `env` is only consulted to tell macro calls from function calls, so it
+/// should be the scope the code is about to run in
+pub(crate) fn compile(exp: &LangExp, env: &LangEnv) -> Chunk {
+    compile_body(std::slice::from_ref(exp), env)
+}
+
+/// compiles a sequence of forms that evaluates to the last one, as in a lambda body
+pub(crate) fn compile_body(forms: &[LangExp], env: &LangEnv) -> Chunk {
+    let mut compiler = Compiler { code: vec![], forms: vec![], form: None, env };
+    compiler.body(forms, true);
+    compiler.emit(Op::Return);
+
+    Chunk { code: compiler.code, forms: compiler.forms }
+}
+
+struct Compiler<'a> {
+    code: Vec<Op>,
+    forms: Vec<Option<LangList>>,
+    /// the innermost list being compiled
+    form: Option<LangList>,
+    env: &'a LangEnv,
+}
+
+impl Compiler<'_> {
+    fn emit(&mut self, op: Op) {
+        self.code.push(op);
+        self.forms.push(self.form.clone());
+    }
+
+    /// emits `exp`; `tail` is set when its value is the result of the whole chunk
+    fn exp(&mut self, exp: &LangExp, tail: bool) {
+        // out of stack, leave the rest of this form to the tree-walker, which reports it as an error
+        if stack::exhausted() {
+            return self.fallback(exp, tail);
+        }
+
+        match exp {
+            LangExp::Symbol(k) => self.emit(Op::Get(*k)),
+
+            LangExp::Vector(items) => {
+                let items = items.borrow();
+
+                for item in items.iter() {
+                    self.exp(item, false);
+                }
+
+                self.emit(Op::Vector(items.len()));
+            }
+
+            LangExp::List(list) if !list.is_empty() => {
+                let outer = self.form.replace(list.clone());
+                self.list(exp, &list[0], &list[1..], tail);
+                self.form = outer;
+            }
+
+            LangExp::Bool(_) | LangExp::Int(_) | LangExp::Number(_) | LangExp::Char(_) | LangExp::Str(_) | LangExp::List(_)
+            | LangExp::Map(_) | LangExp::Record(_) | LangExp::Promise(_) | LangExp::Thread(_) | LangExp::Channel(_)
+            | LangExp::Func(_) | LangExp::Lambda(_) | LangExp::Macro(_) => self.emit(Op::Const(exp.clone()))
+        }
+    }
+
+    fn list(&mut self, exp: &LangExp, head: &LangExp, arg_forms: &[LangExp], tail: bool) {
+        let name = match head {
+            LangExp::Symbol(name) => *name,
+            _ => return self.call(exp, head, arg_forms, tail)
+        };
+
+        // malformed special forms go to the tree-walker too, so both backends report them the same way
+        let compiled = match name {
+            symbol::QUOTE => self.quote(arg_forms),
+            symbol::IF => self.if_form(arg_forms, tail),
+            symbol::COND => self.cond(arg_forms, tail),
+            symbol::BEGIN => {
+                self.body(arg_forms, tail);
+                true
+            }
+            symbol::AND => self.short_circuit(arg_forms, true, tail),
+            symbol::OR => self.short_circuit(arg_forms, false, tail),
+            symbol::LET => self.let_form(arg_forms, tail),
+            symbol::DEFINE => self.define(arg_forms),
+            symbol::SET => self.set(arg_forms),
+            symbol::LAMBDA => self.lambda(arg_forms),
+            symbol::LEXICAL => self.lexical(arg_forms),
+            _ if SPECIAL_FORMS.contains(&name) => false,
+            _ if matches!(self.env.get(name), Some(LangExp::Macro(_))) => false,
+            _ => {
+                self.call(exp, head, arg_forms, tail);
+                true
+            }
+        };
+
+        if !compiled {
+            self.fallback(exp, tail);
+        }
+    }
+
+    fn fallback(&mut self, exp: &LangExp, tail: bool) {
+        self.emit(Op::Fallback(exp.clone(), tail));
+    }
+
+    fn call(&mut self, exp: &LangExp, head: &LangExp, arg_forms: &[LangExp], tail: bool) {
+        // chunks are kept for as long as their lambda, the symbol may be bound to a macro later
+        let to_end = match head {
+            LangExp::Symbol(name) => Some(self.jump(|end| Op::GetHead(*name, exp.clone(), end))),
+            _ => {
+                self.exp(head, false);
+                None
+            }
+        };
+
+        for arg in arg_forms {
+            self.exp(arg, false);
+        }
+
+        let argc = arg_forms.len();
+        self.emit(if tail { Op::TailCall(argc, exp.clone()) } else { Op::Call(argc, exp.clone()) });
+
+        if let Some(at) = to_end {
+            self.patch(at);
+        }
+    }
+
+    /// evaluates every form, keeping only the value of the last one
+    fn body(&mut self, forms: &[LangExp], tail: bool) {
+        match forms.split_last() {
+            Some((last, init)) => {
+                for exp in init {
+                    self.exp(exp, false);
+                    self.emit(Op::Pop);
+                }
+
+                self.exp(last, tail);
+            }
+            None => self.emit(Op::Const(LangExp::nil()))
+        }
+    }
+
+    /// emits a jump whose target is filled in later with `patch`
+    fn jump(&mut self, op: impl FnOnce(usize) -> Op) -> usize {
+        self.emit(op(0));
+        self.code.len() - 1
+    }
+
+    /// points the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+
+        self.code[at] = match &self.code[at] {
+            Op::GetHead(name, call, _) => Op::GetHead(*name, call.clone(), target),
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfFalseKeep(_) => Op::JumpIfFalseKeep(target),
+            Op::JumpIfTrueKeep(_) => Op::JumpIfTrueKeep(target),
+            _ => unreachable!("only jumps are patched")
+        };
+    }
+
+    fn quote(&mut self, arg_forms: &[LangExp]) -> bool {
+        match arg_forms {
+            [exp] => {
+                self.emit(Op::Const(exp.clone()));
+                true
+            }
+            _ => false
+        }
+    }
+
+    fn if_form(&mut self, arg_forms: &[LangExp], tail: bool) -> bool {
+        if arg_forms.len() < 2 || arg_forms.len() > 3 {
+            return false;
+        }
+
+        self.exp(&arg_forms[0], false);
+        let to_else = self.jump(Op::JumpIfFalse);
+        self.exp(&arg_forms[1], tail);
+        let to_end = self.jump(Op::Jump);
+        self.patch(to_else);
+        self.body(&arg_forms[2..], tail);
+        self.patch(to_end);
+
+        true
+    }
+
+    fn cond(&mut self, arg_forms: &[LangExp], tail: bool) -> bool {
+        // clauses without a body return their test value, which is left to the tree-walker
+        let well_formed = arg_forms.iter().all(|clause| matches!(clause, LangExp::List(list) if list.len() > 1));
+
+        if !well_formed {
+            return false;
+        }
+
+        let mut to_end = vec![];
+
+        for clause in arg_forms {
+            let list = match clause {
+                LangExp::List(list) => list,
+                _ => unreachable!("checked above")
+            };
+
+            let is_else = matches!(&list[0], LangExp::Symbol(symbol::ELSE));
+            let to_next = if is_else {
+                None
+            } else {
+                self.exp(&list[0], false);
+                Some(self.jump(Op::JumpIfFalse))
+            };
+
+            self.body(&list[1..], tail);
+            to_end.push(self.jump(Op::Jump));
+
+            match to_next {
+                Some(at) => self.patch(at),
+                None => break
+            }
+        }
+
+        // reached only when no clause matched
+        self.emit(Op::Const(LangExp::nil()));
+
+        for at in to_end {
+            self.patch(at);
+        }
+
+        true
+    }
+
+    /// `and` stops at the first falsy operand, `or` at the first truthy one
+    fn short_circuit(&mut self, arg_forms: &[LangExp], is_and: bool, tail: bool) -> bool {
+        let (last, init) = match arg_forms.split_last() {
+            Some(split) => split,
+            None => {
+                self.emit(Op::Const(LangExp::Bool(is_and)));
+                return true;
+            }
+        };
+
+        let mut to_end = vec![];
+
+        for exp in init {
+            self.exp(exp, false);
+            to_end.push(self.jump(if is_and { Op::JumpIfFalseKeep } else { Op::JumpIfTrueKeep }));
+        }
+
+        self.exp(last, tail);
+
+        for at in to_end {
+            self.patch(at);
+        }
+
+        true
+    }
+
+    fn let_form(&mut self, arg_forms: &[LangExp], tail: bool) -> bool {
+        let (bindings, body) = match parse_let_bindings(arg_forms) {
+            Ok(parsed) => parsed,
+            Err(_) => return false
+        };
+
+        for (_, value) in &bindings {
+            self.exp(value, false);
+        }
+
+        let names = bindings.into_iter().map(|(name, _)| name).collect();
+
+        self.emit(Op::EnterScope(Arc::new(names)));
+        self.body(body, tail);
+        self.emit(Op::LeaveScope);
+
+        true
+    }
+
+    fn define(&mut self, arg_forms: &[LangExp]) -> bool {
+        match arg_forms {
+            // special form names are refused by the tree-walker
+            [LangExp::Symbol(name), value] if !SPECIAL_FORMS.contains(name) => {
+                self.exp(value, false);
+                self.emit(Op::Define(*name));
+                true
+            }
+
+            // the function shorthand, compiled as the lambda it stands for
+            [LangExp::List(signature), body @ ..] if !signature.is_empty() => {
+                let mut lambda = vec![LangExp::Symbol(symbol::LAMBDA), LangExp::list(signature[1..].to_vec())];
+                lambda.extend_from_slice(body);
+
+                self.define(&[signature[0].clone(), LangExp::list(lambda)])
+            }
+
+            _ => false
+        }
+    }
+
+    fn set(&mut self, arg_forms: &[LangExp]) -> bool {
+        match arg_forms {
+            [LangExp::Symbol(name), value] => {
+                self.exp(value, false);
+                self.emit(Op::Set(*name));
+                true
+            }
+            _ => false
+        }
+    }
+
+    /// the optimizer's `(%lexical depth name)` is looked up by name, scopes are walked either way
+    fn lexical(&mut self, arg_forms: &[LangExp]) -> bool {
+        match arg_forms {
+            [LangExp::Int(depth), LangExp::Symbol(name)] if *depth >= 0 => {
+                self.emit(Op::Get(*name));
+                true
+            }
+            _ => false
+        }
+    }
+
+    fn lambda(&mut self, arg_forms: &[LangExp]) -> bool {
+        let (params, body) = match arg_forms {
+            [LangExp::List(params), body @ ..] if !body.is_empty() => (params, body),
+            _ => return false
+        };
+
+        // a malformed parameter list is left to the tree-walker to report
+        match parse_params(params) {
+            Ok(params) => {
+                let (doc, body) = split_docstring(body);
+
+                self.emit(Op::Lambda(Arc::new(LambdaTemplate {
+                    params: Arc::new(params),
+                    body: Arc::new(body.to_vec()),
+                    doc,
+                    code: Arc::new(OnceLock::new()),
+                })));
+                true
+            }
+            Err(_) => false
+        }
+    }
+}
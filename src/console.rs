@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// receives everything `print` and `println` write, see [`Interpreter::set_output`](crate::Interpreter::set_output)
+pub type OutputSink = Box<dyn FnMut(&str) -> io::Result<()> + Send>;
+
+/// hands `read-line` its lines without the newline, `None` at the end of input. see
+/// [`Interpreter::set_input`](crate::Interpreter::set_input)
+pub type InputSource = Box<dyn FnMut() -> io::Result<Option<String>> + Send>;
+
+/// where an interpreter's lisp code prints to and reads from, shared by all its scopes and the
+/// threads they spawn. it sits behind a `Mutex` rather than a `Lock`, so the sink and source only
+/// need to be `Send`
+pub(crate) struct Console {
+    pub(crate) output: OutputSink,
+    pub(crate) input: InputSource,
+}
+
+/// exclusive use of `console`, one thread at a time
+pub(crate) fn lock(console: &Mutex<Console>) -> MutexGuard<'_, Console> {
+    console.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Console {
+    /// the process's stdout and stdin, which is where a new interpreter starts out
+    pub(crate) fn stdio() -> Console {
+        Console { output: Box::new(write_stdout), input: Box::new(read_stdin) }
+    }
+}
+
+/// writes right away, so output isn't held back until the next newline
+fn write_stdout(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()
+}
+
+fn read_stdin() -> io::Result<Option<String>> {
+    let mut line = String::new();
+
+    match io::stdin().read_line(&mut line)? {
+        0 => Ok(None),
+        _ => Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
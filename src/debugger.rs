@@ -0,0 +1,180 @@
+use std::cell::Cell;
+
+use crate::builtins::write_out;
+use crate::console;
+use crate::env::LangEnv;
+use crate::eval::eval;
+use crate::exp::{LangErr, LangExp};
+use crate::reader::{list_span, read_str_with};
+use crate::symbol;
+
+const HELP: &str = "\
+  step (s)       pause again at the next form
+  next (n)       pause at the next form that isn't part of this one
+  continue (c)   run on until the next (break)
+  locals (l)     the local bindings, innermost scope first
+  where (w)      the form evaluation is paused at
+  anything else is evaluated in the paused scope
+";
+
+/// what the debugger does with the next form the tree-walker evaluates. the vm doesn't check in
+/// with the debugger, compiled code runs on to the next `(break)`
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Run,
+    /// pause at any form
+    Step,
+    /// pause at a form no deeper than this, see `DEPTH`
+    Next(isize),
+}
+
+thread_local! {
+    static MODE: Cell<Mode> = const { Cell::new(Mode::Run) };
+    /// how deeply evaluation is nested, relative to the last pause. it is only tracked while
+    /// stepping, evaluations left after the last pause but entered before it count as going up
+    static DEPTH: Cell<isize> = const { Cell::new(0) };
+}
+
+fn tracking() -> bool {
+    MODE.with(|mode| mode.get()) != Mode::Run
+}
+
+/// keeps stepping to one evaluation: none is left over from an earlier one when the guard is
+/// made, and none carries over into the next when it is dropped, which may be another
+/// interpreter's on the same thread
+pub(crate) struct StepGuard;
+
+impl StepGuard {
+    pub(crate) fn enter() -> StepGuard {
+        reset();
+        StepGuard
+    }
+}
+
+impl Drop for StepGuard {
+    fn drop(&mut self) {
+        reset();
+    }
+}
+
+fn reset() {
+    MODE.with(|mode| mode.set(Mode::Run));
+}
+
+/// called by `eval` as it starts on a form
+pub(crate) fn enter() {
+    if tracking() {
+        DEPTH.with(|depth| depth.set(depth.get() + 1));
+    }
+}
+
+/// called by `eval` as it finishes a form
+pub(crate) fn leave() {
+    if tracking() {
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// pauses before `exp` when stepping has arrived at it
+pub(crate) fn check(exp: &LangExp, env: &LangEnv) -> Result<(), LangErr> {
+    let due = match MODE.with(|mode| mode.get()) {
+        Mode::Run => false,
+        Mode::Step => true,
+        Mode::Next(level) => DEPTH.with(|depth| depth.get()) <= level
+    };
+
+    // the optimizer's symbol lookups aren't forms of their own in the source
+    let form = match exp {
+        LangExp::List(list) => !list.is_empty() && list[0] != LangExp::Symbol(symbol::LEXICAL),
+        _ => false
+    };
+
+    if due && form {
+        pause(exp, env)?;
+    }
+
+    Ok(())
+}
+
+/// shows `exp` and runs debugger commands read from the interpreter's input until one of them
+/// resumes evaluation. the end of input continues
+pub(crate) fn pause(exp: &LangExp, env: &LangEnv) -> Result<(), LangErr> {
+    // nothing evaluated from the debugger prompt is stepped through
+    MODE.with(|mode| mode.set(Mode::Run));
+    DEPTH.with(|depth| depth.set(0));
+
+    show_form(exp, env)?;
+
+    loop {
+        write_out(env, "debug > ")?;
+
+        let line = match (console::lock(&env.console).input)() {
+            Ok(Some(line)) => line,
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(LangErr::Reason(format!("debugger: {}", e)))
+        };
+
+        match line.trim() {
+            "step" | "s" => return resume(Mode::Step),
+            "next" | "n" => return resume(Mode::Next(0)),
+            "continue" | "c" => return Ok(()),
+            "locals" | "l" => show_locals(env)?,
+            "where" | "w" => show_form(exp, env)?,
+            "help" | "h" => write_out(env, HELP)?,
+            "" => {}
+            source => {
+                let table = env.readtable.borrow().clone();
+                let res = read_str_with(source, &table).and_then(|forms| {
+                    forms.iter().try_fold(LangExp::nil(), |_, form| eval(form, env))
+                });
+
+                match res {
+                    Ok(value) => write_out(env, &format!("// => {}\n", value))?,
+                    Err(err) => write_out(env, &format!("// => {}\n", err))?
+                }
+            }
+        }
+    }
+}
+
+/// the depth the pause left at 0 is the level of the form paused at, its own evaluation having
+/// been entered already
+fn resume(mode: Mode) -> Result<(), LangErr> {
+    MODE.with(|current| current.set(mode));
+
+    Ok(())
+}
+
+fn show_form(exp: &LangExp, env: &LangEnv) -> Result<(), LangErr> {
+    let at = match exp {
+        LangExp::List(list) => list_span(list).map(|span| format!(" at {}:{}", span.line, span.column)),
+        _ => None
+    };
+
+    write_out(env, &format!("paused{}: {}\n", at.unwrap_or_default(), exp))
+}
+
+fn show_locals(env: &LangEnv) -> Result<(), LangErr> {
+    let mut scope = env.clone();
+    let mut shown = false;
+
+    // the outermost scope holds the globals, which would drown out the locals
+    while let Some(outer) = scope.outer.clone() {
+        // copied out of the scope, the output sink is free to evaluate code of its own
+        let mut bindings: Vec<_> = scope.data.borrow().iter().map(|(name, value)| (*name, value.clone())).collect();
+        bindings.sort_by_key(|(name, _)| name.name());
+
+        for (name, value) in bindings {
+            write_out(env, &format!("  {} = {}\n", name, value))?;
+            shown = true;
+        }
+
+        scope = (*outer).clone();
+    }
+
+    if !shown {
+        write_out(env, "  no local bindings, this is the top level\n")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+/// what the user did at the prompt
+pub enum ReadResult {
+    Line(String),
+    /// ctrl-c, the current input should be discarded
+    Interrupted,
+    /// ctrl-d on an empty line or the end of piped input
+    Eof,
+}
+
+/// a minimal line editor with history, falling back to plain `read_line` when stdin is not a terminal
+pub struct Editor {
+    history: Vec<String>,
+    /// the words tab completes to, sorted
+    completions: Vec<String>,
+    interactive: bool,
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor {
+            history: vec![],
+            completions: vec![],
+            interactive: io::stdin().is_terminal() && io::stdout().is_terminal(),
+        }
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        if self.history.last().map(|last| last.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+        }
+    }
+
+    /// replaces the words tab completes to; `words` must be sorted
+    pub fn set_completions(&mut self, words: Vec<String>) {
+        self.completions = words;
+    }
+
+    pub fn read_line(&mut self, prompt: &str) -> ReadResult {
+        print!("{}", prompt);
+        io::stdout().flush().expect("failed to flush stdout");
+
+        if !self.interactive {
+            return read_plain_line();
+        }
+
+        match RawMode::enable() {
+            Some(_raw) => self.read_edited_line(prompt),
+            None => read_plain_line()
+        }
+    }
+
+    fn read_edited_line(&mut self, prompt: &str) -> ReadResult {
+        let mut line = LineBuffer { prompt, chars: vec![], cursor: 0 };
+
+        // the line being typed is kept as the last entry while browsing history
+        let mut entries = self.history.clone();
+        entries.push(String::new());
+        let mut entry = entries.len() - 1;
+
+        loop {
+            let key = match read_key() {
+                Some(key) => key,
+                None => return ReadResult::Eof
+            };
+
+            match key {
+                Key::Enter => {
+                    print!("\r\n");
+                    return ReadResult::Line(line.chars.iter().collect());
+                }
+
+                Key::Interrupt => {
+                    print!("^C\r\n");
+                    return ReadResult::Interrupted;
+                }
+
+                Key::EndOfFile if line.chars.is_empty() => {
+                    print!("\r\n");
+                    return ReadResult::Eof;
+                }
+
+                Key::EndOfFile | Key::Delete => {
+                    if line.cursor < line.chars.len() {
+                        line.chars.remove(line.cursor);
+                    }
+                }
+
+                Key::Backspace => {
+                    if line.cursor > 0 {
+                        line.cursor -= 1;
+                        line.chars.remove(line.cursor);
+                    }
+                }
+
+                Key::Left => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                Key::Home => line.cursor = 0,
+                Key::End => line.cursor = line.chars.len(),
+
+                Key::Up | Key::Down => {
+                    let next = match key {
+                        Key::Up => entry.checked_sub(1),
+                        _ if entry + 1 < entries.len() => Some(entry + 1),
+                        _ => None
+                    };
+
+                    if let Some(next) = next {
+                        entries[entry] = line.chars.iter().collect();
+                        entry = next;
+                        line.chars = entries[entry].chars().collect();
+                        line.cursor = line.chars.len();
+                    }
+                }
+
+                Key::Char(c) => {
+                    line.chars.insert(line.cursor, c);
+                    line.cursor += 1;
+                }
+
+                Key::Tab => self.complete(&mut line),
+
+                Key::Ignored => {}
+            }
+
+            line.redraw();
+        }
+    }
+
+    /// extends the word before the cursor as far as every completion of it agrees, listing the
+    /// candidates when that doesn't add anything
+    fn complete(&self, line: &mut LineBuffer) {
+        let start = line.chars[..line.cursor]
+            .iter()
+            .rposition(|c| is_delimiter(*c))
+            .map_or(0, |i| i + 1);
+        let prefix: String = line.chars[start..line.cursor].iter().collect();
+
+        if prefix.is_empty() {
+            return;
+        }
+
+        let candidates: Vec<&String> = self.completions.iter().filter(|word| word.starts_with(&prefix)).collect();
+
+        let common = match candidates.split_first() {
+            Some((first, rest)) => rest.iter().fold(first.as_str(), |common, word| common_prefix(common, word)),
+            None => return
+        };
+
+        if common.len() > prefix.len() {
+            for c in common[prefix.len()..].chars() {
+                line.chars.insert(line.cursor, c);
+                line.cursor += 1;
+            }
+        } else if candidates.len() > 1 {
+            let words: Vec<&str> = candidates.iter().map(|word| word.as_str()).collect();
+            print!("\r\n{}\r\n", words.join("  "));
+        }
+    }
+}
+
+/// characters that end a symbol, so completion only looks at the one being typed
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "()[]{}'`,\"".contains(c)
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i);
+
+    &a[..len]
+}
+
+struct LineBuffer<'a> {
+    prompt: &'a str,
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineBuffer<'_> {
+    /// repaints the prompt and the line, then puts the terminal cursor back where the edit cursor is
+    fn redraw(&self) {
+        let text: String = self.chars.iter().collect();
+        print!("\r{}{}\x1b[K", self.prompt, text);
+
+        let back = self.chars.len() - self.cursor;
+        if back > 0 {
+            print!("\x1b[{}D", back);
+        }
+
+        io::stdout().flush().expect("failed to flush stdout");
+    }
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Interrupt,
+    EndOfFile,
+    Ignored,
+}
+
+fn read_byte() -> Option<u8> {
+    let mut buf = [0u8; 1];
+
+    match io::stdin().read(&mut buf) {
+        Ok(1) => Some(buf[0]),
+        _ => None
+    }
+}
+
+/// decodes one keypress, including ansi escape sequences for the arrow keys
+fn read_key() -> Option<Key> {
+    let byte = read_byte()?;
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        3 => Key::Interrupt,
+        4 => Key::EndOfFile,
+        1 => Key::Home,
+        5 => Key::End,
+        8 | 127 => Key::Backspace,
+        27 => read_escape_sequence()?,
+        _ if byte < 32 => Key::Ignored,
+        _ => Key::Char(read_utf8_char(byte)?)
+    };
+
+    Some(key)
+}
+
+fn read_escape_sequence() -> Option<Key> {
+    if read_byte()? != b'[' {
+        return Some(Key::Ignored);
+    }
+
+    let key = match read_byte()? {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'3' if read_byte()? == b'~' => Key::Delete,
+        _ => Key::Ignored
+    };
+
+    Some(key)
+}
+
+fn read_utf8_char(first: u8) -> Option<char> {
+    let len = match first {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1
+    };
+
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        bytes.push(read_byte()?);
+    }
+
+    std::str::from_utf8(&bytes).ok()?.chars().next()
+}
+
+fn read_plain_line() -> ReadResult {
+    let mut line = String::new();
+
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => ReadResult::Eof,
+        Ok(_) => ReadResult::Line(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+}
+
+/// puts the terminal into raw mode through `stty`, restoring the previous settings on drop
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        let saved = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        let status = stty(&["raw", "-echo"])?;
+
+        if status { Some(RawMode { saved }) } else { None }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> Option<bool> {
+    Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .status()
+        .ok()
+        .map(|status| status.success())
+}
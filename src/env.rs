@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::console::Console;
+use crate::exp::LangExp;
+use crate::lock::Lock;
+use crate::profile::Profiler;
+use crate::reader::ReadTable;
+use crate::symbol::SymbolId;
+use crate::testing::Tests;
+use crate::threads::Threads;
+use crate::trace::Tracer;
+
+/// a handle to a scope; clones share the same bindings, so closures see later definitions
+#[derive(Clone)]
+pub struct LangEnv {
+    pub(crate) data: Arc<Lock<HashMap<SymbolId, LangExp>>>,
+    pub(crate) outer: Option<Arc<LangEnv>>,
+    /// files already evaluated by `load` or `require`, shared by every scope of an interpreter
+    pub(crate) loaded: Arc<Lock<HashSet<PathBuf>>>,
+    /// where `print` and `read-line` go, likewise shared
+    pub(crate) console: Arc<Mutex<Console>>,
+    /// the functions whose calls are logged, likewise shared
+    pub(crate) tracer: Arc<Tracer>,
+    /// call counts and times while profiling, likewise shared
+    pub(crate) profiler: Arc<Profiler>,
+    /// the reader macros source evaluated in this interpreter is read with, likewise shared
+    pub(crate) readtable: Arc<Lock<ReadTable>>,
+    /// the tests `deftest` declared, likewise shared
+    pub(crate) tests: Arc<Tests>,
+    /// the threads that could still send on a channel, likewise shared
+    pub(crate) threads: Arc<Threads>,
+}
+
+impl LangEnv {
+    /// creates an empty scope nested inside `outer`
+    pub fn new_child(outer: &LangEnv) -> LangEnv {
+        LangEnv {
+            data: Arc::new(Lock::new(HashMap::new())),
+            outer: Some(Arc::new(outer.clone())),
+            loaded: outer.loaded.clone(),
+            console: outer.console.clone(),
+            tracer: outer.tracer.clone(),
+            profiler: outer.profiler.clone(),
+            readtable: outer.readtable.clone(),
+            tests: outer.tests.clone(),
+            threads: outer.threads.clone(),
+        }
+    }
+
+    /// binds a symbol in this scope, shadowing any outer binding
+    pub fn insert(&self, k: SymbolId, v: LangExp) {
+        self.data.borrow_mut().insert(k, v);
+    }
+
+    /// rebinds an existing symbol in the nearest scope that defines it, returning false when it is unbound
+    pub fn set(&self, k: SymbolId, v: LangExp) -> bool {
+        if let Some(slot) = self.data.borrow_mut().get_mut(&k) {
+            *slot = v;
+            return true;
+        }
+
+        match &self.outer {
+            Some(outer) => outer.set(k, v),
+            None => false
+        }
+    }
+
+    /// the symbols bound in this scope itself, not counting outer scopes
+    pub fn names(&self) -> Vec<SymbolId> {
+        self.data.borrow().keys().copied().collect()
+    }
+
+    /// the outermost scope this one is nested in, where top-level definitions live
+    pub fn global(&self) -> LangEnv {
+        match &self.outer {
+            Some(outer) => outer.global(),
+            None => self.clone()
+        }
+    }
+
+    /// looks a symbol up in this scope, then walks the chain of outer scopes
+    pub fn get(&self, k: SymbolId) -> Option<LangExp> {
+        match self.data.borrow().get(&k) {
+            Some(exp) => Some(exp.clone()),
+            None => match &self.outer {
+                Some(outer) => outer.get(k),
+                None => None
+            }
+        }
+    }
+}
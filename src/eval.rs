@@ -0,0 +1,1340 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+use crate::debugger;
+use crate::env::LangEnv;
+use crate::builtins::{native_doc, write_out};
+use crate::exp::{LangErr, LangExp, LangLambda, LangRecord, Params, Promise, RecordType};
+use crate::interpreter::eval_source;
+use crate::limits::{self, Level};
+use crate::lock::Lock;
+use crate::profile::CallTimer;
+use crate::stack;
+use crate::symbol::{self, SymbolId};
+use crate::trace::CallSite;
+
+/// the outcome of one evaluation step: either a finished value, or a form in tail position
+/// that `eval` should continue with in place of recursing
+enum Tail {
+    Done(LangExp),
+    Eval(LangExp, LangEnv),
+    /// the body of a called lambda and the scope of its arguments: the call goes a level deeper,
+    /// unless it takes the place of the one running, and errors in it are traced through it
+    Enter(Arc<Vec<LangExp>>, LangEnv, CallSite),
+    /// the values of a `recur`, which only the enclosing `loop` takes, and only from the tail of its body
+    Recur(Vec<LangExp>),
+}
+
+/// how evaluating the last form of a `loop` body finished
+enum Iteration {
+    Finished(LangExp),
+    Recur(Vec<LangExp>),
+}
+
+/// the names `eval_built_in_form` dispatches on, so the bytecode compiler knows which heads are not calls
+pub(crate) const SPECIAL_FORMS: &[SymbolId] = &[
+    symbol::QUOTE, symbol::QUASIQUOTE, symbol::IF, symbol::COND, symbol::BEGIN, symbol::AND, symbol::OR,
+    symbol::LET, symbol::LET_STAR, symbol::LETREC, symbol::DEFINE, symbol::SET, symbol::LAMBDA,
+    symbol::DEFMACRO, symbol::MACROEXPAND, symbol::MACROEXPAND_1, symbol::LOAD, symbol::REQUIRE, symbol::TRY,
+    symbol::DOC, symbol::MATCH, symbol::DEFSTRUCT, symbol::DELAY, symbol::WHILE, symbol::DOTIMES, symbol::LOOP,
+    symbol::RECUR, symbol::TIME, symbol::BREAK, symbol::LEXICAL, symbol::ASSERT, symbol::ASSERT_EQ,
+    symbol::DEFTEST,
+];
+
+/// special forms get their arguments unevaluated, so they are dispatched before the head is evaluated
+fn eval_built_in_form(exp: &LangExp, arg_forms: &[LangExp], env: &LangEnv) -> Option<Result<Tail, LangErr>> {
+    match exp {
+        LangExp::Symbol(s) => special_form(*s).map(|form| form(arg_forms, env)),
+        _ => None
+    }
+}
+
+/// evaluates the arguments of a special form
+type SpecialForm = fn(&[LangExp], &LangEnv) -> Result<Tail, LangErr>;
+
+/// the special form `name` names, if any. looked up in a table rather than called from the match
+/// so the frame of `eval_built_in_form`, which every call nests in, stays small in debug builds
+fn special_form(name: SymbolId) -> Option<SpecialForm> {
+    let form: SpecialForm = match name {
+        symbol::QUOTE => |args, _| eval_quote_args(args).map(Tail::Done),
+        symbol::QUASIQUOTE => |args, env| eval_quasiquote_args(args, env).map(Tail::Done),
+        symbol::IF => eval_if_args,
+        symbol::COND => eval_cond_args,
+        symbol::BEGIN => eval_body_tail,
+        symbol::AND => eval_and_args,
+        symbol::OR => eval_or_args,
+        symbol::LET => eval_let_args,
+        symbol::LET_STAR => eval_let_star_args,
+        symbol::LETREC => eval_letrec_args,
+        symbol::DEFINE => |args, env| eval_define_args(args, env).map(Tail::Done),
+        symbol::SET => |args, env| eval_set_args(args, env).map(Tail::Done),
+        symbol::LAMBDA => |args, env| eval_lambda_args(args, env).map(Tail::Done),
+        symbol::DEFMACRO => |args, env| eval_defmacro_args(args, env).map(Tail::Done),
+        symbol::MACROEXPAND => |args, env| eval_macroexpand_args(args, env, true).map(Tail::Done),
+        symbol::MACROEXPAND_1 => |args, env| eval_macroexpand_args(args, env, false).map(Tail::Done),
+        symbol::LOAD => |args, env| eval_load_args(args, env, false).map(Tail::Done),
+        symbol::REQUIRE => |args, env| eval_load_args(args, env, true).map(Tail::Done),
+        symbol::TRY => eval_try_args,
+        symbol::DOC => |args, env| eval_doc_args(args, env).map(Tail::Done),
+        symbol::MATCH => eval_match_args,
+        symbol::DEFSTRUCT => |args, env| eval_defstruct_args(args, env).map(Tail::Done),
+        symbol::DELAY => |args, env| eval_delay_args(args, env).map(Tail::Done),
+        symbol::WHILE => |args, env| eval_while_args(args, env).map(Tail::Done),
+        symbol::DOTIMES => |args, env| eval_dotimes_args(args, env).map(Tail::Done),
+        symbol::LOOP => |args, env| eval_loop_args(args, env).map(Tail::Done),
+        symbol::RECUR => eval_recur_args,
+        symbol::TIME => |args, env| eval_time_args(args, env).map(Tail::Done),
+        symbol::BREAK => |args, env| eval_break_args(args, env).map(Tail::Done),
+        symbol::LEXICAL => |args, env| eval_lexical_args(args, env).map(Tail::Done),
+        symbol::ASSERT => |args, env| eval_assert_args(args, env).map(Tail::Done),
+        symbol::ASSERT_EQ => |args, env| eval_assert_eq_args(args, env).map(Tail::Done),
+        symbol::DEFTEST => |args, env| eval_deftest_args(args, env).map(Tail::Done),
+        _ => return None
+    };
+
+    Some(form)
+}
+
+/// `(quote exp)` returns its argument as data, without evaluating it
+fn eval_quote_args(arg_forms: &[LangExp]) -> Result<LangExp, LangErr> {
+    match arg_forms {
+        [exp] => Ok(exp.clone()),
+        _ => Err(LangErr::Reason("quote expects exactly one form".to_string()))
+    }
+}
+
+fn eval_quasiquote_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    match arg_forms {
+        [exp] => eval_quasiquote(exp, 0, env),
+        _ => Err(LangErr::Reason("quasiquote expects exactly one form".to_string()))
+    }
+}
+
+/// returns `(head exp)` when `list` is exactly that shape
+fn match_wrapped(list: &[LangExp], head: SymbolId) -> Option<&LangExp> {
+    match list {
+        [LangExp::Symbol(s), exp] if *s == head => Some(exp),
+        _ => None
+    }
+}
+
+/// copies a quasiquote template, evaluating `unquote` and splicing `unquote-splicing` forms.
+/// `depth` counts enclosing nested quasiquotes, only forms at depth 0 are evaluated
+fn eval_quasiquote(template: &LangExp, depth: usize, env: &LangEnv) -> Result<LangExp, LangErr> {
+    stack::check()?;
+
+    let list = match template {
+        LangExp::List(list) => list,
+        _ => return Ok(template.clone())
+    };
+
+    if let Some(exp) = match_wrapped(list, symbol::UNQUOTE) {
+        return if depth == 0 {
+            eval(exp, env)
+        } else {
+            Ok(LangExp::list(vec![list[0].clone(), eval_quasiquote(exp, depth - 1, env)?]))
+        };
+    }
+
+    if let Some(exp) = match_wrapped(list, symbol::QUASIQUOTE) {
+        return Ok(LangExp::list(vec![list[0].clone(), eval_quasiquote(exp, depth + 1, env)?]));
+    }
+
+    let mut res = vec![];
+
+    for item in list.iter() {
+        let spliced = match item {
+            LangExp::List(inner) if depth == 0 => match_wrapped(inner, symbol::UNQUOTE_SPLICING),
+            _ => None
+        };
+
+        match spliced {
+            Some(exp) => match &eval(exp, env)? {
+                LangExp::List(items) => res.extend(items.iter().cloned()),
+                _ => return Err(LangErr::Reason("unquote-splicing expects a list".to_string()))
+            },
+            None => res.push(eval_quasiquote(item, depth, env)?)
+        }
+    }
+
+    // splicing can build lists as big as the builtins can
+    let res = LangExp::list(res);
+    limits::check_size(&res)?;
+
+    Ok(res)
+}
+
+/// only `false` is falsy, every other value counts as true
+pub(crate) fn is_truthy(exp: &LangExp) -> bool {
+    !matches!(exp, LangExp::Bool(false))
+}
+
+/// evaluates all but the last form, which is left in tail position; an empty body yields `()`
+fn eval_body_tail(forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    match forms.split_last() {
+        Some((last, init)) => {
+            for exp in init {
+                eval(exp, env)?;
+            }
+
+            Ok(Tail::Eval(last.clone(), env.clone()))
+        }
+        None => Ok(Tail::Done(LangExp::nil()))
+    }
+}
+
+/// evaluates every form, returning the value of the last one
+fn eval_body(forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    eval_body_tail(forms, env).and_then(finish)
+}
+
+/// `(if test then else)`, only the chosen branch is evaluated; a missing else yields `()`
+fn eval_if_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    if arg_forms.len() < 2 || arg_forms.len() > 3 {
+        return Err(LangErr::Reason("if expects a test, a then form and an optional else form".to_string()));
+    }
+
+    let test_eval = eval(&arg_forms[0], env)?;
+
+    if is_truthy(&test_eval) {
+        eval_body_tail(&arg_forms[1..2], env)
+    } else {
+        eval_body_tail(&arg_forms[2..], env)
+    }
+}
+
+/// `(and a b...)` stops at the first falsy operand and returns it, otherwise the last operand is
+/// evaluated in tail position; `(and)` is true
+fn eval_and_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (last, init) = match arg_forms.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Done(LangExp::Bool(true)))
+    };
+
+    for exp in init {
+        let res = eval(exp, env)?;
+
+        if !is_truthy(&res) {
+            return Ok(Tail::Done(res));
+        }
+    }
+
+    Ok(Tail::Eval(last.clone(), env.clone()))
+}
+
+/// `(or a b...)` stops at the first truthy operand and returns it; `(or)` is false
+fn eval_or_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (last, init) = match arg_forms.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Done(LangExp::Bool(false)))
+    };
+
+    for exp in init {
+        let res = eval(exp, env)?;
+
+        if is_truthy(&res) {
+            return Ok(Tail::Done(res));
+        }
+    }
+
+    Ok(Tail::Eval(last.clone(), env.clone()))
+}
+
+/// `(cond (test body...) ... (else body...))` evaluates the body of the first clause whose test holds
+fn eval_cond_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    for clause in arg_forms {
+        let (test, body) = match clause {
+            LangExp::List(list) => list
+                .split_first()
+                .ok_or(LangErr::Reason("expected a test in cond clause".to_string()))?,
+            _ => return Err(LangErr::Reason("expected cond clauses to be lists".to_string()))
+        };
+
+        let test_eval = match test {
+            LangExp::Symbol(symbol::ELSE) => LangExp::Bool(true),
+            _ => eval(test, env)?
+        };
+
+        if is_truthy(&test_eval) {
+            return if body.is_empty() { Ok(Tail::Done(test_eval)) } else { eval_body_tail(body, env) };
+        }
+    }
+
+    Ok(Tail::Done(LangExp::nil()))
+}
+
+/// `(name value)` pairs of a let form, values still unevaluated
+type LetBindings<'a> = Vec<(SymbolId, &'a LangExp)>;
+
+/// splits `(((name value)...) body...)` into the binding pairs and the body
+pub(crate) fn parse_let_bindings(arg_forms: &[LangExp]) -> Result<(LetBindings<'_>, &[LangExp]), LangErr> {
+    let (bindings_exp, body) = arg_forms
+        .split_first()
+        .ok_or(LangErr::Reason("expected a binding list in let".to_string()))?;
+
+    let bindings = match bindings_exp {
+        LangExp::List(list) => list
+            .iter()
+            .map(|binding| match binding {
+                LangExp::List(pair) => match &pair[..] {
+                    [LangExp::Symbol(name), value] => Ok((*name, value)),
+                    _ => Err(LangErr::Reason("expected let bindings of the form (name value)".to_string()))
+                },
+                _ => Err(LangErr::Reason("expected let bindings of the form (name value)".to_string()))
+            })
+            .collect::<Result<LetBindings, LangErr>>()?,
+        _ => return Err(LangErr::Reason("expected a binding list in let".to_string()))
+    };
+
+    Ok((bindings, body))
+}
+
+/// `(while test body...)` evaluates the body for as long as the test is truthy, returning `()`
+fn eval_while_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (test, body) = arg_forms
+        .split_first()
+        .ok_or(LangErr::Reason("while expects a test and a body".to_string()))?;
+
+    while is_truthy(&eval(test, env)?) {
+        eval_body(body, env)?;
+    }
+
+    Ok(LangExp::nil())
+}
+
+/// `(dotimes (i n) body...)` evaluates the body with `i` bound to 0, 1... up to `n` - 1, returning `()`
+fn eval_dotimes_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (name, count, body) = match arg_forms {
+        [LangExp::List(spec), body @ ..] => match &spec[..] {
+            [LangExp::Symbol(name), count] => (*name, count, body),
+            _ => return Err(LangErr::Reason("dotimes expects (name count) followed by a body".to_string()))
+        },
+        _ => return Err(LangErr::Reason("dotimes expects (name count) followed by a body".to_string()))
+    };
+
+    let count = match eval(count, env)? {
+        LangExp::Int(n) => n,
+        other => return Err(LangErr::type_error("an integer", &other))
+    };
+
+    for i in 0..count {
+        let inner = LangEnv::new_child(env);
+        inner.insert(name, LangExp::Int(i));
+
+        eval_body(body, &inner)?;
+    }
+
+    Ok(LangExp::nil())
+}
+
+/// `(loop ((name init)...) body...)` binds like `let` and evaluates the body. a `(recur values...)`
+/// in tail position of the body starts it over with the names bound to the new values instead, so
+/// looping this way never grows the stack. a `recur` anywhere else is an error
+fn eval_loop_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (bindings, body) = parse_let_bindings(arg_forms).map_err(|_| {
+        LangErr::Reason("loop expects a list of (name value) bindings followed by a body".to_string())
+    })?;
+
+    let mut values = bindings
+        .iter()
+        .map(|(_, value)| eval(value, env))
+        .collect::<Result<Vec<LangExp>, LangErr>>()?;
+
+    loop {
+        let inner = LangEnv::new_child(env);
+
+        for ((name, _), value) in bindings.iter().zip(values) {
+            inner.insert(*name, value);
+        }
+
+        let last = match body.split_last() {
+            Some((last, init)) => {
+                for exp in init {
+                    eval(exp, &inner)?;
+                }
+                last
+            }
+            None => return Ok(LangExp::nil())
+        };
+
+        match eval_nested(last, &inner, true)? {
+            Iteration::Finished(res) => return Ok(res),
+            Iteration::Recur(next) if next.len() == bindings.len() => values = next,
+            Iteration::Recur(next) => return Err(LangErr::arity_error("recur", bindings.len(), next.len()))
+        }
+    }
+}
+
+/// `(recur values...)` hands the values to the enclosing `loop`, see `eval_loop_args`
+fn eval_recur_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let values = arg_forms
+        .iter()
+        .map(|x| eval(x, env))
+        .collect::<Result<Vec<LangExp>, LangErr>>()?;
+
+    Ok(Tail::Recur(values))
+}
+
+fn misplaced_recur() -> LangErr {
+    LangErr::Reason("recur used outside the tail position of a loop".to_string())
+}
+
+/// `(let ((x 1) (y 2)) body...)` evaluates every value in the enclosing scope before binding any
+fn eval_let_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (bindings, body) = parse_let_bindings(arg_forms)?;
+
+    let values = bindings
+        .iter()
+        .map(|(_, value)| eval(value, env))
+        .collect::<Result<Vec<LangExp>, LangErr>>()?;
+
+    let inner = LangEnv::new_child(env);
+
+    for ((name, _), value) in bindings.into_iter().zip(values) {
+        inner.insert(name, value);
+    }
+
+    eval_body_tail(body, &inner)
+}
+
+/// `let*` binds one by one, so each value can see the bindings before it
+fn eval_let_star_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (bindings, body) = parse_let_bindings(arg_forms)?;
+    let mut inner = env.clone();
+
+    for (name, value) in bindings {
+        let value = eval(value, &inner)?;
+        inner = LangEnv::new_child(&inner);
+        inner.insert(name, value);
+    }
+
+    eval_body_tail(body, &LangEnv::new_child(&inner))
+}
+
+/// `letrec` evaluates the values inside the new scope, so local functions can refer to each other
+fn eval_letrec_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (bindings, body) = parse_let_bindings(arg_forms)?;
+    let inner = LangEnv::new_child(env);
+
+    for (name, value) in bindings {
+        let value = eval(value, &inner)?;
+        inner.insert(name, value);
+    }
+
+    eval_body_tail(body, &inner)
+}
+
+/// `(define name value)` binds in the current scope, `(define name "doc" value)` also documents the
+/// function or macro it binds, and `(define (name params...) body)` is sugar for a lambda
+fn eval_define_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let first_form = arg_forms
+        .first()
+        .ok_or(LangErr::Reason("expected first form in define".to_string()))?;
+
+    match first_form {
+        LangExp::Symbol(name) => {
+            let (doc, value) = match &arg_forms[1..] {
+                [value] => (None, value),
+                [LangExp::Str(doc), value] => (Some(doc.clone()), value),
+                _ => return Err(LangErr::Reason("define expects a name, an optional docstring and a value".to_string()))
+            };
+
+            // the binding would never be looked up, the special form is dispatched on first
+            if SPECIAL_FORMS.contains(name) {
+                return Err(LangErr::Reason(format!("cannot define {}, it names a special form", name)));
+            }
+
+            let value = documented(named(eval(value, env)?, *name), doc)?;
+            env.insert(*name, value);
+
+            Ok(first_form.clone())
+        }
+
+        LangExp::List(signature) => {
+            let (name, params) = signature
+                .split_first()
+                .ok_or(LangErr::Reason("expected a function name in define".to_string()))?;
+
+            let mut lambda = vec![LangExp::Symbol(symbol::LAMBDA), LangExp::list(params.to_vec())];
+            lambda.extend_from_slice(&arg_forms[1..]);
+
+            eval_define_args(&[name.clone(), LangExp::list(lambda)], env)
+        }
+
+        _ => Err(LangErr::Reason("expected a symbol or a signature as the first form in define".to_string()))
+    }
+}
+
+/// `(set! name value)` updates the binding `define` or a binding form created, wherever in the scope chain it is
+fn eval_set_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    match arg_forms {
+        [LangExp::Symbol(name), value] => {
+            let value = eval(value, env)?;
+
+            if env.set(*name, value.clone()) {
+                Ok(value)
+            } else {
+                Err(LangErr::UnboundSymbol(name.to_string()))
+            }
+        }
+        _ => Err(LangErr::Reason("set! expects a symbol and a value".to_string()))
+    }
+}
+
+/// `(lambda (params...) body...)` captures the current scope
+fn eval_lambda_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (params_exp, body) = arg_forms
+        .split_first()
+        .ok_or(LangErr::Reason("expected a parameter list in lambda".to_string()))?;
+
+    make_lambda(params_exp, body, env).map(|lambda| LangExp::Lambda(Arc::new(lambda)))
+}
+
+/// `(defmacro name (params...) body...)` binds a macro, which receives its argument forms unevaluated
+/// and returns the form to evaluate in their place
+fn eval_defmacro_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    match arg_forms {
+        [LangExp::Symbol(name), params_exp, body @ ..] => {
+            let lambda = make_lambda(params_exp, body, env)?;
+            env.insert(*name, LangExp::Macro(Arc::new(LangLambda { name: Some(*name), ..lambda })));
+
+            Ok(arg_forms[0].clone())
+        }
+        _ => Err(LangErr::Reason("defmacro expects a name, a parameter list and a body".to_string()))
+    }
+}
+
+/// the expansion of `form` if its head names a macro in `env`
+pub(crate) fn expand_once(form: &LangExp, env: &LangEnv) -> Result<Option<LangExp>, LangErr> {
+    let (head, arg_forms) = match form {
+        LangExp::List(list) if !list.is_empty() => (&list[0], &list[1..]),
+        _ => return Ok(None)
+    };
+
+    match head {
+        LangExp::Symbol(k) => match &env.get(*k) {
+            Some(LangExp::Macro(lambda)) => expand_macro(lambda, arg_forms).map(Some),
+            _ => Ok(None)
+        },
+        _ => Ok(None)
+    }
+}
+
+/// runs the macro body with the forms bound to its parameters
+fn expand_macro(lambda: &LangLambda, arg_forms: &[LangExp]) -> Result<LangExp, LangErr> {
+    let inner = bind_lambda_args(lambda, arg_forms)?;
+
+    finish(eval_body_tail(&lambda.body, &inner)?)
+}
+
+/// `(macroexpand form)` evaluates its argument to a form and expands it until its head is no longer a
+/// macro, `macroexpand-1` expands just once
+fn eval_macroexpand_args(arg_forms: &[LangExp], env: &LangEnv, repeat: bool) -> Result<LangExp, LangErr> {
+    let mut form = match arg_forms {
+        [exp] => eval(exp, env)?,
+        _ => return Err(LangErr::Reason("macroexpand expects exactly one form".to_string()))
+    };
+
+    while let Some(expanded) = expand_once(&form, env)? {
+        form = expanded;
+
+        if !repeat {
+            break;
+        }
+    }
+
+    Ok(form)
+}
+
+/// `(load "path")` evaluates every form of a file into the current environment and returns the last
+/// value. `(require "path")` does the same unless the file was loaded before, returning whether it
+/// evaluated anything. relative paths are resolved against the working directory
+fn eval_load_args(arg_forms: &[LangExp], env: &LangEnv, once: bool) -> Result<LangExp, LangErr> {
+    let name = if once { "require" } else { "load" };
+
+    let path = match arg_forms {
+        [exp] => match &eval(exp, env)? {
+            LangExp::Str(path) => path.to_string(),
+            other => return Err(LangErr::type_error("a string", other))
+        },
+        _ => return Err(LangErr::Reason(format!("{} expects exactly one path", name)))
+    };
+
+    limits::check_io(name)?;
+
+    let source = fs::read_to_string(&path)
+        .map_err(|e| LangErr::Reason(format!("{}: cannot read {}: {}", name, path, e)))?;
+
+    // the same file reached through different relative paths should only be required once
+    let key = Path::new(&path).canonicalize().unwrap_or_else(|_| Path::new(&path).to_path_buf());
+    let first_time = env.loaded.borrow_mut().insert(key.clone());
+
+    if once && !first_time {
+        return Ok(LangExp::Bool(false));
+    }
+
+    let res = match eval_source(&source, env) {
+        Ok(res) => res,
+        Err(e) => {
+            // a file that failed partway through hasn't been loaded, requiring it again tries again
+            if first_time {
+                env.loaded.borrow_mut().remove(&key);
+            }
+
+            return Err(e.in_file(&path, &source));
+        }
+    };
+
+    Ok(if once { LangExp::Bool(true) } else { res })
+}
+
+/// `(try body... (catch e handler...))` evaluates the body, and if it fails evaluates the handler
+/// with `e` bound to the raised value. errors from the interpreter itself, such as type errors,
+/// are caught as their message
+fn eval_try_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (catch, body) = match arg_forms.split_last() {
+        Some((LangExp::List(catch), body)) if matches!(catch.first(), Some(LangExp::Symbol(symbol::CATCH))) => (catch, body),
+        _ => return Err(LangErr::Reason("try expects a body followed by a (catch name handler...) clause".to_string()))
+    };
+
+    let (name, handler) = match &catch[1..] {
+        [LangExp::Symbol(name), handler @ ..] => (*name, handler),
+        _ => return Err(LangErr::Reason("catch expects a name for the error and a handler".to_string()))
+    };
+
+    let err = match eval_body(body, env) {
+        Ok(res) => return Ok(Tail::Done(res)),
+        // catching these would let the code keep going past them
+        Err(err) if matches!(err.inner(), LangErr::LimitExceeded(_) | LangErr::Exit(_)) => return Err(err),
+        Err(err) => err
+    };
+
+    let condition = match err.inner() {
+        LangErr::Raised(payload) => payload.clone(),
+        other => LangExp::Str(other.to_string().into())
+    };
+
+    let inner = LangEnv::new_child(env);
+    inner.insert(name, condition);
+
+    eval_body_tail(handler, &inner)
+}
+
+/// `(match exp (pattern body...)...)` evaluates the body of the first clause whose pattern matches
+/// the value of `exp`, with the names the pattern binds in scope. see `match_pattern` for the patterns
+fn eval_match_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let (exp, clauses) = arg_forms
+        .split_first()
+        .ok_or(LangErr::Reason("match expects a value followed by (pattern body...) clauses".to_string()))?;
+
+    let value = eval(exp, env)?;
+
+    for clause in clauses {
+        let (pattern, body) = match clause {
+            LangExp::List(list) if !list.is_empty() => (&list[0], &list[1..]),
+            _ => return Err(LangErr::Reason("match clauses must look like (pattern body...)".to_string()))
+        };
+
+        let mut bindings = vec![];
+
+        if match_pattern(pattern, &value, env, &mut bindings)? {
+            let inner = LangEnv::new_child(env);
+
+            for (name, value) in bindings {
+                inner.insert(name, value);
+            }
+
+            return eval_body_tail(body, &inner);
+        }
+    }
+
+    Err(LangErr::Reason(format!("no match clause matches {}", value)))
+}
+
+/// whether `value` has the shape of `pattern`, collecting the names it binds on the way:
+///
+/// - `_` matches anything, any other symbol matches anything and binds it
+/// - numbers, strings, booleans, `()` and quoted forms match values equal to them
+/// - `(cons head tail)` matches a non-empty list, `(list items...)` a list of exactly that many
+///   items and `[items...]` a vector of them, with the parts matched against the sub-patterns
+/// - `(? pred)` matches when calling `pred` on the value returns a truthy value, and `(? pred pattern)`
+///   also requires the value to match `pattern`
+fn match_pattern(pattern: &LangExp, value: &LangExp, env: &LangEnv, bindings: &mut Vec<(SymbolId, LangExp)>) -> Result<bool, LangErr> {
+    let list = match pattern {
+        LangExp::Symbol(symbol::WILDCARD) => return Ok(true),
+
+        LangExp::Symbol(name) => {
+            bindings.push((*name, value.clone()));
+            return Ok(true);
+        }
+
+        LangExp::Vector(items) => return match value {
+            // copied out of the locks, a `(? pred)` guard may write to either vector
+            LangExp::Vector(values) => {
+                let (items, values) = (items.borrow().clone(), values.borrow().clone());
+                match_all(&items, &values, env, bindings)
+            }
+            _ => Ok(false)
+        },
+
+        LangExp::List(list) if !list.is_empty() => list,
+
+        LangExp::Bool(_) | LangExp::Int(_) | LangExp::Number(_) | LangExp::Str(_) | LangExp::List(_) =>
+            return Ok(pattern == value),
+
+        _ => return Err(LangErr::Reason(format!("{} is not a valid pattern", pattern)))
+    };
+
+    match (&list[0], &list[1..]) {
+        (LangExp::Symbol(symbol::QUOTE), [datum]) => Ok(datum == value),
+
+        (LangExp::Symbol(symbol::CONS), [head, tail]) => match value {
+            LangExp::List(values) => match values.rest() {
+                Some(rest) => Ok(
+                    match_pattern(head, &values[0], env, bindings)? && match_pattern(tail, &LangExp::List(rest), env, bindings)?
+                ),
+                None => Ok(false)
+            },
+            _ => Ok(false)
+        },
+
+        (LangExp::Symbol(symbol::LIST), items) => match value {
+            LangExp::List(values) => match_all(items, values, env, bindings),
+            _ => Ok(false)
+        },
+
+        (LangExp::Symbol(symbol::PREDICATE), [pred, rest @ ..]) if rest.len() <= 1 => {
+            if !is_truthy(&apply(&eval(pred, env)?, std::slice::from_ref(value), env)?) {
+                return Ok(false);
+            }
+
+            match rest {
+                [pattern] => match_pattern(pattern, value, env, bindings),
+                _ => Ok(true)
+            }
+        }
+
+        _ => Err(LangErr::Reason(format!("{} is not a valid pattern", pattern)))
+    }
+}
+
+/// matches the values one to one against the patterns, failing when their numbers differ
+fn match_all(patterns: &[LangExp], values: &[LangExp], env: &LangEnv, bindings: &mut Vec<(SymbolId, LangExp)>) -> Result<bool, LangErr> {
+    if patterns.len() != values.len() {
+        return Ok(false);
+    }
+
+    for (pattern, value) in patterns.iter().zip(values) {
+        if !match_pattern(pattern, value, env, bindings)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// `(defstruct name fields...)` declares a record type and defines `make-name` taking the fields in
+/// order, the predicate `name?`, and `name-field` and `set-name-field!` for every field
+fn eval_defstruct_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let names = arg_forms
+        .iter()
+        .map(|form| match form {
+            LangExp::Symbol(name) => Ok(*name),
+            _ => Err(LangErr::Reason("defstruct expects a name followed by field names".to_string()))
+        })
+        .collect::<Result<Vec<SymbolId>, LangErr>>()?;
+
+    let (name, fields) = names
+        .split_first()
+        .ok_or(LangErr::Reason("defstruct expects a name followed by field names".to_string()))?;
+
+    // the generated functions are lambdas around the record builtins, which find the type
+    // through this instance quoted into their bodies
+    let kind = Arc::new(RecordType { name: *name, fields: fields.to_vec() });
+    let prototype = LangExp::list(vec![
+        LangExp::Symbol(symbol::QUOTE),
+        LangExp::Record(Arc::new(LangRecord { kind, fields: Lock::new(vec![]) })),
+    ]);
+
+    let sym = |name: String| LangExp::Symbol(SymbolId::intern(&name));
+    let define = |function: String, params: Vec<LangExp>, builtin: &str, args: Vec<LangExp>| -> Result<(), LangErr> {
+        let mut call = vec![sym(builtin.to_string()), prototype.clone()];
+        call.extend(args);
+
+        let lambda = make_lambda(&LangExp::list(params), &[LangExp::list(call)], env)?;
+        let function = SymbolId::intern(&function);
+        env.insert(function, named(LangExp::Lambda(Arc::new(lambda)), function));
+
+        Ok(())
+    };
+
+    let params: Vec<LangExp> = fields.iter().map(|field| LangExp::Symbol(*field)).collect();
+    define(format!("make-{}", name), params.clone(), "%record-new", params)?;
+    define(format!("{}?", name), vec![sym("x".to_string())], "%record-is?", vec![sym("x".to_string())])?;
+
+    for (i, field) in fields.iter().enumerate() {
+        let index = LangExp::Int(i as i64);
+        let record = sym("record".to_string());
+        let value = sym("value".to_string());
+
+        define(format!("{}-{}", name, field), vec![record.clone()], "%record-get", vec![index.clone(), record.clone()])?;
+        define(format!("set-{}-{}!", name, field), vec![record.clone(), value.clone()], "%record-set!", vec![index, record, value])?;
+    }
+
+    Ok(arg_forms[0].clone())
+}
+
+/// `(delay exp)` returns a promise to evaluate `exp` in the current scope once `force` needs it
+fn eval_delay_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    match arg_forms {
+        [exp] => Ok(LangExp::Promise(Arc::new(Lock::new(Promise::Pending(exp.clone(), env.clone()))))),
+        _ => Err(LangErr::Reason("delay expects exactly one form".to_string()))
+    }
+}
+
+/// `(time exp)` evaluates `exp` and returns its value, printing how long that took
+fn eval_time_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let exp = match arg_forms {
+        [exp] => exp,
+        _ => return Err(LangErr::Reason("time expects exactly one form".to_string()))
+    };
+
+    let start = Instant::now();
+    let res = eval(exp, env)?;
+
+    write_out(env, &format!("elapsed time: {:.3} ms\n", start.elapsed().as_secs_f64() * 1000.0))?;
+
+    Ok(res)
+}
+
+/// `(break)` pauses in the debugger, which reads commands from the interpreter's input. see
+/// `debugger::pause`
+fn eval_break_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    if !arg_forms.is_empty() {
+        return Err(LangErr::Reason("break expects no arguments".to_string()));
+    }
+
+    limits::check_io("break")?;
+
+    let form = LangExp::list(vec![LangExp::Symbol(symbol::BREAK)]);
+    debugger::pause(&form, env)?;
+
+    Ok(LangExp::nil())
+}
+
+/// `(assert form)` fails unless `form` evaluates to something truthy, naming the form
+fn eval_assert_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let form = match arg_forms {
+        [form] => form,
+        _ => return Err(LangErr::Reason("assert expects exactly one form".to_string()))
+    };
+
+    if !is_truthy(&eval(form, env)?) {
+        return Err(LangErr::Reason(format!("assertion failed: {}", form)));
+    }
+
+    Ok(LangExp::Bool(true))
+}
+
+/// `(assert-eq form expected)` fails unless `form` evaluates to a value `equal?` to `expected`,
+/// naming the form and both values
+fn eval_assert_eq_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (form, expected) = match arg_forms {
+        [form, expected] => (form, expected),
+        _ => return Err(LangErr::Reason("assert-eq expects a form and the value it should have".to_string()))
+    };
+
+    let (actual, expected) = (eval(form, env)?, eval(expected, env)?);
+
+    if actual != expected {
+        return Err(LangErr::Reason(format!("assertion failed: {} is {}, expected {}", form, actual, expected)));
+    }
+
+    Ok(LangExp::Bool(true))
+}
+
+/// `(deftest name body...)` defines `name` as a function without parameters running the body, and
+/// declares it a test for `run-tests`
+fn eval_deftest_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (name, body) = match arg_forms {
+        [LangExp::Symbol(name), body @ ..] if !body.is_empty() => (*name, body),
+        _ => return Err(LangErr::Reason("deftest expects a name and a body".to_string()))
+    };
+
+    let test = named(LangExp::Lambda(Arc::new(make_lambda(&LangExp::nil(), body, env)?)), name);
+    env.insert(name, test.clone());
+    env.tests.add(name, test);
+
+    Ok(arg_forms[0].clone())
+}
+
+/// `(%lexical depth name)` is what the optimizer turns a symbol into when the innermost `depth`
+/// scopes can't bind it, so the lookup starts that many scopes out
+fn eval_lexical_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let (depth, name) = match arg_forms {
+        [LangExp::Int(depth), LangExp::Symbol(name)] if *depth >= 0 => (*depth, *name),
+        _ => return Err(LangErr::Reason("%lexical expects a depth and a symbol".to_string()))
+    };
+
+    let mut scope = env;
+
+    for _ in 0..depth {
+        match &scope.outer {
+            Some(outer) => scope = outer,
+            None => break
+        }
+    }
+
+    scope.get(name).ok_or_else(|| LangErr::UnboundSymbol(name.to_string()))
+}
+
+/// the value of a promise, evaluating its form the first time. other values are returned as they are
+pub(crate) fn force(exp: &LangExp) -> Result<LangExp, LangErr> {
+    let promise = match exp {
+        LangExp::Promise(promise) => promise,
+        _ => return Ok(exp.clone())
+    };
+
+    // the borrow ends before evaluating, the form may force this same promise
+    let (form, env) = match &*promise.borrow() {
+        Promise::Forced(value) => return Ok(value.clone()),
+        Promise::Pending(form, env) => (form.clone(), env.clone())
+    };
+
+    let value = eval(&form, &env)?;
+    let mut state = promise.borrow_mut();
+
+    // a nested force may have got there first, its value stands
+    if let Promise::Forced(first) = &*state {
+        return Ok(first.clone());
+    }
+
+    *state = Promise::Forced(value.clone());
+
+    Ok(value)
+}
+
+/// builds the closure shared by `lambda` and `defmacro`
+fn make_lambda(params_exp: &LangExp, body: &[LangExp], env: &LangEnv) -> Result<LangLambda, LangErr> {
+    if body.is_empty() {
+        return Err(LangErr::Reason("expected a body in lambda".to_string()));
+    }
+
+    let params = match params_exp {
+        LangExp::List(list) => parse_params(list)?,
+        _ => return Err(LangErr::Reason("expected lambda parameters to be a list".to_string()))
+    };
+
+    let (doc, body) = split_docstring(body);
+
+    Ok(LangLambda {
+        params: Arc::new(params),
+        body: Arc::new(body.to_vec()),
+        env: env.clone(),
+        name: None,
+        doc,
+        code: Arc::new(OnceLock::new()),
+    })
+}
+
+/// reads a lambda parameter list: required names, then optionally `&optional` followed by names or
+/// `(name default)` pairs, then optionally `&rest` and a single name
+pub(crate) fn parse_params(list: &[LangExp]) -> Result<Params, LangErr> {
+    let mut params = Params { required: vec![], optional: vec![], rest: None };
+    let mut optional = false;
+    let mut names = list.iter();
+
+    while let Some(param) = names.next() {
+        match param {
+            LangExp::Symbol(symbol::OPTIONAL) if !optional => optional = true,
+
+            LangExp::Symbol(symbol::REST) => match (names.next(), names.next()) {
+                (Some(LangExp::Symbol(rest)), None) if !is_marker(*rest) => params.rest = Some(*rest),
+                _ => return Err(LangErr::Reason("expected a single name after &rest".to_string()))
+            },
+
+            LangExp::Symbol(name) if !is_marker(*name) && optional => params.optional.push((*name, LangExp::nil())),
+
+            LangExp::Symbol(name) if !is_marker(*name) => params.required.push(*name),
+
+            LangExp::List(pair) if optional => match &pair[..] {
+                [LangExp::Symbol(name), default] if !is_marker(*name) => params.optional.push((*name, default.clone())),
+                _ => return Err(LangErr::Reason("expected an optional parameter to be a name or (name default)".to_string()))
+            },
+
+            _ => return Err(LangErr::Reason("expected symbols in lambda parameter list".to_string()))
+        }
+    }
+
+    Ok(params)
+}
+
+fn is_marker(name: SymbolId) -> bool {
+    name == symbol::OPTIONAL || name == symbol::REST
+}
+
+/// a lambda body starting with a string that isn't also its result documents the lambda
+pub(crate) fn split_docstring(body: &[LangExp]) -> (Option<Arc<str>>, &[LangExp]) {
+    match body {
+        [LangExp::Str(doc), rest @ ..] if !rest.is_empty() => (Some(doc.clone()), rest),
+        _ => (None, body)
+    }
+}
+
+/// gives the lambda or macro being defined the docstring it is defined with, which replaces the
+/// one from its own body. other values have nowhere to keep one
+fn documented(value: LangExp, doc: Option<Arc<str>>) -> Result<LangExp, LangErr> {
+    match (&value, doc) {
+        (_, None) => Ok(value),
+        (LangExp::Lambda(lambda), doc) => Ok(LangExp::Lambda(Arc::new(LangLambda { doc, ..LangLambda::clone(lambda) }))),
+        (LangExp::Macro(lambda), doc) => Ok(LangExp::Macro(Arc::new(LangLambda { doc, ..LangLambda::clone(lambda) }))),
+        (_, Some(_)) => Err(LangErr::type_error("a function or a macro to document", &value))
+    }
+}
+
+/// `(doc name)` prints how to call what `name` is bound to and its documentation
+fn eval_doc_args(arg_forms: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    let name = match arg_forms {
+        [LangExp::Symbol(name)] => *name,
+        _ => return Err(LangErr::Reason("doc expects a symbol".to_string()))
+    };
+
+    let value = env.get(name).ok_or_else(|| LangErr::UnboundSymbol(name.to_string()))?;
+
+    let text = match &value {
+        LangExp::Lambda(lambda) | LangExp::Macro(lambda) => {
+            let mut signature = vec![name.to_string()];
+            signature.extend(lambda.params.to_strings());
+
+            match &lambda.doc {
+                Some(doc) => format!("({})\n  {}", signature.join(" "), doc),
+                None => format!("({})", signature.join(" "))
+            }
+        }
+
+        LangExp::Func(native) => match native_doc(&native.name.name()) {
+            Some((signature, doc)) => format!("{}\n  {}", signature, doc),
+            None => format!("no documentation for `{}`", name)
+        },
+        _ => format!("no documentation for `{}` ({})", name, value.type_name())
+    };
+
+    write_out(env, &format!("{}\n", text))?;
+
+    Ok(LangExp::nil())
+}
+
+/// gives an anonymous lambda the name it is being defined under, so backtraces can show it
+pub(crate) fn named(value: LangExp, name: SymbolId) -> LangExp {
+    match &value {
+        LangExp::Lambda(lambda) if lambda.name.is_none() => LangExp::Lambda(Arc::new(LangLambda { name: Some(name), ..LangLambda::clone(lambda) })),
+        _ => value
+    }
+}
+
+/// binds already-evaluated arguments to the parameters in a fresh scope nested in the lambda's own
+pub(crate) fn bind_lambda_args(lambda: &LangLambda, args: &[LangExp]) -> Result<LangEnv, LangErr> {
+    let params = &lambda.params;
+    let arity = params.arity();
+
+    if !arity.accepts(args.len()) {
+        let name = lambda.name.map_or("lambda".to_string(), |name| name.to_string());
+        return Err(LangErr::Arity { name, expected: arity.describe(), got: args.len() });
+    }
+
+    let env = LangEnv::new_child(&lambda.env);
+
+    for (param, arg) in params.required.iter().zip(args) {
+        env.insert(*param, arg.clone());
+    }
+
+    let mut rest = args[params.required.len()..].iter();
+
+    // defaults are only evaluated for the parameters left out, and see the ones before them
+    for (param, default) in &params.optional {
+        let value = match rest.next() {
+            Some(arg) => arg.clone(),
+            None => eval(default, &env)?
+        };
+
+        env.insert(*param, value);
+    }
+
+    if let Some(param) = params.rest {
+        env.insert(param, LangExp::list(rest.cloned().collect()));
+    }
+
+    Ok(env)
+}
+
+/// evaluates the head and the arguments of the call `exp`; a lambda body is returned in tail position
+fn eval_application(exp: &LangExp, first_form: &LangExp, arg_forms: &[LangExp], env: &LangEnv) -> Result<Tail, LangErr> {
+    let first_eval = eval(first_form, env)?;
+
+    // macros get the argument forms as they are, and their expansion is evaluated in place of the call
+    if let LangExp::Macro(lambda) = &first_eval {
+        return Ok(Tail::Eval(expand_macro(lambda, arg_forms)?, env.clone()));
+    }
+
+    if !is_callable(&first_eval) {
+        return Err(LangErr::type_error("a function in head position", &first_eval));
+    }
+
+    let args_eval = arg_forms
+        .iter()
+        .map(|x| eval(x, env))
+        .collect::<Result<Vec<LangExp>, LangErr>>()?;
+
+    apply_tail(&first_eval, &args_eval, Some(exp), env)
+}
+
+pub(crate) fn is_callable(exp: &LangExp) -> bool {
+    matches!(exp, LangExp::Func(_) | LangExp::Lambda(_))
+}
+
+/// `call` is the form the call was written as, when there is one, and `env` the scope it is made from
+fn apply_tail(f: &LangExp, args: &[LangExp], call: Option<&LangExp>, env: &LangEnv) -> Result<Tail, LangErr> {
+    let traced = env.tracer.traced_name(f);
+
+    match traced {
+        Some(name) => apply_traced(name, f, args, call, env).map(Tail::Done),
+        None => apply_untraced(f, args, call, env)
+    }
+}
+
+/// calls `f`, logging the call with its arguments and then its value, indented by how many logged
+/// calls are already running. the call isn't a tail call, the log has to wait for its value
+pub(crate) fn apply_traced(name: SymbolId, f: &LangExp, args: &[LangExp], call: Option<&LangExp>, env: &LangEnv) -> Result<LangExp, LangErr> {
+    let depth = env.tracer.depth.load(Ordering::Relaxed);
+    let indent = "  ".repeat(depth);
+
+    let shown: Vec<String> = args.iter().map(|arg| format!(" {}", arg)).collect();
+    write_out(env, &format!("{}({}{})\n", indent, name, shown.concat()))?;
+
+    env.tracer.depth.store(depth + 1, Ordering::Relaxed);
+    let res = apply_untraced(f, args, call, env).and_then(finish);
+    env.tracer.depth.store(depth, Ordering::Relaxed);
+
+    match &res {
+        Ok(value) => write_out(env, &format!("{}=> {}\n", indent, value))?,
+        Err(err) => write_out(env, &format!("{}!! {}\n", indent, err))?
+    }
+
+    res
+}
+
+fn apply_untraced(f: &LangExp, args: &[LangExp], call: Option<&LangExp>, env: &LangEnv) -> Result<Tail, LangErr> {
+    match f {
+        LangExp::Func(native) => native.call(args, env).map(Tail::Done),
+
+        LangExp::Lambda(lambda) => {
+            let inner = bind_lambda_args(lambda, args)?;
+            Ok(Tail::Enter(lambda.body.clone(), inner, CallSite::new(lambda, call)))
+        }
+
+        other => Err(LangErr::type_error("a function", other))
+    }
+}
+
+/// calls a native function or a lambda with already-evaluated arguments, the same way a call in
+/// source code does from `env`; this is what builtins like `map` use to call back into lisp
+pub(crate) fn apply(f: &LangExp, args: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+    finish(apply_tail(f, args, None, env)?)
+}
+
+/// runs a pending tail form to completion
+fn finish(tail: Tail) -> Result<LangExp, LangErr> {
+    match tail {
+        Tail::Done(res) => Ok(res),
+        Tail::Eval(exp, env) => eval(&exp, &env),
+        Tail::Enter(body, env, site) => {
+            let _level = Level::enter()?;
+            let _timer = CallTimer::start(&env, site.name());
+            eval_body(&body, &env).map_err(|err| err.traced(&site))
+        }
+        Tail::Recur(_) => Err(misplaced_recur())
+    }
+}
+
+/// evaluation runs as a loop: forms in tail position (branches of `if` and `cond`, the last form of
+/// `begin`, `let` and lambda bodies) replace the current frame, so tail recursion runs in constant stack.
+/// errors are traced through the lambda call the loop is currently in; a tail call replaces it
+pub(crate) fn eval(exp: &LangExp, env: &LangEnv) -> Result<LangExp, LangErr> {
+    match eval_nested(exp, env, false)? {
+        Iteration::Finished(res) => Ok(res),
+        Iteration::Recur(_) => unreachable!("only the tail of a loop body finishes with recur")
+    }
+}
+
+/// `eval`, which for the last form of a `loop` body (`in_loop`) may also finish with a `recur`
+/// reached in tail position, before any lambda call
+fn eval_nested(exp: &LangExp, env: &LangEnv, in_loop: bool) -> Result<Iteration, LangErr> {
+    stack::check()?;
+    debugger::enter();
+
+    let res = eval_loop(exp, env, in_loop, None);
+    debugger::leave();
+
+    // the forms the loop went on with may not be located, when a tail call went into code read
+    // from another source. the vm points at the form it was given then, so this does too
+    res.map_err(|err| match exp {
+        LangExp::List(list) => err.at_list(list),
+        _ => err
+    })
+}
+
+/// a lambda call the vm is running, handed to the tree-walker along with the form in the tail of
+/// its body that the vm leaves to it
+pub(crate) struct RunningCall {
+    pub(crate) site: CallSite,
+    pub(crate) timer: Option<CallTimer>,
+    /// the level the call went down, given up when the call is over
+    pub(crate) level: Level,
+}
+
+/// evaluates `exp`, the tail of the body of `call`, as if the tree-walker had made the call: a
+/// call from the tail of `exp` takes its place, and errors before then are traced through it
+pub(crate) fn eval_in_tail_of(exp: &LangExp, env: &LangEnv, call: RunningCall) -> Result<LangExp, LangErr> {
+    if let Err(err) = stack::check() {
+        return Err(failed(err, exp, &Some(call.site)));
+    }
+
+    debugger::enter();
+    let res = eval_loop(exp, env, false, Some(call));
+    debugger::leave();
+
+    match res? {
+        Iteration::Finished(res) => Ok(res),
+        Iteration::Recur(_) => unreachable!("only the tail of a loop body finishes with recur")
+    }
+}
+
+/// an error leaving the form `exp`, which is pointed at unless a form inside it already is, and
+/// traced through `site`, the call the loop was in
+fn failed(err: LangErr, exp: &LangExp, site: &Option<CallSite>) -> LangErr {
+    let err = match exp {
+        LangExp::List(list) => err.at_list(list),
+        _ => err
+    };
+
+    match site {
+        Some(site) => err.traced(site),
+        None => err
+    }
+}
+
+/// `running` is the lambda call the loop starts out in, if the vm handed it one
+fn eval_loop(exp: &LangExp, env: &LangEnv, mut in_loop: bool, running: Option<RunningCall>) -> Result<Iteration, LangErr> {
+    let mut exp = Cow::Borrowed(exp);
+    let mut env = Cow::Borrowed(env);
+    let (mut site, mut _timer, mut _level) = match running {
+        Some(call) => (Some(call.site), call.timer, Some(call.level)),
+        None => (None, None, None)
+    };
+
+    loop {
+        let mut tail = match eval_step(&exp, &env) {
+            Ok(tail) => tail,
+            Err(err) => return Err(failed(err, &exp, &site))
+        };
+
+        // a lambda called here runs its body up to the last form, which the loop goes on with
+        if let Tail::Enter(body, next_env, next_site) = tail {
+            // a call from the tail of the one the loop is in takes its place, like in the vm
+            if _level.is_none() {
+                _level = Some(Level::enter().map_err(|err| failed(err, &exp, &site))?);
+            }
+
+            // a recur in the body of the lambda called from the loop's tail isn't the loop's
+            in_loop = false;
+            _timer = CallTimer::start(&next_env, next_site.name());
+            env = Cow::Owned(next_env);
+            site = Some(next_site);
+
+            tail = match eval_body_tail(&body, &env) {
+                Ok(tail) => tail,
+                Err(err) => return Err(failed(err, &exp, &site))
+            };
+        }
+
+        match tail {
+            Tail::Done(res) => return Ok(Iteration::Finished(res)),
+            Tail::Recur(values) if in_loop => return Ok(Iteration::Recur(values)),
+            Tail::Recur(_) => return Err(failed(misplaced_recur(), &exp, &site)),
+            Tail::Eval(next_exp, next_env) => {
+                exp = Cow::Owned(next_exp);
+                env = Cow::Owned(next_env);
+            }
+            Tail::Enter(..) => unreachable!("a lambda body finishes with a value or a form to evaluate")
+        }
+    }
+}
+
+/// one iteration of the `eval` loop, which spends a step of fuel and may stop in the debugger.
+/// everything but a call evaluates in `eval_atom`, keeping this frame small: every level of
+/// nested evaluation has one
+fn eval_step(exp: &LangExp, env: &LangEnv) -> Result<Tail, LangErr> {
+    limits::step()?;
+    debugger::check(exp, env)?;
+
+    match exp {
+        LangExp::List(list) if !list.is_empty() => {
+            let first_form = &list[0];
+            let arg_forms = &list[1..];
+
+            match eval_built_in_form(first_form, arg_forms, env) {
+                Some(res) => res,
+                None => eval_application(exp, first_form, arg_forms, env)
+            }
+        }
+
+        _ => eval_atom(exp, env).map(Tail::Done)
+    }
+}
+
+/// the value of a form that isn't a call
+fn eval_atom(exp: &LangExp, env: &LangEnv) -> Result<LangExp, LangErr> {
+    match exp {
+        LangExp::Symbol(k) => env.get(*k).ok_or_else(|| LangErr::UnboundSymbol(k.to_string())),
+
+        LangExp::Bool(_a) => Ok(exp.clone()),
+
+        LangExp::Int(_a) => Ok(exp.clone()),
+
+        LangExp::Number(_a) => Ok(exp.clone()),
+
+        LangExp::Char(_a) => Ok(exp.clone()),
+
+        LangExp::Str(_a) => Ok(exp.clone()),
+
+        // vector literals evaluate their elements into a fresh vector
+        LangExp::Vector(items) => {
+            // copied out first, an element may write to the vector while it is evaluated
+            let items = items.borrow().clone();
+            let evaluated = items
+                .iter()
+                .map(|x| eval(x, env))
+                .collect::<Result<Vec<LangExp>, LangErr>>()?;
+
+            Ok(LangExp::Vector(Arc::new(Lock::new(evaluated))))
+        }
+
+        // the empty list evaluates to itself
+        LangExp::List(_) => Ok(exp.clone()),
+
+        LangExp::Map(_) => Ok(exp.clone()),
+
+        LangExp::Record(_) => Ok(exp.clone()),
+
+        LangExp::Promise(_) => Ok(exp.clone()),
+
+        LangExp::Thread(_) | LangExp::Channel(_) => Ok(exp.clone()),
+
+        // functions only appear in code built at runtime, as in `(eval (list + 1 2))`
+        LangExp::Func(_) | LangExp::Lambda(_) | LangExp::Macro(_) => Ok(exp.clone())
+    }
+}
@@ -0,0 +1,847 @@
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::mem;
+use std::sync::{Arc, OnceLock};
+
+use crate::compiler::Chunk;
+use crate::env::LangEnv;
+use crate::limits;
+use crate::list::LangList;
+use crate::lock::Lock;
+use crate::profile::CallTimer;
+use crate::reader::source_span;
+use crate::symbol::{self, SymbolId};
+use crate::threads::{Channel, ThreadHandle};
+use crate::trace::TraceFrame;
+
+/// a lisp value; code and data share this representation
+#[derive(Clone)]
+pub enum LangExp {
+    Bool(bool),
+    Symbol(SymbolId),
+    Int(i64),
+    Number(f64),
+    /// a single unicode scalar value, read as `#\a` or by name, like `#\space`
+    Char(char),
+    Str(Arc<str>),
+    /// lists and strings are shared rather than copied when values are cloned, which happens on
+    /// every lookup and every tail call
+    List(LangList),
+    /// a mutable array; clones share the same storage, so `vector-set!` is visible through all of them
+    Vector(Arc<Lock<Vec<LangExp>>>),
+    /// an immutable hash map, `assoc` and `dissoc` return updated copies
+    Map(Arc<LangMap>),
+    Func(Native),
+    Lambda(Arc<LangLambda>),
+    /// a function from unevaluated forms to the form that replaces them, see `defmacro`
+    Macro(Arc<LangLambda>),
+    /// an instance of a type declared with `defstruct`; clones share the same fields
+    Record(Arc<LangRecord>),
+    /// a form whose evaluation is put off until `force` asks for its value, see `delay`
+    Promise(Arc<Lock<Promise>>),
+    /// a thread started by `spawn`, see `join`
+    Thread(Arc<ThreadHandle>),
+    /// a queue of values between threads, see `chan`
+    Channel(Arc<Channel>),
+}
+
+pub enum Promise {
+    /// not forced yet: the form and the scope `delay` captured
+    Pending(LangExp, LangEnv),
+    /// the value, kept so forcing again doesn't evaluate the form again
+    Forced(LangExp),
+}
+
+/// the name and field names of a `defstruct` type. each evaluation of `defstruct` makes a new
+/// type, told apart by identity
+pub struct RecordType {
+    pub(crate) name: SymbolId,
+    pub(crate) fields: Vec<SymbolId>,
+}
+
+pub struct LangRecord {
+    pub(crate) kind: Arc<RecordType>,
+    /// one value per field of `kind`, in order
+    pub(crate) fields: Lock<Vec<LangExp>>,
+}
+
+/// maps use a fixed hasher so iteration order, and therefore printing, is the same on every run
+pub type LangMap = HashMap<LangExp, LangExp, BuildHasherDefault<DefaultHasher>>;
+
+/// signature of builtins implemented in rust. they get the scope they are called from, which
+/// lets them evaluate code or call back into lisp
+pub type NativeFn = fn(&[LangExp], &LangEnv) -> Result<LangExp, LangErr>;
+
+/// how many arguments a native function takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// anywhere from the first count to the second, inclusive
+    Between(usize, usize),
+    Any,
+}
+
+impl Arity {
+    pub(crate) fn accepts(self, n: usize) -> bool {
+        match self {
+            Arity::Exact(expected) => n == expected,
+            Arity::AtLeast(min) => n >= min,
+            Arity::Between(min, max) => n >= min && n <= max,
+            Arity::Any => true
+        }
+    }
+
+    pub(crate) fn describe(self) -> String {
+        match self {
+            Arity::Exact(expected) => plural(expected, "argument"),
+            Arity::AtLeast(min) => format!("at least {}", plural(min, "argument")),
+            Arity::Between(min, max) => format!("{} to {} arguments", min, max),
+            Arity::Any => "any number of arguments".to_string()
+        }
+    }
+}
+
+/// a builtin implemented in rust, together with the name it was registered under
+#[derive(Clone, Copy)]
+pub struct Native {
+    pub(crate) name: SymbolId,
+    pub(crate) arity: Arity,
+    pub(crate) f: NativeFn,
+}
+
+impl Native {
+    /// checks the number of arguments against the arity, then runs the function
+    pub(crate) fn call(&self, args: &[LangExp], env: &LangEnv) -> Result<LangExp, LangErr> {
+        if !self.arity.accepts(args.len()) {
+            return Err(LangErr::Arity { name: self.name.to_string(), expected: self.arity.describe(), got: args.len() });
+        }
+
+        let _timer = CallTimer::start(env, Some(self.name));
+
+        let res = (self.f)(args, env)?;
+        limits::check_size(&res)?;
+
+        Ok(res)
+    }
+}
+
+/// the parameter list of a lambda: `(a b &optional (c default) d &rest more)`. optional parameters
+/// without a default are bound to the empty list when left out
+pub(crate) struct Params {
+    pub(crate) required: Vec<SymbolId>,
+    /// each with the form its default is evaluated from, in the scope of the parameters before it
+    pub(crate) optional: Vec<(SymbolId, LangExp)>,
+    /// bound to a list of the arguments after the required and optional ones
+    pub(crate) rest: Option<SymbolId>,
+}
+
+impl Params {
+    pub(crate) fn arity(&self) -> Arity {
+        let min = self.required.len();
+
+        match (self.optional.len(), self.rest) {
+            (_, Some(_)) => Arity::AtLeast(min),
+            (0, None) => Arity::Exact(min),
+            (optional, None) => Arity::Between(min, min + optional)
+        }
+    }
+
+    /// the parameter list as it was written
+    pub(crate) fn to_strings(&self) -> Vec<String> {
+        let mut res: Vec<String> = self.required.iter().map(|param| param.to_string()).collect();
+
+        if !self.optional.is_empty() {
+            res.push(symbol::OPTIONAL.to_string());
+            res.extend(self.optional.iter().map(|(param, default)| format!("({} {})", param, default)));
+        }
+
+        if let Some(rest) = self.rest {
+            res.push(symbol::REST.to_string());
+            res.push(rest.to_string());
+        }
+
+        res
+    }
+}
+
+/// a user-defined function together with the scope it was created in
+#[derive(Clone)]
+pub struct LangLambda {
+    pub(crate) params: Arc<Params>,
+    pub(crate) body: Arc<Vec<LangExp>>,
+    pub(crate) env: LangEnv,
+    /// the name it was first `define`d under, for backtraces
+    pub(crate) name: Option<SymbolId>,
+    /// the string the body started with, if it had more forms after it
+    pub(crate) doc: Option<Arc<str>>,
+    /// the body compiled for the bytecode backend, filled in on the first call from compiled code
+    pub(crate) code: Arc<OnceLock<Arc<Chunk>>>,
+}
+
+/// a place in source text; lines and columns count from 1, `len` is measured in characters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+#[derive(Debug)]
+pub enum LangErr {
+    /// malformed source text
+    Parse(String),
+    /// a symbol with no binding in any enclosing scope
+    UnboundSymbol(String),
+    /// a value of the wrong type, e.g. a string passed to `+`
+    Type { expected: String, got: String },
+    /// a function called with the wrong number of arguments
+    Arity { name: String, expected: String, got: usize },
+    /// any other runtime failure
+    Reason(String),
+    /// a value raised from lisp with `raise` or `error`, which `try` hands to its `catch` clause
+    Raised(LangExp),
+    /// evaluation went past one of the interpreter's `Limits`. `try` doesn't catch it
+    LimitExceeded(String),
+    /// `(exit code)` on its way out of the interpreter, see [`LangErr::exit_code`]. `try` doesn't
+    /// catch it either
+    Exit(i32),
+    /// another error together with the source span it was raised from
+    Located { err: Box<LangErr>, span: Span },
+    /// an error raised while evaluating a file pulled in with `load` or `require`. it keeps the
+    /// file's source so the error can be rendered against the right text
+    InFile { path: String, source: String, err: Box<LangErr> },
+    /// another error together with the lisp calls it propagated through, innermost first.
+    /// `omitted` counts the frames beyond the ones kept
+    Traced { err: Box<LangErr>, frames: Vec<TraceFrame>, omitted: usize },
+}
+
+impl LangErr {
+    /// a type error for `got`, which should have been a value of type `expected`
+    pub(crate) fn type_error(expected: &str, got: &LangExp) -> LangErr {
+        LangErr::Type { expected: expected.to_string(), got: got.type_name().to_string() }
+    }
+
+    /// an arity error for `name`, which takes exactly `expected` arguments
+    pub(crate) fn arity_error(name: &str, expected: usize, got: usize) -> LangErr {
+        LangErr::Arity { name: name.to_string(), expected: plural(expected, "argument"), got }
+    }
+
+    /// attaches a source location, keeping the innermost one if there already is one
+    pub fn at(self, span: Span) -> LangErr {
+        match self {
+            LangErr::Located { .. } | LangErr::InFile { .. } => self,
+            LangErr::Traced { err, frames, omitted } => LangErr::Traced { err: Box::new(err.at(span)), frames, omitted },
+            _ => LangErr::Located { err: Box::new(self), span }
+        }
+    }
+
+    /// attaches the span `list` was read from, if it is in the source being evaluated and the
+    /// error has no location yet. called with each form an error propagates out of, innermost first
+    pub(crate) fn at_list(self, list: &LangList) -> LangErr {
+        if self.span().is_some() || matches!(self, LangErr::InFile { .. }) {
+            return self;
+        }
+
+        match source_span(list) {
+            Some(span) => self.at(span),
+            None => self
+        }
+    }
+
+    /// moves the location to `span`, or attaches it if there is none
+    pub(crate) fn relocated(self, span: Span) -> LangErr {
+        match self {
+            LangErr::Located { err, .. } => LangErr::Located { err, span },
+            LangErr::Traced { err, frames, omitted } => LangErr::Traced { err: Box::new(err.relocated(span)), frames, omitted },
+            _ => self.at(span)
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LangErr::Located { span, .. } => Some(*span),
+            LangErr::Traced { err, .. } => err.span(),
+            _ => None
+        }
+    }
+
+    /// the error without its location
+    pub fn inner(&self) -> &LangErr {
+        match self {
+            LangErr::Located { err, .. } | LangErr::InFile { err, .. } | LangErr::Traced { err, .. } => err.inner(),
+            _ => self
+        }
+    }
+
+    /// the code `(exit code)` asked the process to exit with, when that is what stopped evaluation
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.inner() {
+            LangErr::Exit(code) => Some(*code),
+            _ => None
+        }
+    }
+
+    /// marks an error raised while evaluating the file at `path`, unless it came from a file
+    /// that one loaded in turn
+    pub(crate) fn in_file(self, path: &str, source: &str) -> LangErr {
+        match self {
+            LangErr::InFile { .. } => self,
+            _ => LangErr::InFile { path: path.to_string(), source: source.to_string(), err: Box::new(self) }
+        }
+    }
+
+    /// the file the error was raised in, when that wasn't the source passed to the interpreter
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            LangErr::InFile { path, .. } => Some(path),
+            LangErr::Traced { err, .. } => err.path(),
+            _ => None
+        }
+    }
+
+    /// formats the error followed by the offending line of `source` with a caret under the span,
+    /// then the backtrace if there is one. errors from loaded files are shown against that file
+    /// instead, prefixed with its path
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            LangErr::InFile { path, source, err } => return format!("{}:{}", path, err.render(source)),
+            LangErr::Traced { err, frames, omitted } => {
+                let mut res = err.render(source);
+
+                for frame in frames {
+                    res.push_str(&format!("\n  {}", frame));
+                }
+
+                if *omitted > 0 {
+                    res.push_str(&format!("\n  ... and {}", plural(*omitted, "more call")));
+                }
+
+                return res;
+            }
+            _ => {}
+        }
+
+        let span = match self.span() {
+            Some(span) => span,
+            None => return self.to_string()
+        };
+
+        let line = match source.lines().nth(span.line - 1) {
+            Some(line) => line,
+            None => return self.to_string()
+        };
+
+        let width = line.chars().count();
+        let len = span.len.min(width.saturating_sub(span.column - 1)).max(1);
+        let caret = format!("{}^{}", " ".repeat(span.column - 1), "~".repeat(len - 1));
+
+        format!("{}\n    {}\n    {}", self, line, caret)
+    }
+}
+
+/// "1 argument", "2 arguments"
+pub(crate) fn plural(n: usize, noun: &str) -> String {
+    if n == 1 { format!("{} {}", n, noun) } else { format!("{} {}s", n, noun) }
+}
+
+impl fmt::Display for LangErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LangErr::Parse(msg) => write!(f, "parse error: {}", msg),
+            LangErr::UnboundSymbol(name) => write!(f, "unbound symbol `{}`", name),
+            LangErr::Type { expected, got } => write!(f, "type error: expected {}, got {}", expected, got),
+            LangErr::Arity { name, expected, got } => write!(f, "`{}` expects {}, got {}", name, expected, got),
+            LangErr::Reason(msg) => write!(f, "{}", msg),
+            LangErr::Raised(LangExp::Str(msg)) => write!(f, "error: {}", msg),
+            LangErr::Raised(payload) => write!(f, "error: {}", payload),
+            LangErr::LimitExceeded(msg) => write!(f, "limit exceeded: {}", msg),
+            LangErr::Exit(code) => write!(f, "exit with code {}", code),
+            LangErr::Located { err, span } => write!(f, "{}:{}: {}", span.line, span.column, err),
+            LangErr::InFile { path, err, .. } => write!(f, "{}:{}", path, err),
+            LangErr::Traced { err, .. } => write!(f, "{}", err)
+        }
+    }
+}
+
+impl std::error::Error for LangErr {}
+
+impl LangExp {
+    pub fn list(items: Vec<LangExp>) -> LangExp {
+        LangExp::List(LangList::new(items))
+    }
+
+    /// the empty list, which is also what `nil` reads as
+    pub fn nil() -> LangExp {
+        LangExp::list(vec![])
+    }
+
+    /// identity, what `eq?` checks: lists, strings, vectors and maps are only identical to the same
+    /// allocation, except that all empty lists are identical. other values compare like `==`, so
+    /// NaN is identical to itself and functions only to themselves
+    pub fn is_identical(&self, other: &LangExp) -> bool {
+        match (self, other) {
+            (LangExp::List(a), LangExp::List(b)) => LangList::ptr_eq(a, b) || (a.is_empty() && b.is_empty()),
+            (LangExp::Str(a), LangExp::Str(b)) => Arc::ptr_eq(a, b),
+            (LangExp::Vector(a), LangExp::Vector(b)) => Arc::ptr_eq(a, b),
+            (LangExp::Map(a), LangExp::Map(b)) => Arc::ptr_eq(a, b),
+            (LangExp::Record(a), LangExp::Record(b)) => Arc::ptr_eq(a, b),
+            (LangExp::Promise(a), LangExp::Promise(b)) => Arc::ptr_eq(a, b),
+            (LangExp::Thread(a), LangExp::Thread(b)) => Arc::ptr_eq(a, b),
+            (LangExp::Channel(a), LangExp::Channel(b)) => Arc::ptr_eq(a, b),
+            _ => self == other
+        }
+    }
+
+    /// the name of the variant, as shown in type errors
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LangExp::Bool(_) => "bool",
+            LangExp::Symbol(_) => "symbol",
+            LangExp::Int(_) => "integer",
+            LangExp::Number(_) => "float",
+            LangExp::Char(_) => "char",
+            LangExp::Str(_) => "string",
+            LangExp::List(_) => "list",
+            LangExp::Vector(_) => "vector",
+            LangExp::Map(_) => "map",
+            LangExp::Record(_) => "record",
+            LangExp::Promise(_) => "promise",
+            LangExp::Thread(_) => "thread",
+            LangExp::Channel(_) => "channel",
+            LangExp::Func(_) => "function",
+            LangExp::Lambda(_) => "lambda",
+            LangExp::Macro(_) => "macro",
+        }
+    }
+}
+
+/// structural equality, which is what map keys and `equal?` use. floats are equal when their bits are, so NaN
+/// equals itself and `0.0` differs from `-0.0`; integers never equal floats. functions and lambdas
+/// are only equal to themselves. values are compared without recursion, and vectors or records
+/// that end up inside themselves are equal when they agree however far they are unrolled
+impl PartialEq for LangExp {
+    fn eq(&self, other: &LangExp) -> bool {
+        let mut pending = vec![];
+        // pairs of vectors or records already compared or being compared. meeting one again,
+        // inside itself, adds nothing: a difference between them shows up where they were first met
+        let mut seen = HashSet::new();
+
+        if !compare(self, other, &mut pending, &mut seen) {
+            return false;
+        }
+
+        while let Some((a, b)) = pending.pop() {
+            if !compare(&a, &b, &mut pending, &mut seen) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// whether `a` and `b` agree on what they hold themselves, queueing the pairs of elements that
+/// are left to compare
+fn compare(a: &LangExp, b: &LangExp, pending: &mut Vec<(LangExp, LangExp)>, seen: &mut HashSet<(usize, usize)>) -> bool {
+    match (a, b) {
+        (LangExp::Bool(a), LangExp::Bool(b)) => a == b,
+        (LangExp::Symbol(a), LangExp::Symbol(b)) => a == b,
+        (LangExp::Int(a), LangExp::Int(b)) => a == b,
+        (LangExp::Number(a), LangExp::Number(b)) => a.to_bits() == b.to_bits(),
+        (LangExp::Char(a), LangExp::Char(b)) => a == b,
+        (LangExp::Str(a), LangExp::Str(b)) => a == b,
+        (LangExp::List(a), LangExp::List(b)) => {
+            if LangList::ptr_eq(a, b) {
+                return true;
+            }
+
+            a.len() == b.len() && {
+                pending.extend(a.iter().cloned().zip(b.iter().cloned()));
+                true
+            }
+        }
+        (LangExp::Vector(a), LangExp::Vector(b)) => {
+            if Arc::ptr_eq(a, b) || !seen.insert((Arc::as_ptr(a) as *const () as usize, Arc::as_ptr(b) as *const () as usize)) {
+                return true;
+            }
+
+            let (a, b) = (a.borrow().clone(), b.borrow().clone());
+
+            a.len() == b.len() && {
+                pending.extend(a.into_iter().zip(b));
+                true
+            }
+        }
+        (LangExp::Map(a), LangExp::Map(b)) => {
+            if Arc::ptr_eq(a, b) {
+                return true;
+            }
+
+            a.len() == b.len() && a.iter().all(|(key, value)| match b.get(key) {
+                Some(other) => {
+                    pending.push((value.clone(), other.clone()));
+                    true
+                }
+                None => false
+            })
+        }
+        (LangExp::Record(a), LangExp::Record(b)) => {
+            if !Arc::ptr_eq(&a.kind, &b.kind) {
+                return false;
+            }
+
+            if Arc::ptr_eq(a, b) || !seen.insert((Arc::as_ptr(a) as *const () as usize, Arc::as_ptr(b) as *const () as usize)) {
+                return true;
+            }
+
+            pending.extend(a.fields.borrow().clone().into_iter().zip(b.fields.borrow().clone()));
+            true
+        }
+        (LangExp::Promise(a), LangExp::Promise(b)) => Arc::ptr_eq(a, b),
+        (LangExp::Thread(a), LangExp::Thread(b)) => Arc::ptr_eq(a, b),
+        (LangExp::Channel(a), LangExp::Channel(b)) => Arc::ptr_eq(a, b),
+        (LangExp::Func(a), LangExp::Func(b)) => a.f as usize == b.f as usize,
+        (LangExp::Lambda(a), LangExp::Lambda(b)) => a.same(b),
+        (LangExp::Macro(a), LangExp::Macro(b)) => a.same(b),
+        _ => false
+    }
+}
+
+impl Eq for LangExp {}
+
+/// consistent with `PartialEq`. a vector hashes its current contents, so mutating a vector that is
+/// used as a map key makes it unreachable
+impl Hash for LangExp {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_value(self, false, state);
+    }
+}
+
+/// hashes `exp` without recursion. a vector or record inside another one only hashes its length,
+/// as does a map inside a map: that keeps the walk from going round values inside themselves, and
+/// equal values, which agree however far they are unrolled, still agree on what is left
+fn hash_value<H: Hasher>(exp: &LangExp, in_map: bool, state: &mut H) {
+    // each value still to hash, with whether it is inside a vector or record
+    let mut pending = vec![(exp.clone(), false)];
+
+    while let Some((exp, in_mutable)) = pending.pop() {
+        mem::discriminant(&exp).hash(state);
+
+        match &exp {
+            LangExp::Bool(b) => b.hash(state),
+            LangExp::Symbol(s) => s.hash(state),
+            LangExp::Int(i) => i.hash(state),
+            LangExp::Number(f) => f.to_bits().hash(state),
+            LangExp::Char(c) => c.hash(state),
+            LangExp::Str(s) => s.hash(state),
+            LangExp::List(list) => {
+                list.len().hash(state);
+                pending.extend(list.iter().rev().map(|item| (item.clone(), in_mutable)));
+            }
+            LangExp::Vector(items) => {
+                let items = items.borrow().clone();
+                items.len().hash(state);
+
+                if !in_mutable {
+                    pending.extend(items.into_iter().rev().map(|item| (item, true)));
+                }
+            }
+            LangExp::Record(record) => {
+                let fields = record.fields.borrow().clone();
+                fields.len().hash(state);
+
+                if !in_mutable {
+                    pending.extend(fields.into_iter().rev().map(|field| (field, true)));
+                }
+            }
+            LangExp::Map(map) => {
+                map.len().hash(state);
+
+                if !in_map {
+                    // entries are unordered, so combine their hashes with a commutative operation
+                    let combined = map.iter().fold(0u64, |acc, (key, value)| {
+                        let mut hasher = DefaultHasher::new();
+                        hash_value(key, true, &mut hasher);
+                        hash_value(value, true, &mut hasher);
+                        acc.wrapping_add(hasher.finish())
+                    });
+
+                    combined.hash(state);
+                }
+            }
+            LangExp::Func(native) => (native.f as usize).hash(state),
+            LangExp::Lambda(lambda) | LangExp::Macro(lambda) => (Arc::as_ptr(&lambda.body) as usize).hash(state),
+            LangExp::Promise(promise) => (Arc::as_ptr(promise) as usize).hash(state),
+            LangExp::Thread(thread) => (Arc::as_ptr(thread) as usize).hash(state),
+            LangExp::Channel(channel) => (Arc::as_ptr(channel) as usize).hash(state),
+        }
+    }
+}
+
+impl LangLambda {
+    /// identity: created by the same evaluation of a `lambda` form
+    fn same(&self, other: &LangLambda) -> bool {
+        Arc::ptr_eq(&self.body, &other.body) && Arc::ptr_eq(&self.env.data, &other.env.data)
+    }
+}
+
+impl fmt::Debug for LangExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// the characters written by name after `#\`, the ones that would be invisible otherwise
+pub(crate) const CHAR_NAMES: &[(&str, char)] = &[
+    ("space", ' '),
+    ("newline", '\n'),
+    ("tab", '\t'),
+    ("return", '\r'),
+    ("nul", '\0'),
+];
+
+/// inverse of `read_string`, so printed strings read back as the same value
+pub(crate) fn escape_string(s: &str) -> String {
+    let mut res = String::new();
+
+    for c in s.chars() {
+        match c {
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            _ => res.push(c)
+        }
+    }
+
+    res
+}
+
+/// prints a value the way the reader writes it, so `read_str` reads the text back as an equal value.
+/// this holds for data: booleans, numbers, characters, strings, symbols, and lists, vectors and maps of them,
+/// unless a vector is inside itself. it doesn't for symbols made by `gensym`, or interned under a name that
+/// doesn't read as a symbol, like `a b`. functions, records, promises, threads and channels print in a form
+/// meant for people only
+pub fn write(exp: &LangExp) -> String {
+    exp.to_string()
+}
+
+/// a part of a value left to print: a value that is printed next, some text, or the end of a
+/// vector, record or promise
+enum Piece {
+    Exp(LangExp),
+    Text(Cow<'static, str>),
+    Close(&'static str),
+}
+
+/// containers are printed from a stack of pieces rather than by recursing into them, so values
+/// nested any depth print. a vector, record or promise that contains itself prints as `...` where
+/// it appears inside itself
+impl fmt::Display for LangExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut pending = vec![Piece::Exp(self.clone())];
+        // the vectors, records and promises being printed
+        let mut open: Vec<usize> = vec![];
+
+        while let Some(piece) = pending.pop() {
+            let exp = match piece {
+                Piece::Exp(exp) => exp,
+                Piece::Text(text) => {
+                    f.write_str(&text)?;
+                    continue;
+                }
+                Piece::Close(text) => {
+                    open.pop();
+                    f.write_str(text)?;
+                    continue;
+                }
+            };
+
+            let address = exp.mutable_address();
+
+            if let Some(address) = address {
+                if open.contains(&address) {
+                    f.write_str("...")?;
+                    continue;
+                }
+
+                open.push(address);
+            }
+
+            let (start, end, parts): (Cow<'static, str>, &'static str, Vec<Piece>) = match &exp {
+                LangExp::List(list) => ("(".into(), ")", separated(list.iter().cloned())),
+
+                LangExp::Vector(items) => ("[".into(), "]", separated(items.borrow().iter().cloned())),
+
+                LangExp::Map(map) => ("{".into(), "}", separated(map.iter().flat_map(|(k, v)| [k.clone(), v.clone()]))),
+
+                LangExp::Record(record) => {
+                    let mut parts = vec![];
+
+                    for (i, (name, value)) in record.kind.fields.iter().zip(record.fields.borrow().iter()).enumerate() {
+                        let comma = if i == 0 { "" } else { "," };
+                        parts.push(Piece::Text(format!("{} {}: ", comma, name).into()));
+                        parts.push(Piece::Exp(value.clone()));
+                    }
+
+                    (format!("#<{}", record.kind.name).into(), ">", parts)
+                }
+
+                LangExp::Promise(promise) => match &*promise.borrow() {
+                    Promise::Pending(..) => ("#<promise".into(), ">", vec![]),
+                    Promise::Forced(value) => ("#<promise ".into(), ">", vec![Piece::Exp(value.clone())])
+                },
+
+                atom => {
+                    f.write_str(&atom_string(atom))?;
+                    continue;
+                }
+            };
+
+            f.write_str(&start)?;
+
+            // the parts are printed in order, the stack pops them last first
+            pending.push(if address.is_some() { Piece::Close(end) } else { Piece::Text(end.into()) });
+            pending.extend(parts.into_iter().rev());
+        }
+
+        Ok(())
+    }
+}
+
+impl LangExp {
+    /// where a vector, record or promise is kept, which tells them apart while printing; they are
+    /// the values that can end up inside themselves
+    pub(crate) fn mutable_address(&self) -> Option<usize> {
+        match self {
+            LangExp::Vector(items) => Some(Arc::as_ptr(items) as *const () as usize),
+            LangExp::Record(record) => Some(Arc::as_ptr(record) as *const () as usize),
+            LangExp::Promise(promise) => Some(Arc::as_ptr(promise) as *const () as usize),
+            _ => None
+        }
+    }
+}
+
+/// `items` with a space between each two
+fn separated(items: impl Iterator<Item = LangExp>) -> Vec<Piece> {
+    let mut parts = vec![];
+
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            parts.push(Piece::Text(" ".into()));
+        }
+
+        parts.push(Piece::Exp(item));
+    }
+
+    parts
+}
+
+/// the printed form of a value that has no values inside it
+fn atom_string(exp: &LangExp) -> String {
+    match exp {
+        LangExp::Bool(b) => b.to_string(),
+
+        LangExp::Symbol(s) => s.to_string(),
+
+        LangExp::Int(i) => i.to_string(),
+
+        // whole floats keep a fractional part or an exponent, so they read back as floats
+        LangExp::Number(n) if n.is_finite() && n.fract() == 0.0 && n.abs() < 1e16 => format!("{:.1}", n),
+
+        LangExp::Number(n) if n.is_finite() && n.fract() == 0.0 => format!("{:e}", n),
+
+        LangExp::Number(n) if n.is_nan() => "+nan.0".to_string(),
+
+        LangExp::Number(n) if n.is_infinite() => if *n > 0.0 { "+inf.0" } else { "-inf.0" }.to_string(),
+
+        LangExp::Number(n) => n.to_string(),
+
+        LangExp::Char(c) => match CHAR_NAMES.iter().find(|(_, named)| named == c) {
+            Some((name, _)) => format!("#\\{}", name),
+            None => format!("#\\{}", c)
+        },
+
+        LangExp::Str(s) => format!("\"{}\"", escape_string(s)),
+
+        LangExp::Func(_) => "Function {}".to_string(),
+
+        LangExp::Lambda(_) => "Lambda {}".to_string(),
+
+        LangExp::Macro(_) => "Macro {}".to_string(),
+
+        LangExp::Thread(_) => "#<thread>".to_string(),
+
+        LangExp::Channel(_) => "#<channel>".to_string(),
+
+        LangExp::List(_) | LangExp::Vector(_) | LangExp::Map(_) | LangExp::Record(_) | LangExp::Promise(_) =>
+            unreachable!("values with values inside them are printed a piece at a time")
+    }
+}
+
+/// values nested deeper than the stack could recurse are freed a level at a time: what a list,
+/// vector, map, record, promise or closure has inside is taken out of it before it goes, when
+/// this is the last reference to it, and freed the same way
+impl Drop for LangExp {
+    fn drop(&mut self) {
+        let mut pending = match self.take_contents() {
+            Some(contents) => contents,
+            None => return
+        };
+
+        while let Some(mut exp) = pending.pop() {
+            if let Some(contents) = exp.take_contents() {
+                pending.extend(contents);
+            }
+        }
+    }
+}
+
+impl LangExp {
+    /// the values inside this one, taken out of it if nothing else refers to them through it
+    fn take_contents(&mut self) -> Option<Vec<LangExp>> {
+        let contents = match self {
+            LangExp::List(list) => list.take_unique()?,
+            LangExp::Vector(items) => mem::take(Arc::get_mut(items)?.get_mut()),
+            LangExp::Map(map) => Arc::get_mut(map)?.drain().flat_map(|(k, v)| [k, v]).collect(),
+            LangExp::Record(record) => mem::take(Arc::get_mut(record)?.fields.get_mut()),
+            LangExp::Promise(promise) => match Arc::get_mut(promise)?.get_mut() {
+                Promise::Pending(exp, env) => {
+                    let mut contents = take_scope(env);
+                    contents.push(mem::replace(exp, LangExp::Bool(false)));
+                    contents
+                }
+                Promise::Forced(exp) => vec![mem::replace(exp, LangExp::Bool(false))]
+            },
+            LangExp::Lambda(lambda) | LangExp::Macro(lambda) => take_scope(&mut Arc::get_mut(lambda)?.env),
+            _ => return None
+        };
+
+        (!contents.is_empty()).then_some(contents)
+    }
+}
+
+/// the values bound in `env` and in the scopes it is nested in, taken out of those nothing else
+/// refers to. the scopes are unlinked as they go, so a chain of them is freed a scope at a time
+fn take_scope(env: &mut LangEnv) -> Vec<LangExp> {
+    let mut contents = take_bindings(env);
+    let mut outer = env.outer.take();
+
+    while let Some(Ok(mut scope)) = outer.map(Arc::try_unwrap) {
+        contents.extend(take_bindings(&mut scope));
+        outer = scope.outer.take();
+    }
+
+    contents
+}
+
+fn take_bindings(env: &mut LangEnv) -> Vec<LangExp> {
+    match Arc::get_mut(&mut env.data) {
+        Some(data) => data.get_mut().drain().map(|(_, value)| value).collect(),
+        None => vec![]
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use crate::builtins::default_env;
+use crate::env::LangEnv;
+use crate::exp::{LangErr, LangExp, LangLambda, RecordType};
+use crate::stack;
+use crate::symbol::{self, SymbolId};
+
+const HEADER: &str = ";; a session image written by save-image, start from it with `lisp --image <file>`\n";
+
+/// the width definitions are pretty printed to
+const WIDTH: usize = 100;
+
+/// writes the definitions of `env`'s global scope that differ from a fresh interpreter's to `path`,
+/// as source that recreates them when evaluated. record types come first, then macros, then
+/// functions, then the other values. builtins are written as their names, so they are bound again
+/// from the interpreter loading the image. the bindings that belong to the session itself, see
+/// `is_session_state`, are left out. returns the names with values that can't be written as source,
+/// like threads, channels, promises, symbols made by `gensym` (which reading would intern), or
+/// lambdas whose bodies hold such values
+pub(crate) fn save(env: &LangEnv, path: &str) -> Result<LangExp, LangErr> {
+    let image = Image { global: env.global(), fresh: default_env() };
+
+    let mut names = image.global.names();
+    names.sort_by_key(|name| name.name());
+
+    let (mut structs, mut macros, mut functions, mut values, mut unsaved) = (vec![], vec![], vec![], vec![], vec![]);
+
+    for name in names {
+        let value = match image.global.get(name) {
+            Some(value) => value,
+            None => continue
+        };
+
+        if is_session_state(name) || image.fresh.get(name).is_some_and(|builtin| same_definition(&value, &builtin)) {
+            continue;
+        }
+
+        if !name.is_interned() {
+            unsaved.push(LangExp::Symbol(name));
+            continue;
+        }
+
+        // the functions `defstruct` generates are written as the `defstruct` itself
+        if let LangExp::Lambda(lambda) = &value {
+            if let Some((builtin, kind)) = record_type(lambda) {
+                if &*builtin == "%record-new" {
+                    let mut form = vec![LangExp::Symbol(symbol::DEFSTRUCT), LangExp::Symbol(kind.name)];
+                    form.extend(kind.fields.iter().map(|field| LangExp::Symbol(*field)));
+                    structs.push(LangExp::list(form));
+                }
+
+                continue;
+            }
+        }
+
+        let form = match &value {
+            LangExp::Macro(lambda) if image.is_global(&lambda.env) => lambda_form(lambda).map(|lambda| {
+                let mut form = vec![LangExp::Symbol(symbol::DEFMACRO), LangExp::Symbol(name)];
+                form.extend_from_slice(&lambda[1..]);
+                (&mut macros, LangExp::list(form))
+            }),
+
+            // another name for a function defined under its own name is written as that name
+            LangExp::Lambda(lambda) if lambda.name.is_some_and(|own| own != name && image.defines(own, &value)) => {
+                lambda.name.map(|own| (&mut values, define(name, LangExp::Symbol(own))))
+            }
+
+            LangExp::Lambda(lambda) if image.is_global(&lambda.env) => lambda_form(lambda).map(|lambda| {
+                // `(define (name params...) body...)`
+                let mut signature = vec![LangExp::Symbol(name)];
+                signature.extend(list_items(&lambda[1]));
+
+                let mut form = vec![LangExp::Symbol(symbol::DEFINE), LangExp::list(signature)];
+                form.extend_from_slice(&lambda[2..]);
+                (&mut functions, LangExp::list(form))
+            }),
+
+            LangExp::Lambda(lambda) => image.closure_form(lambda, &mut vec![]).map(|form| (&mut functions, define(name, form))),
+
+            _ => image.form(&value, &mut vec![]).map(|form| (&mut values, define(name, form)))
+        };
+
+        match form {
+            Some((section, form)) => section.push(form),
+            None => unsaved.push(LangExp::Symbol(name))
+        }
+    }
+
+    let mut source = HEADER.to_string();
+
+    for section in [structs, macros, functions, values] {
+        if section.is_empty() {
+            continue;
+        }
+
+        source.push('\n');
+
+        for form in section {
+            source.push_str(&form.to_pretty_string(WIDTH));
+            source.push('\n');
+        }
+    }
+
+    fs::write(path, source).map_err(|e| LangErr::Reason(format!("save-image: cannot write {}: {}", path, e)))?;
+
+    Ok(LangExp::list(unsaved))
+}
+
+struct Image {
+    global: LangEnv,
+    /// the definitions a new interpreter starts with, which the image leaves out
+    fresh: LangEnv,
+}
+
+impl Image {
+    fn is_global(&self, env: &LangEnv) -> bool {
+        Arc::ptr_eq(&env.data, &self.global.data)
+    }
+
+    /// whether `name` is bound to this very function
+    fn defines(&self, name: SymbolId, value: &LangExp) -> bool {
+        self.global.get(name).is_some_and(|bound| bound == *value)
+    }
+
+    /// a form evaluating to a value equal to `value`, if there is one. `visiting` holds the
+    /// lambdas being written, whose scopes may hold themselves
+    fn form(&self, value: &LangExp, visiting: &mut Vec<*const Vec<LangExp>>) -> Option<LangExp> {
+        if stack::exhausted() {
+            return None;
+        }
+
+        match value {
+            LangExp::Bool(_) | LangExp::Int(_) | LangExp::Number(_) | LangExp::Char(_) | LangExp::Str(_) => Some(value.clone()),
+
+            _ if is_data(value) => Some(LangExp::list(vec![LangExp::Symbol(symbol::QUOTE), value.clone()])),
+
+            LangExp::List(items) => self.call("list", &items[..], visiting),
+
+            LangExp::Vector(items) => {
+                let items = items.borrow().clone();
+                self.call("vector", &items, visiting)
+            }
+
+            LangExp::Map(map) => {
+                let pairs: Vec<LangExp> = map.iter().flat_map(|(k, v)| [k.clone(), v.clone()]).collect();
+                self.call("hash-map", &pairs, visiting)
+            }
+
+            // made again with the constructor `defstruct` defined, if that is still bound
+            LangExp::Record(record) => {
+                let constructor = SymbolId::intern(&format!("make-{}", record.kind.name));
+
+                match &self.global.get(constructor) {
+                    Some(LangExp::Lambda(lambda)) if record_type(lambda).is_some_and(|(builtin, kind)| {
+                        &*builtin == "%record-new" && Arc::ptr_eq(&kind, &record.kind)
+                    }) => {
+                        let fields = record.fields.borrow().clone();
+                        self.call(&constructor.name(), &fields, visiting)
+                    }
+                    _ => None
+                }
+            }
+
+            LangExp::Func(native) => match self.fresh.get(native.name) {
+                Some(LangExp::Func(builtin)) if builtin.f as usize == native.f as usize => Some(LangExp::Symbol(native.name)),
+                _ => None
+            },
+
+            LangExp::Lambda(lambda) if lambda.name.is_some_and(|name| self.defines(name, value)) => lambda.name.map(LangExp::Symbol),
+
+            LangExp::Lambda(lambda) => self.closure_form(lambda, visiting),
+
+            _ => None
+        }
+    }
+
+    /// `(function forms...)` with a form for each of `args`
+    fn call(&self, function: &str, args: &[LangExp], visiting: &mut Vec<*const Vec<LangExp>>) -> Option<LangExp> {
+        let mut form = vec![LangExp::Symbol(SymbolId::intern(function))];
+
+        for arg in args {
+            form.push(self.form(arg, visiting)?);
+        }
+
+        Some(LangExp::list(form))
+    }
+
+    /// the lambda, inside a `let` binding the locals it closed over. closures that shared a scope
+    /// get a copy of it each
+    fn closure_form(&self, lambda: &LangLambda, visiting: &mut Vec<*const Vec<LangExp>>) -> Option<LangExp> {
+        let body = Arc::as_ptr(&lambda.body);
+
+        if visiting.contains(&body) {
+            return None;
+        }
+
+        let form = LangExp::list(lambda_form(lambda)?);
+
+        // the innermost binding of each name is the one the lambda sees
+        let mut locals: HashMap<SymbolId, LangExp> = HashMap::new();
+        let mut scope = lambda.env.clone();
+
+        while !self.is_global(&scope) {
+            for (name, value) in scope.data.borrow().iter() {
+                locals.entry(*name).or_insert_with(|| value.clone());
+            }
+
+            scope = match &scope.outer {
+                Some(outer) => (**outer).clone(),
+                None => return None
+            };
+        }
+
+        if locals.is_empty() {
+            return Some(form);
+        }
+
+        let mut names: Vec<SymbolId> = locals.keys().copied().collect();
+        names.sort_by_key(|name| name.name());
+
+        visiting.push(body);
+        let bindings: Option<Vec<LangExp>> = names
+            .iter()
+            .map(|name| Some(LangExp::list(vec![LangExp::Symbol(*name), self.form(&locals[name], visiting)?])))
+            .collect();
+        visiting.pop();
+
+        Some(LangExp::list(vec![LangExp::Symbol(symbol::LET), LangExp::list(bindings?), form]))
+    }
+}
+
+/// `(lambda (params...) "doc" body...)`, when the defaults and body are plain data
+fn lambda_form(lambda: &LangLambda) -> Option<Vec<LangExp>> {
+    Some(lambda_source(lambda)).filter(|form| form.iter().all(is_data))
+}
+
+fn lambda_source(lambda: &LangLambda) -> Vec<LangExp> {
+    let params = &lambda.params;
+    let mut list: Vec<LangExp> = params.required.iter().map(|param| LangExp::Symbol(*param)).collect();
+
+    if !params.optional.is_empty() {
+        list.push(LangExp::Symbol(symbol::OPTIONAL));
+        list.extend(params.optional.iter().map(|(param, default)| LangExp::list(vec![LangExp::Symbol(*param), default.clone()])));
+    }
+
+    if let Some(rest) = params.rest {
+        list.push(LangExp::Symbol(symbol::REST));
+        list.push(LangExp::Symbol(rest));
+    }
+
+    let mut form = vec![LangExp::Symbol(symbol::LAMBDA), LangExp::list(list)];
+    form.extend(lambda.doc.iter().map(|doc| LangExp::Str(doc.clone())));
+    form.extend(lambda.body.iter().cloned());
+
+    form
+}
+
+/// the record builtin and type a function generated by `defstruct` wraps
+fn record_type(lambda: &LangLambda) -> Option<(Arc<str>, Arc<RecordType>)> {
+    let call = match &lambda.body[..] {
+        [LangExp::List(call)] => call,
+        _ => return None
+    };
+
+    let builtin = match call.first() {
+        Some(LangExp::Symbol(builtin)) if builtin.name().starts_with("%record-") => builtin.name(),
+        _ => return None
+    };
+
+    match call.get(1) {
+        Some(LangExp::List(quoted)) => match &quoted[..] {
+            [LangExp::Symbol(quote), LangExp::Record(prototype)] if *quote == symbol::QUOTE => Some((builtin, prototype.kind.clone())),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// whether `name` is bound by the interpreter or the `lisp` binary for the session at hand: the
+/// repl's results `$1`, `$2`..., `$_`, `*command-line-args*` and `*last-backtrace*`. the session
+/// starting from the image binds its own
+fn is_session_state(name: SymbolId) -> bool {
+    let name = name.name();
+
+    match name.strip_prefix('$') {
+        Some(n) => n == "_" || (!n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())),
+        None => &*name == "*command-line-args*" || &*name == "*last-backtrace*"
+    }
+}
+
+/// whether a binding in the image would define the same thing a new interpreter starts with
+fn same_definition(value: &LangExp, builtin: &LangExp) -> bool {
+    match (value, builtin) {
+        // the functions `defstruct` generates print the record type quoted into them as an instance
+        (LangExp::Lambda(a), LangExp::Lambda(b)) | (LangExp::Macro(a), LangExp::Macro(b)) => {
+            let source = |lambda: &LangLambda| LangExp::list(lambda_source(lambda)).to_string();
+            source(a) == source(b)
+        }
+        _ => value == builtin
+    }
+}
+
+/// `(define name form)`
+fn define(name: SymbolId, form: LangExp) -> LangExp {
+    LangExp::list(vec![LangExp::Symbol(symbol::DEFINE), LangExp::Symbol(name), form])
+}
+
+fn list_items(exp: &LangExp) -> Vec<LangExp> {
+    match exp {
+        LangExp::List(items) => items.to_vec(),
+        _ => vec![]
+    }
+}
+
+/// whether `exp` reads back as itself from its printed form
+fn is_data(exp: &LangExp) -> bool {
+    match exp {
+        LangExp::Symbol(symbol) => symbol.is_interned(),
+        LangExp::Bool(_) | LangExp::Int(_) | LangExp::Number(_) | LangExp::Char(_) | LangExp::Str(_) => true,
+        LangExp::List(items) => items.iter().all(is_data),
+        LangExp::Vector(items) => items.borrow().iter().all(is_data),
+        LangExp::Map(map) => map.iter().all(|(k, v)| is_data(k) && is_data(v)),
+        _ => false
+    }
+}
@@ -0,0 +1,247 @@
+use std::{io, mem};
+
+use crate::builtins::default_env;
+use crate::console::{self, Console};
+use crate::debugger;
+use crate::env::LangEnv;
+use crate::eval::{eval, SPECIAL_FORMS};
+use crate::exp::{Arity, LangErr, LangExp, Native, NativeFn};
+use crate::reader::{check_macro_prefix, find_symbol, read_all, tokenize, Form, ReaderFn, ReaderMacro, SourceGuard, SpannedToken};
+use crate::limits::{LimitGuard, Limits};
+use crate::optimizer::optimize;
+use crate::stack::StackGuard;
+use crate::symbol::SymbolId;
+use crate::vm;
+
+/// the lisp variable the backtrace of the last failed `eval_str` is bound to, one string per call
+const LAST_BACKTRACE: &str = "*last-backtrace*";
+
+/// how an [`Interpreter`] runs code. both give the same results; the tree-walker is the reference
+/// implementation, the bytecode backend compiles each form for a stack machine and is faster on hot loops.
+/// `(break)` pauses on both, but stepping on from there only stops at forms the tree-walker evaluates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    TreeWalker,
+    Bytecode,
+}
+
+/// an interpreter session: a global environment preloaded with the builtins
+pub struct Interpreter {
+    env: LangEnv,
+    backend: Backend,
+    stack_limit: Option<usize>,
+    limits: Limits,
+    optimizing: bool,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_backend(Backend::default())
+    }
+
+    pub fn with_backend(backend: Backend) -> Interpreter {
+        let env = default_env();
+        env.insert(SymbolId::intern(LAST_BACKTRACE), LangExp::nil());
+
+        Interpreter { env, backend, stack_limit: None, limits: Limits::default(), optimizing: false }
+    }
+
+    /// how many bytes of the calling thread's stack evaluation may use before deep recursion is
+    /// reported as an error. by default it is most of what the thread has left, where that can be
+    /// found out (on linux), and 1 MiB, which fits any thread, elsewhere
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        self.stack_limit = Some(bytes);
+    }
+
+    /// bounds what each later `eval_str` may do, for running code that can't be trusted to stop or
+    /// to stay away from the file system. going past a limit fails with [`LangErr::LimitExceeded`]
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// runs each top-level form of later `eval_str` calls through the optimizer before evaluating
+    /// it, see `(optimize form)`. the forms evaluate the same either way: a call of a symbol that
+    /// isn't bound yet, which may be a macro defined by a later form, is left as it is, and so are
+    /// the symbols after it in the same lambda or let. off by default; the `lisp` binary turns it
+    /// on for the files it runs
+    pub fn set_optimizing(&mut self, on: bool) {
+        self.optimizing = on;
+    }
+
+    /// reads and evaluates every form in `source`, returning the value of the last one.
+    /// errors carry a span into `source` and the lisp calls they propagated through, see
+    /// [`LangErr::render`]; the calls are also bound to `*last-backtrace*`
+    pub fn eval_str(&mut self, source: &str) -> Result<LangExp, LangErr> {
+        let _guard = StackGuard::enter(self.stack_limit);
+        let _limits = LimitGuard::enter(self.limits);
+        let _stepping = debugger::StepGuard::enter();
+
+        let res = match self.backend {
+            Backend::TreeWalker => eval_source_with(source, &self.env, eval, self.optimizing),
+            Backend::Bytecode => eval_source_with(source, &self.env, vm::eval, self.optimizing)
+        };
+
+        if let Err(err) = &res {
+            let frames = err.backtrace().iter().map(|frame| LangExp::Str(frame.to_string().into())).collect();
+            self.env.insert(SymbolId::intern(LAST_BACKTRACE), LangExp::list(frames));
+        }
+
+        res
+    }
+
+    /// like [`eval_str`](Interpreter::eval_str), but returns what the repl would show: the printed
+    /// value of the last form, or the rendered error. meant for front-ends that only deal in text,
+    /// such as a web page calling into a wasm build
+    pub fn eval_to_string(&mut self, source: &str) -> String {
+        match self.eval_str(source) {
+            Ok(res) => res.to_string(),
+            Err(err) => err.render(source)
+        }
+    }
+
+    /// sends what `print` and `println` write to `sink` instead of stdout
+    pub fn set_output(&mut self, sink: impl FnMut(&str) -> io::Result<()> + Send + 'static) {
+        console::lock(&self.env.console).output = Box::new(sink);
+    }
+
+    /// makes `read-line` take its lines from `source` instead of stdin
+    pub fn set_input(&mut self, source: impl FnMut() -> io::Result<Option<String>> + Send + 'static) {
+        console::lock(&self.env.console).input = Box::new(source);
+    }
+
+    /// turns the profiler on or off. while it is on, every call of a builtin or a named lambda is
+    /// counted and timed; see [`profile_report`](Interpreter::profile_report)
+    pub fn set_profiling(&mut self, on: bool) {
+        self.env.profiler.set_enabled(on);
+    }
+
+    /// a table of the calls profiled so far with their count and cumulative time, the slowest first.
+    /// a call's time includes the calls it makes, up to a tail call, which ends it
+    pub fn profile_report(&self) -> String {
+        self.env.profiler.report()
+    }
+
+    /// forgets the calls profiled so far
+    pub fn clear_profile(&mut self) {
+        self.env.profiler.clear();
+    }
+
+    /// how many tests failed the last time `(run-tests)` ran, 0 if it hasn't
+    pub fn failed_tests(&self) -> usize {
+        self.env.tests.failed()
+    }
+
+    /// starts over with a fresh global environment, dropping every definition and registered native.
+    /// the backend, stack limit, limits, output and input are kept, and so is whether profiling and
+    /// optimizing are on
+    pub fn reset(&mut self) {
+        let stack_limit = self.stack_limit;
+        let limits = self.limits;
+        let optimizing = self.optimizing;
+        let profiling = self.env.profiler.enabled();
+        let console = mem::replace(&mut *console::lock(&self.env.console), Console::stdio());
+
+        *self = Interpreter::with_backend(self.backend);
+        self.stack_limit = stack_limit;
+        self.limits = limits;
+        self.optimizing = optimizing;
+        self.set_profiling(profiling);
+        *console::lock(&self.env.console) = console;
+    }
+
+    /// makes a rust function callable from lisp under `name`. calls with a number of arguments
+    /// `arity` doesn't allow fail before reaching `f`
+    pub fn register_native(&mut self, name: &str, arity: Arity, f: NativeFn) {
+        let name = SymbolId::intern(name);
+        self.env.insert(name, LangExp::Func(Native { name, arity, f }));
+    }
+
+    /// makes the reader call `f` on the form after `prefix` and read what it returns in its place,
+    /// from the next top-level form on. `prefix` is recognised where a token starts; one ending in
+    /// an opening bracket, like `#{`, leaves the bracket to start the form after it
+    pub fn set_reader_macro(&mut self, prefix: &str, f: ReaderFn) -> Result<(), LangErr> {
+        check_macro_prefix(prefix)?;
+        self.env.readtable.borrow_mut().set(prefix, ReaderMacro::Native(f));
+
+        Ok(())
+    }
+
+    /// every name code typed at the top level can refer to: the global bindings and the special forms,
+    /// sorted. the repl completes symbols from these
+    pub fn symbol_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.env.names().iter().chain(SPECIAL_FORMS).map(|name| name.to_string()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// the global environment, for reading or binding values from rust
+    pub fn env(&self) -> &LangEnv {
+        &self.env
+    }
+}
+
+/// evaluates the forms of `source` one after another in `env` with the tree-walker, also used by `load`
+pub(crate) fn eval_source(source: &str, env: &LangEnv) -> Result<LangExp, LangErr> {
+    eval_source_with(source, env, eval, false)
+}
+
+fn eval_source_with(source: &str, env: &LangEnv, evaluate: fn(&LangExp, &LangEnv) -> Result<LangExp, LangErr>, optimizing: bool) -> Result<LangExp, LangErr> {
+    let _source = SourceGuard::enter();
+
+    // a copy, so reader macros that run lisp code can change the table while it is being read with
+    let mut table = env.readtable.borrow().clone();
+    let mut tokens = tokenize(source, &table)?;
+    let mut forms = read_all(&tokens, &table)?.into_iter();
+    let mut evaluated_exp = LangExp::nil();
+
+    while let Some(form) = forms.next() {
+        // each form is optimized just before it runs, so it sees the macros and builtins defined so far
+        let exp = if optimizing { optimize(&form.exp, env) } else { form.exp.clone() };
+
+        evaluated_exp = evaluate(&exp, env)
+            .map_err(|e| locate(e, &form, &tokens))?;
+
+        // a reader macro set by this form applies to the forms after it, which are read again
+        if env.readtable.borrow().version() != table.version() {
+            let end = tokens[form.tokens.end - 1].span;
+
+            table = env.readtable.borrow().clone();
+            tokens = tokenize(source, &table)?;
+
+            let first = tokens.iter().position(|t| (t.span.line, t.span.column) > (end.line, end.column));
+            tokens = tokens.split_off(first.unwrap_or(tokens.len()));
+            forms = read_all(&tokens, &table)?.into_iter();
+        }
+    }
+
+    Ok(evaluated_exp)
+}
+
+/// points an evaluation error at the offending symbol when we can find it, the first one by that
+/// name from the innermost form the error was located at. errors without a location get the
+/// whole form
+fn locate(err: LangErr, form: &Form, tokens: &[SpannedToken]) -> LangErr {
+    let tokens = &tokens[form.tokens.clone()];
+
+    let symbol = match (err.inner(), err.span()) {
+        (LangErr::UnboundSymbol(name), Some(within)) => {
+            let from = tokens.iter().position(|t| (t.span.line, t.span.column) >= (within.line, within.column));
+            find_symbol(&tokens[from.unwrap_or(0)..], name)
+        }
+        (LangErr::UnboundSymbol(name), None) => find_symbol(tokens, name),
+        _ => None
+    };
+
+    match symbol {
+        Some(span) => err.relocated(span),
+        None => err.at(form.span)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use crate::exp::{LangErr, LangExp, LangMap};
+use crate::stack;
+
+/// reads a JSON document. objects become maps with string keys, arrays become lists, `null`
+/// becomes the empty list, and numbers become integers unless they have a fraction or an exponent
+/// or don't fit in one
+pub(crate) fn parse(text: &str) -> Result<LangExp, LangErr> {
+    let mut parser = Parser { text, pos: 0 };
+
+    let value = parser.value()?;
+    parser.skip_whitespace();
+
+    if parser.pos < text.len() {
+        return Err(parser.error("unexpected text after the value"));
+    }
+
+    Ok(value)
+}
+
+/// writes a value as compact JSON, the reverse of `parse`. lists and vectors become arrays, maps
+/// become objects whose keys must be strings or symbols, and symbols are written as strings
+pub(crate) fn encode(exp: &LangExp) -> Result<String, LangErr> {
+    let mut out = String::new();
+    encode_into(exp, &mut out)?;
+
+    Ok(out)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// byte offset of the next character to read
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> LangErr {
+        LangErr::Parse(format!("invalid json at byte {}: {}", self.pos, msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// consumes `word` if the text continues with it
+    fn eat(&mut self, word: &str) -> bool {
+        let found = self.text[self.pos..].starts_with(word);
+
+        if found {
+            self.pos += word.len();
+        }
+
+        found
+    }
+
+    fn value(&mut self) -> Result<LangExp, LangErr> {
+        stack::check()?;
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(LangExp::Str(self.string()?.into())),
+            Some('-' | '0'..='9') => self.number(),
+            _ if self.eat("true") => Ok(LangExp::Bool(true)),
+            _ if self.eat("false") => Ok(LangExp::Bool(false)),
+            _ if self.eat("null") => Ok(LangExp::nil()),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input"))
+        }
+    }
+
+    fn object(&mut self) -> Result<LangExp, LangErr> {
+        self.pos += 1;
+
+        let mut map = LangMap::default();
+
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(LangExp::Map(Arc::new(map)));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+
+            let key = self.string()?;
+
+            self.skip_whitespace();
+            if !self.eat(":") {
+                return Err(self.error("expected `:` after the key"));
+            }
+
+            let value = self.value()?;
+            map.insert(LangExp::Str(key.into()), value);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(LangExp::Map(Arc::new(map))),
+                _ => return Err(self.error("expected `,` or `}` in an object"))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<LangExp, LangErr> {
+        self.pos += 1;
+
+        let mut items = vec![];
+
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(LangExp::list(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(LangExp::list(items)),
+                _ => return Err(self.error("expected `,` or `]` in an array"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, LangErr> {
+        self.pos += 1;
+
+        let mut s = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(self.escape()?),
+                Some(c) if c < ' ' => return Err(self.error("control character in a string")),
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string"))
+            }
+        }
+    }
+
+    /// the character an escape sequence stands for, after its backslash
+    fn escape(&mut self) -> Result<char, LangErr> {
+        let c = match self.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let unit = self.hex4()?;
+
+                // characters outside the basic plane are written as a pair of surrogates
+                let code = if (0xd800..0xdc00).contains(&unit) && self.eat("\\u") {
+                    let low = self.hex4()?;
+
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("invalid surrogate pair"));
+                    }
+
+                    0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    unit
+                };
+
+                return char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"));
+            }
+            _ => return Err(self.error("unknown escape sequence"))
+        };
+
+        Ok(c)
+    }
+
+    fn hex4(&mut self) -> Result<u32, LangErr> {
+        let digits = self.text.get(self.pos..self.pos + 4).filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()));
+
+        match digits {
+            Some(digits) => {
+                self.pos += 4;
+                Ok(u32::from_str_radix(digits, 16).expect("checked to be hex digits"))
+            }
+            None => Err(self.error("expected four hex digits after `\\u`"))
+        }
+    }
+
+    fn number(&mut self) -> Result<LangExp, LangErr> {
+        let start = self.pos;
+        self.eat("-");
+
+        match self.peek() {
+            Some('0') => self.pos += 1,
+            Some('1'..='9') => self.digits(),
+            _ => return Err(self.error("expected a digit"))
+        }
+
+        let mut is_int = true;
+
+        if self.eat(".") {
+            is_int = false;
+
+            if !matches!(self.peek(), Some('0'..='9')) {
+                return Err(self.error("expected a digit after `.`"));
+            }
+            self.digits();
+        }
+
+        if self.eat("e") || self.eat("E") {
+            is_int = false;
+
+            if !self.eat("+") {
+                self.eat("-");
+            }
+
+            if !matches!(self.peek(), Some('0'..='9')) {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+            self.digits();
+        }
+
+        let literal = &self.text[start..self.pos];
+
+        match literal.parse::<i64>() {
+            Ok(i) if is_int => Ok(LangExp::Int(i)),
+            _ => Ok(LangExp::Number(literal.parse().expect("valid json numbers are valid floats")))
+        }
+    }
+
+    fn digits(&mut self) {
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+}
+
+fn encode_into(exp: &LangExp, out: &mut String) -> Result<(), LangErr> {
+    stack::check()?;
+
+    match exp {
+        LangExp::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+
+        LangExp::Int(i) => out.push_str(&i.to_string()),
+
+        LangExp::Number(n) if n.is_finite() => out.push_str(&exp.to_string()),
+
+        LangExp::Number(_) => return Err(LangErr::Reason(format!("cannot encode {} as json", exp))),
+
+        LangExp::Str(s) => encode_string(s, out),
+
+        // json has no characters, a one-character string is the closest
+        LangExp::Char(c) => encode_string(&c.to_string(), out),
+
+        LangExp::Symbol(s) => encode_string(&s.name(), out),
+
+        LangExp::List(items) => encode_array(items, out)?,
+
+        LangExp::Vector(items) => encode_array(&items.borrow(), out)?,
+
+        LangExp::Map(map) => {
+            out.push('{');
+
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                match k {
+                    LangExp::Str(s) => encode_string(s, out),
+                    LangExp::Symbol(s) => encode_string(&s.name(), out),
+                    _ => return Err(LangErr::Type { expected: "a string or symbol key".to_string(), got: k.type_name().to_string() })
+                }
+
+                out.push(':');
+                encode_into(v, out)?;
+            }
+
+            out.push('}');
+        }
+
+        LangExp::Func(_) | LangExp::Lambda(_) | LangExp::Macro(_) | LangExp::Record(_) | LangExp::Promise(_)
+        | LangExp::Thread(_) | LangExp::Channel(_) =>
+            return Err(LangErr::Reason(format!("cannot encode a {} as json", exp.type_name())))
+    }
+
+    Ok(())
+}
+
+fn encode_array(items: &[LangExp], out: &mut String) -> Result<(), LangErr> {
+    out.push('[');
+
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        encode_into(item, out)?;
+    }
+
+    out.push(']');
+
+    Ok(())
+}
+
+fn encode_string(s: &str, out: &mut String) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+
+    out.push('"');
+}
@@ -0,0 +1,78 @@
+//! A small lisp interpreter that can be embedded into other rust programs as a scripting layer.
+//!
+//! Create an [`Interpreter`], optionally register your own natives with
+//! [`Interpreter::register_native`], then feed it source text with [`Interpreter::eval_str`].
+//!
+//! The library itself never touches the terminal: `print` and `read-line` go through the sink and
+//! source set with [`Interpreter::set_output`] and [`Interpreter::set_input`], stdout and stdin by
+//! default. That, together with [`Interpreter::eval_to_string`], is all a `wasm32-unknown-unknown`
+//! build needs to be driven from JavaScript, e.g. with a wasm-bindgen wrapper along these lines:
+//!
+//! ```ignore
+//! #[wasm_bindgen]
+//! pub struct Repl(lisp::Interpreter);
+//!
+//! #[wasm_bindgen]
+//! impl Repl {
+//!     #[wasm_bindgen(constructor)]
+//!     pub fn new(print: js_sys::Function) -> Repl {
+//!         let mut interpreter = lisp::Interpreter::new();
+//!         interpreter.set_output(move |text| {
+//!             let _ = print.call1(&JsValue::NULL, &JsValue::from_str(text));
+//!             Ok(())
+//!         });
+//!         Repl(interpreter)
+//!     }
+//!
+//!     pub fn eval(&mut self, source: &str) -> String {
+//!         self.0.eval_to_string(source)
+//!     }
+//! }
+//! ```
+
+// vectors are mutable, but hash their contents when used as map keys, see `impl Hash for LangExp`
+#![allow(clippy::mutable_key_type)]
+
+mod builtins;
+mod compiler;
+mod console;
+mod debugger;
+mod env;
+mod eval;
+mod exp;
+mod image;
+mod interpreter;
+mod json;
+mod limits;
+mod list;
+mod lock;
+mod num;
+mod optimizer;
+mod pretty;
+mod profile;
+mod random;
+mod reader;
+mod stack;
+mod symbol;
+mod testing;
+mod threads;
+mod trace;
+mod vm;
+
+pub use console::{InputSource, OutputSink};
+pub use env::LangEnv;
+pub use exp::{write, Arity, LangErr, LangExp, LangLambda, LangMap, LangRecord, Native, NativeFn, Promise, RecordType, Span};
+pub use interpreter::{Backend, Interpreter};
+pub use limits::Limits;
+pub use list::LangList;
+pub use lock::Lock;
+pub use symbol::SymbolId;
+pub use threads::{Channel, ThreadHandle};
+pub use trace::TraceFrame;
+pub use reader::{is_incomplete, read_str, ReaderFn};
+
+/// a lisp value, as returned by [`Interpreter::eval_str`]
+pub type Value = LangExp;
+
+/// an error raised while reading or evaluating lisp source
+pub type Error = LangErr;
@@ -0,0 +1,173 @@
+use std::cell::Cell;
+
+use crate::exp::{LangErr, LangExp};
+
+/// guardrails for evaluating untrusted code, see [`Interpreter::set_limits`](crate::Interpreter::set_limits).
+/// the default sets no limits and allows io
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// how many steps one `eval_str` may take: the tree-walker spends one per form it evaluates,
+    /// the bytecode backend one per instruction
+    pub fuel: Option<u64>,
+    /// how many lambda calls may be running at once, which bounds recursion that isn't in tail
+    /// position. a tail call takes the place of the call it is made from
+    pub depth: Option<usize>,
+    /// the most elements a list, vector or map, or characters a string, a builtin may return
+    pub size: Option<usize>,
+    /// whether the builtins that touch files, stdin or the process (`slurp`, `spit`, `save-image`,
+    /// `read-line`, `load`, `require`, `getenv`, `setenv`, `sh` and `exit`) are available, and
+    /// `spawn`, whose threads could outlive the evaluation. `print` still works, it goes to the
+    /// interpreter's output sink
+    pub io: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits { fuel: None, depth: None, size: None, io: true }
+    }
+}
+
+/// the limits of the running evaluation and how much of them is used up
+#[derive(Clone, Copy)]
+struct Budget {
+    limits: Limits,
+    fuel_left: Option<u64>,
+    depth: usize,
+}
+
+thread_local! {
+    static BUDGET: Cell<Option<Budget>> = const { Cell::new(None) };
+}
+
+/// applies `f` to the budget in force, if there is one that limits anything
+fn update(f: impl FnOnce(&mut Budget) -> Result<(), LangErr>) -> Result<(), LangErr> {
+    BUDGET.with(|budget| match budget.get() {
+        Some(mut current) if current.limits != Limits::default() => {
+            let res = f(&mut current);
+            budget.set(Some(current));
+            res
+        }
+        _ => Ok(())
+    })
+}
+
+/// puts `limits` in force for an evaluation with a full tank of fuel, until the guard is dropped and
+/// whatever was in force before is restored
+pub(crate) struct LimitGuard {
+    previous: Option<Budget>,
+}
+
+impl LimitGuard {
+    pub(crate) fn enter(limits: Limits) -> LimitGuard {
+        let budget = Budget { limits, fuel_left: limits.fuel, depth: 0 };
+
+        LimitGuard { previous: BUDGET.with(|current| current.replace(Some(budget))) }
+    }
+}
+
+impl Drop for LimitGuard {
+    fn drop(&mut self) {
+        BUDGET.with(|budget| budget.set(self.previous));
+    }
+}
+
+/// the limits in force with the fuel that is left, for a thread started by `spawn` to run under
+pub(crate) fn remaining() -> Option<Limits> {
+    BUDGET.with(|budget| budget.get().map(|budget| Limits { fuel: budget.fuel_left, ..budget.limits }))
+}
+
+/// spends one step of fuel
+pub(crate) fn step() -> Result<(), LangErr> {
+    update(|budget| match &mut budget.fuel_left {
+        Some(0) => Err(LangErr::LimitExceeded(format!("ran out of fuel after {} steps", budget.limits.fuel.unwrap_or(0)))),
+        Some(left) => {
+            *left -= 1;
+            Ok(())
+        }
+        None => Ok(())
+    })
+}
+
+/// goes one call deeper, failing when that is past the depth limit. the depth is only tracked
+/// when there is a limit on it
+pub(crate) fn descend() -> Result<(), LangErr> {
+    update(|budget| match budget.limits.depth {
+        Some(max) if budget.depth >= max => Err(LangErr::LimitExceeded(format!("calls nested more than {} deep", max))),
+        Some(_) => {
+            budget.depth += 1;
+            Ok(())
+        }
+        None => Ok(())
+    })
+}
+
+/// a level gone down with `descend` for as long as the guard is kept
+pub(crate) struct Level;
+
+impl Level {
+    pub(crate) fn enter() -> Result<Level, LangErr> {
+        descend()?;
+        Ok(Level)
+    }
+
+    /// the guard for a level already gone down with `descend`
+    pub(crate) fn taken_over() -> Level {
+        Level
+    }
+}
+
+impl Drop for Level {
+    fn drop(&mut self) {
+        ascend();
+    }
+}
+
+/// comes back up a level gone down with `descend`
+pub(crate) fn ascend() {
+    let _ = update(|budget| {
+        budget.depth = budget.depth.saturating_sub(1);
+        Ok(())
+    });
+}
+
+/// the current depth, to come back to with `restore_depth` when levels are left in bulk
+pub(crate) fn depth() -> usize {
+    BUDGET.with(|budget| budget.get().map_or(0, |budget| budget.depth))
+}
+
+pub(crate) fn restore_depth(depth: usize) {
+    let _ = update(|budget| {
+        budget.depth = depth;
+        Ok(())
+    });
+}
+
+/// fails when `exp` is bigger than the size limit allows
+pub(crate) fn check_size(exp: &LangExp) -> Result<(), LangErr> {
+    let max = match BUDGET.with(|budget| budget.get().and_then(|budget| budget.limits.size)) {
+        Some(max) => max,
+        None => return Ok(())
+    };
+
+    let (size, unit) = match exp {
+        LangExp::List(items) => (items.len(), "elements"),
+        LangExp::Vector(items) => (items.borrow().len(), "elements"),
+        LangExp::Map(map) => (map.len(), "entries"),
+        LangExp::Str(s) => (s.chars().count(), "characters"),
+        _ => return Ok(())
+    };
+
+    if size > max {
+        return Err(LangErr::LimitExceeded(format!("a {} of {} {} is over the size limit of {}", exp.type_name(), size, unit, max)));
+    }
+
+    Ok(())
+}
+
+/// fails when io is turned off, naming the builtin or form `name` that attempted it
+pub(crate) fn check_io(name: &str) -> Result<(), LangErr> {
+    match BUDGET.with(|budget| budget.get()) {
+        Some(budget) if !budget.limits.io => Err(LangErr::Reason(format!("`{}` is disabled: this interpreter doesn't allow io", name))),
+        _ => Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use std::cell::UnsafeCell;
+use std::iter;
+use std::mem::{self, MaybeUninit};
+use std::ops::Deref;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+
+use crate::exp::LangExp;
+
+/// the elements of a lisp list. it derefs to a slice, but `cons` and `cdr` share the elements of
+/// the list they are given instead of copying them: a list is the tail of a buffer, from `start`
+/// on, and the buffer is filled in from the back, so consing onto a list usually just writes the
+/// free slot in front of it. consing onto a list that another `cons` already wrote the slot in
+/// front of copies it instead
+#[derive(Clone)]
+pub struct LangList {
+    buffer: Arc<Buffer>,
+    start: usize,
+}
+
+/// the slots from `front` on are initialized and never change again. the ones before it are free,
+/// and the list starting at `front` may claim the last of them to cons onto itself
+struct Buffer {
+    slots: Box<[UnsafeCell<MaybeUninit<LangExp>>]>,
+    front: AtomicUsize,
+}
+
+// SAFETY: a slot is only written by the thread that claimed it by moving `front` past it, before
+// any list that includes it exists, and never written again. every other access is a read of
+// initialized slots
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+/// the fewest free slots a buffer is made with when `cons` has to copy
+const MIN_ROOM: usize = 4;
+
+impl Buffer {
+    /// a buffer holding `items` at its back, behind `room` free slots
+    fn new(room: usize, items: impl ExactSizeIterator<Item = LangExp>) -> Buffer {
+        let slots = (0..room + items.len()).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect::<Box<[_]>>();
+
+        for (slot, item) in slots[room..].iter().zip(items) {
+            // SAFETY: the buffer isn't shared yet
+            unsafe { (*slot.get()).write(item) };
+        }
+
+        Buffer { slots, front: AtomicUsize::new(room) }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let front = *self.front.get_mut();
+
+        for slot in self.slots[front..].iter_mut() {
+            // SAFETY: the slots from `front` on are initialized, and dropped only here
+            unsafe { slot.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl LangList {
+    /// the list of `items`
+    pub fn new(items: Vec<LangExp>) -> LangList {
+        LangList { buffer: Arc::new(Buffer::new(0, items.into_iter())), start: 0 }
+    }
+
+    /// `head` followed by the elements of `self`
+    pub fn cons(&self, head: LangExp) -> LangList {
+        let start = self.start;
+
+        let claimed = start > 0
+            && self.buffer.front.compare_exchange(start, start - 1, Ordering::AcqRel, Ordering::Relaxed).is_ok();
+
+        if claimed {
+            // SAFETY: moving `front` past the slot made this call the only one that writes it, and
+            // no list reads it until the one returned here
+            unsafe { (*self.buffer.slots[start - 1].get()).write(head) };
+
+            return LangList { buffer: self.buffer.clone(), start: start - 1 };
+        }
+
+        // copied into a buffer with as much room in front as there are elements, so a list built up
+        // one cons at a time is copied a logarithmic number of times
+        let items = iter::once(head).chain(self.iter().cloned());
+        let len = self.len() + 1;
+
+        LangList { buffer: Arc::new(Buffer::new(len.max(MIN_ROOM), ExactLen(items, len))), start: len.max(MIN_ROOM) }
+    }
+
+    /// the list without its first element, or `None` if it is empty
+    pub fn rest(&self) -> Option<LangList> {
+        (!self.is_empty()).then(|| LangList { buffer: self.buffer.clone(), start: self.start + 1 })
+    }
+
+    /// identity: the same elements of the same buffer
+    pub fn ptr_eq(a: &LangList, b: &LangList) -> bool {
+        Arc::ptr_eq(&a.buffer, &b.buffer) && a.start == b.start
+    }
+
+    /// tells lists apart for as long as they are alive, see `WeakList`
+    pub(crate) fn address(&self) -> (usize, usize) {
+        (Arc::as_ptr(&self.buffer) as usize, self.start)
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakList {
+        WeakList(Arc::downgrade(&self.buffer))
+    }
+
+    /// the elements, if no other list shares them, leaving the list empty. used to free deeply
+    /// nested lists without recursing
+    pub(crate) fn take_unique(&mut self) -> Option<Vec<LangExp>> {
+        let buffer = Arc::get_mut(&mut self.buffer)?;
+        let len = buffer.slots.len();
+        let front = mem::replace(buffer.front.get_mut(), len);
+
+        let items = buffer.slots[front..]
+            .iter_mut()
+            // SAFETY: the slots from the old `front` on are initialized, and no longer counted as
+            // such, so each is moved out once
+            .map(|slot| unsafe { slot.get_mut().assume_init_read() })
+            .collect();
+
+        self.start = len;
+        Some(items)
+    }
+}
+
+/// an iterator whose length is known up front, which `Buffer::new` needs
+struct ExactLen<I>(I, usize);
+
+impl<I: Iterator> Iterator for ExactLen<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.1, Some(self.1))
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for ExactLen<I> {}
+
+/// holds on to the allocation of a list's buffer, so its address isn't reused by another list,
+/// without keeping the elements alive
+pub(crate) struct WeakList(Weak<Buffer>);
+
+impl WeakList {
+    pub(crate) fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+}
+
+impl Deref for LangList {
+    type Target = [LangExp];
+
+    fn deref(&self) -> &[LangExp] {
+        let slots = &self.buffer.slots[self.start..];
+
+        // SAFETY: a list only covers initialized slots, which never change, and
+        // `UnsafeCell<MaybeUninit<LangExp>>` has the layout of `LangExp`
+        unsafe { slice::from_raw_parts(slots.as_ptr() as *const LangExp, slots.len()) }
+    }
+}
+
+impl Default for LangList {
+    fn default() -> LangList {
+        LangList::new(vec![])
+    }
+}
+
+impl From<Vec<LangExp>> for LangList {
+    fn from(items: Vec<LangExp>) -> LangList {
+        LangList::new(items)
+    }
+}
+
+impl<'a> IntoIterator for &'a LangList {
+    type Item = &'a LangExp;
+    type IntoIter = slice::Iter<'a, LangExp>;
+
+    fn into_iter(self) -> slice::Iter<'a, LangExp> {
+        self.iter()
+    }
+}
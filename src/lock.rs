@@ -0,0 +1,40 @@
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// the interior mutability behind vectors, records, promises and scopes. it has the interface of a
+/// `RefCell`, but is a read-write lock so values can be shared with threads started by `spawn`.
+/// unlike a `RefCell` it waits for a conflicting borrow on another thread rather than panicking.
+///
+/// it isn't reentrant: a thread asking for a borrow that conflicts with one it holds itself waits
+/// forever, and so may a second shared borrow while another thread waits for exclusive access. a
+/// guard must therefore never be held while lisp code runs, since that code can reach the same
+/// value. copy the contents out first when they are to be evaluated, as `eval_atom` does
+pub struct Lock<T>(RwLock<T>);
+
+impl<T> Lock<T> {
+    pub fn new(value: T) -> Lock<T> {
+        Lock(RwLock::new(value))
+    }
+
+    /// shared access, for as long as the guard lives
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        // a thread that panicked while holding the lock can't have left lisp data half updated in
+        // a way that matters more than losing the value, so poisoning is ignored
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// exclusive access, for as long as the guard lives
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// exclusive access through a unique reference, which needs no locking
+    pub fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Default> Default for Lock<T> {
+    fn default() -> Lock<T> {
+        Lock::new(T::default())
+    }
+}
@@ -1,212 +1,215 @@
-use std::collections::HashMap;
-use std::num::ParseFloatError;
-use std::{fmt, io};
-
-#[derive(Clone)]
-enum LangExp {
-    Symbol(String),
-    Number(f64),
-    List(Vec<LangExp>),
-    Func(fn(&[LangExp]) -> Result<LangExp, LangErr>),
-}
+mod commands;
+mod editor;
 
-#[derive(Debug)]
-enum LangErr {
-    Reason(String),
-}
+use std::{env, fs, process, thread};
 
-#[derive(Clone)]
-struct LangEnv {
-    data: HashMap<String, LangExp>
-}
+use commands::{run_command, show_result, Outcome, Settings};
+use editor::{Editor, ReadResult};
+use lisp::{Interpreter, LangExp, SymbolId};
 
-fn tokenize(exp: String) -> Vec<String> {
-    exp
-        .replace("(", " ( ") //range
-        .replace(")", " ( ")
-        .split_whitespace()
-        .map(|x| x.to_string())
-        .collect()
-}
+const USAGE: &str = "usage: lisp [--image file] [file.lisp args... | -e expression]";
 
-//noinspection RsNeedlessLifetimes
-/// parse and transform an individual token from an iterator
-fn parse<'a>(tokens: &'a [String]) -> Result<(LangExp, &'a [String]), LangErr> {
-    let (token, rest) = tokens.split_first()
-        .ok_or(LangErr::Reason("could not get token".to_string()))?;
+/// the interpreter runs on a thread with a stack this big, so lisp code can recurse deeply
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
-    match &token[..] {
-        "(" => read_seq(rest),
-        ")" => Err(LangErr::Reason("unexpected `)`".to_string())),
-        _ => Ok((parse_atom(token), rest))
-    }
+/// an interpreter allowed most of the big stack, keeping some headroom for printing results
+fn new_interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_stack_limit(STACK_SIZE / 8 * 7);
+    interpreter
 }
 
-//noinspection RsNeedlessLifetimes
-/// gets token for a current position. starts reading a list and parsing tokens from "(" until ")"
-fn read_seq<'a>(tokens: &'a [String]) -> Result<(LangExp, &'a [String]), LangErr> {
-    let mut res: Vec<LangExp> = vec![];
-
-    let mut xs = tokens;
+/// reads lines until they form complete expressions, showing a continuation prompt in between.
+/// returns `None` once input is exhausted
+fn slurp_exp(editor: &mut Editor) -> Option<String> {
+    let mut exp = String::new();
+    let mut prompt = "lang > ";
 
     loop {
-        let (next_token, rest) = xs
-            .split_first()
-            .ok_or(LangErr::Reason("could not find closing `)`".to_string()))
-            ?;
+        match editor.read_line(prompt) {
+            ReadResult::Line(line) => {
+                exp.push_str(&line);
+                exp.push('\n');
+
+                if !lisp::is_incomplete(&exp) {
+                    return Some(exp);
+                }
+
+                prompt = "  ... ";
+            }
+
+            ReadResult::Interrupted => {
+                exp.clear();
+                prompt = "lang > ";
+            }
 
-        if next_token == ")" {
-            return Ok((LangExp::List(res), rest)); // skip ")", head to the token after
+            ReadResult::Eof => return None
         }
     }
 }
 
-fn parse_atom(token: &str) -> LangExp {
-    let potential_float: Result<f64, ParseFloatError> = token.parse();
+/// binds `*command-line-args*` to the script and the arguments after it, as strings. only the
+/// binary binds it, so interpreters embedded elsewhere don't see the process's arguments
+fn bind_command_line_args(interpreter: &Interpreter, args: &[String]) {
+    let args = args.iter().map(|arg| LangExp::Str(arg.as_str().into())).collect();
 
-    match potential_float {
-        Ok(v) => LangExp::Number(v),
-        Err(_) => LangExp::Symbol(token.to_string().clone())
-    }
+    interpreter.env().insert(SymbolId::intern("*command-line-args*"), LangExp::list(args));
 }
 
-fn default_env() -> LangEnv {
-    let mut data: HashMap<String, LangExp> = HashMap::new();
+/// binds the `n`th result of the session to `$n`, and to `$_` as the most recent one
+fn bind_result(interpreter: &Interpreter, n: usize, res: &LangExp) {
+    interpreter.env().insert(SymbolId::intern(&format!("${}", n)), res.clone());
+    interpreter.env().insert(SymbolId::intern("$_"), res.clone());
+}
 
-    data.insert(
-        "+".to_string(),
-        LangExp::Func(
-            |args: &[LangExp]| -> Result<LangExp, LangErr> {
-                let sum = parse_list_of_floats(args)?.iter().fold(0.0, |sum, a| sum + a); //
+/// returns the code the session asked to exit with, 0 when input ran out
+fn repl(mut interpreter: Interpreter) -> i32 {
+    let mut editor = Editor::new();
+    let mut settings = Settings::default();
+    let mut results = 0;
 
-                Ok(LangExp::Number(sum))
-            }
-        ),
-    );
-
-    data.insert(
-        "-".to_string(),
-        LangExp::Func(
-            |args: &[LangExp]| -> Result<LangExp, LangErr> {
-                let floats = parse_list_of_floats(args)?;
-                let first = *floats.first().ok_or(LangErr::Reason("expected at least one number".to_string()))?;
-                let sum_of_rest = floats[1..].iter().fold(0.0, |sum, a| sum + a); //
-
-                Ok(LangExp::Number(sum_of_rest))
-            }
-        ),
-    );
-
-    LangEnv { data }
-}
+    loop {
+        // refreshed before every prompt so names defined so far complete too
+        editor.set_completions(interpreter.symbol_names());
 
-fn parse_list_of_floats(args: &[LangExp]) -> Result<Vec<f64>, LangErr> {
-    args
-        .iter()
-        .map(|x| parse_single_float(x))
-        .collect()
-}
+        let exp = match slurp_exp(&mut editor) {
+            Some(exp) => exp,
+            None => return 0
+        };
 
-fn parse_single_float(exp: &LangExp) -> Result<f64, LangErr> {
-    match exp {
-        LangExp::Number(num) => Ok(*num),
-        _ => Err(LangErr::Reason("expected a number".to_string()))
-    }
-}
+        if exp.trim().is_empty() {
+            continue;
+        }
 
-fn eval(exp: &LangExp, env: &mut LangEnv) -> Result<LangExp, LangErr> {
-    match exp {
-        LangExp::Symbol(k) =>
-            env.data.get(k)
-                .ok_or(LangErr::Reason(format!("unexpected symbol k='{}'", k)))
-                .map(|x| x.clone()
-                ),
-
-        LangExp::Number(_a) => Ok(exp.clone()),
-
-        LangExp::List(list) => {
-            let first_form = list
-                .first()
-                .ok_or(LangErr::Reason("expected a non-empty list".to_string()))?;
-
-            let arg_forms = &list[1..];
-            let first_eval = eval(first_form, env)?;
-
-            match first_eval {
-                LangExp::Func(f) => {
-                    let args_eval = arg_forms
-                        .iter()
-                        .map(|x| eval(x, env))
-                        .collect::<Result<Vec<LangExp>, LangErr>>();
-                    f(&args_eval?)
+        editor.add_history(&exp.trim().replace('\n', " "));
+
+        // meta-commands like `:help` are handled here instead of being evaluated
+        if let Some(command) = exp.trim_start().strip_prefix(':') {
+            match run_command(command, &mut interpreter, &mut settings) {
+                Outcome::Continue => continue,
+                Outcome::Reset => {
+                    // numbering starts over, `$1` being the first result after the reset
+                    results = 0;
+                    bind_command_line_args(&interpreter, &[]);
+                    continue;
                 }
-
-                _ => Err(LangErr::Reason("first form must be a function".to_string()))
+                Outcome::Quit => return 0
             }
         }
 
-        LangExp::Func(_) => Err(LangErr::Reason("unexpected form".to_string()))
+        match interpreter.eval_str(&exp) {
+            Ok(res) => {
+                results += 1;
+                bind_result(&interpreter, results, &res);
+                println!("{}", show_result(&res, &settings));
+            }
+            Err(e) => match e.exit_code() {
+                Some(code) => return code,
+                None => println!("// => {}", e.render(&exp))
+            }
+        }
     }
 }
 
-impl fmt::Display for LangExp {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let str = match self {
-            LangExp::Symbol(s) => s.clone(),
-
-            LangExp::Number(n) => n.to_string(),
-
-            LangExp::List(list) => {
-                let xs: Vec<String> = list
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect();
+/// evaluates every form in the file, reporting the first error on stderr. fails with the code the
+/// process should exit with, the one given to `exit` or 1
+fn eval_file(interpreter: &mut Interpreter, path: &str) -> Result<(), i32> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            return Err(1);
+        }
+    };
 
-                format!("({})", xs.join(","))
+    match interpreter.eval_str(&source) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            if let Some(code) = e.exit_code() {
+                return Err(code);
             }
 
-            LangExp::Func(_) => "Function {}".to_string(),
-        };
-
-        write!(f, "{}", str)
+            // errors from files it loaded already name their own path
+            match e.path() {
+                Some(_) => eprintln!("{}", e.render(&source)),
+                None => eprintln!("{}:{}", path, e.render(&source))
+            }
+            Err(1)
+        }
     }
 }
 
-fn parse_eval(exp: String, env: &mut LangEnv) -> Result<LangExp, LangErr> {
-    let (parsed_exp, _) = parse(&tokenize(exp))?;
-    let evaluated_exp = eval(&parsed_exp, env)?;
-
-    Ok(evaluated_exp)
+/// evaluates an expression given on the command line and prints its value
+fn run_expression(mut interpreter: Interpreter, exp: &str) -> i32 {
+    match interpreter.eval_str(exp) {
+        Ok(res) => {
+            println!("{}", res);
+            0
+        }
+        Err(e) => e.exit_code().unwrap_or_else(|| {
+            eprintln!("{}", e.render(exp));
+            1
+        })
+    }
 }
 
-fn slurp_exp() -> String {
-    let mut exp = String::new();
-
-    io::stdin().read_line(&mut exp)
-        .expect("failed to read line");
+fn run(args: &[String]) -> i32 {
+    // an image saved with `save-image` is evaluated before anything else
+    let (image, args) = match args {
+        [flag, image, rest @ ..] if flag == "--image" => (Some(image), rest),
+        _ => (None, args)
+    };
 
-    exp
-}
+    let mut interpreter = new_interpreter();
 
-fn repl() {
-    let env = &mut default_env();
+    if let Some(image) = image {
+        if let Err(code) = eval_file(&mut interpreter, image) {
+            return code;
+        }
+    }
 
-    loop {
-        println!("lang >");
+    match args {
+        [] => {
+            bind_command_line_args(&interpreter, &[]);
+            repl(interpreter)
+        }
+        [flag, exp] if flag == "-e" => {
+            bind_command_line_args(&interpreter, &[]);
+            run_expression(interpreter, exp)
+        }
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{}", USAGE);
+            0
+        }
+        [path, ..] if !path.starts_with('-') => {
+            bind_command_line_args(&interpreter, args);
 
-        let exp = slurp_exp();
+            // files are optimized, forms typed at the repl or given with -e run as they are
+            interpreter.set_optimizing(true);
 
-        match parse_eval(exp, env) {
-            Ok(res) => println!("// => {}", res),
-            Err(e) => match e {
-                LangErr::Reason(msg) => println!("// => {}", msg)
+            // failing tests fail the script too, so it can be used as a test runner
+            match eval_file(&mut interpreter, path) {
+                Ok(()) if interpreter.failed_tests() == 0 => 0,
+                Ok(()) => 1,
+                Err(code) => code
             }
         }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
     }
 }
 
 fn main() {
-    let msg = "(+ 10 5)";
-    repl();
-}
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let code = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&args))
+        .expect("failed to start the interpreter thread")
+        .join()
+        .unwrap_or(101);
 
+    process::exit(code);
+}
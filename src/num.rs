@@ -0,0 +1,136 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use crate::exp::{LangErr, LangExp};
+
+/// a number on either level of the numeric tower. integers are exact; an operation mixing an
+/// integer with a float, or overflowing an `i64`, produces a float
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Num {
+    Int(i64),
+    Float(f64),
+}
+
+impl Num {
+    pub(crate) fn from_exp(exp: &LangExp) -> Result<Num, LangErr> {
+        match exp {
+            LangExp::Int(i) => Ok(Num::Int(*i)),
+            LangExp::Number(f) => Ok(Num::Float(*f)),
+            _ => Err(LangErr::type_error("a number", exp))
+        }
+    }
+
+    pub(crate) fn into_exp(self) -> LangExp {
+        match self {
+            Num::Int(i) => LangExp::Int(i),
+            Num::Float(f) => LangExp::Number(f)
+        }
+    }
+
+    pub(crate) fn as_f64(self) -> f64 {
+        match self {
+            Num::Int(i) => i as f64,
+            Num::Float(f) => f
+        }
+    }
+
+    /// uses `int_op` when both sides are integers and it doesn't overflow, `float_op` otherwise
+    fn combine(self, other: Num, int_op: fn(i64, i64) -> Option<i64>, float_op: fn(f64, f64) -> f64) -> Num {
+        if let (Num::Int(a), Num::Int(b)) = (self, other) {
+            if let Some(res) = int_op(a, b) {
+                return Num::Int(res);
+            }
+        }
+
+        Num::Float(float_op(self.as_f64(), other.as_f64()))
+    }
+
+    pub(crate) fn add(self, other: Num) -> Num {
+        self.combine(other, i64::checked_add, |a, b| a + b)
+    }
+
+    pub(crate) fn sub(self, other: Num) -> Num {
+        self.combine(other, i64::checked_sub, |a, b| a - b)
+    }
+
+    pub(crate) fn mul(self, other: Num) -> Num {
+        self.combine(other, i64::checked_mul, |a, b| a * b)
+    }
+
+    /// integer division stays exact when it has no remainder
+    pub(crate) fn div(self, other: Num) -> Result<Num, LangErr> {
+        if other.is_zero() {
+            return Err(LangErr::Reason("division by zero".to_string()));
+        }
+
+        // `checked_rem` also fails for the one quotient that overflows, `i64::MIN / -1`
+        Ok(self.combine(other, |a, b| if a.checked_rem(b) == Some(0) { a.checked_div(b) } else { None }, |a, b| a / b))
+    }
+
+    /// the remainder takes the sign of the divisor
+    pub(crate) fn modulo(self, other: Num) -> Result<Num, LangErr> {
+        if other.is_zero() {
+            return Err(LangErr::Reason("division by zero".to_string()));
+        }
+
+        Ok(self.combine(
+            other,
+            // the remainder of `i64::MIN / -1` is 0, though the division overflows
+            |a, b| {
+                let r = a.wrapping_rem(b);
+                Some(if r != 0 && (r < 0) != (b < 0) { r + b } else { r })
+            },
+            |a, b| ((a % b) + b) % b,
+        ))
+    }
+
+    pub(crate) fn pow(self, other: Num) -> Num {
+        self.combine(
+            other,
+            |a, b| u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            f64::powf,
+        )
+    }
+
+    pub(crate) fn neg(self) -> Num {
+        Num::Int(0).sub(self)
+    }
+
+    pub(crate) fn abs(self) -> Num {
+        match self {
+            Num::Int(i) => i.checked_abs().map(Num::Int).unwrap_or(Num::Float((i as f64).abs())),
+            Num::Float(f) => Num::Float(f.abs())
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            Num::Int(i) => i == 0,
+            Num::Float(f) => f == 0.0
+        }
+    }
+
+    /// integers compare exactly, anything involving a float compares as floats (so NaN is unordered)
+    pub(crate) fn compare(self, other: Num) -> Option<Ordering> {
+        match (self, other) {
+            (Num::Int(a), Num::Int(b)) => Some(a.cmp(&b)),
+            _ => self.as_f64().partial_cmp(&other.as_f64())
+        }
+    }
+
+    /// rounds a float to an integer with `round`, keeping it a float when it doesn't fit in an `i64`
+    pub(crate) fn to_integer(self, round: fn(f64) -> f64) -> Num {
+        match self {
+            Num::Int(_) => self,
+            Num::Float(f) => {
+                let rounded = round(f);
+
+                if rounded.is_finite() && rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+                    Num::Int(rounded as i64)
+                } else {
+                    Num::Float(rounded)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::env::LangEnv;
+use crate::eval::{expand_once, is_truthy, parse_let_bindings, parse_params, SPECIAL_FORMS};
+use crate::exp::LangExp;
+use crate::list::LangList;
+use crate::lock::Lock;
+use crate::reader::copy_list_span;
+use crate::stack;
+use crate::symbol::{self, SymbolId};
+
+/// the builtins a call to is replaced by its value when every argument is a number literal
+const FOLDABLE: &[&str] = &[
+    "+", "-", "*", "/", "mod", "pow", "sqrt", "min", "max", "abs", "floor", "ceil", "round", "truncate",
+    "exact->inexact", "=", "<", ">", "<=", ">=",
+];
+
+/// rewrites `exp` into a form that evaluates the same in `env` but does less work: macro calls are
+/// expanded, arithmetic on number literals is done already, an `if` with a literal test becomes the
+/// branch it takes, and symbols of the enclosing lambdas and lets become `(%lexical depth name)`,
+/// which starts looking the symbol up `depth` scopes out. the forms it doesn't know, like `match`
+/// or `while`, are left as they are with everything inside them
+pub(crate) fn optimize(exp: &LangExp, env: &LangEnv) -> LangExp {
+    let mut defines = Defines::default();
+    scan(exp, &mut defines);
+
+    Optimizer { env, scopes: vec![], defining: defines.names }.exp(exp)
+}
+
+struct Optimizer<'a> {
+    /// where the form will run, consulted for macros and builtins
+    env: &'a LangEnv,
+    /// the scopes of the lambdas and lets around the form being optimized, innermost last
+    scopes: Vec<Scope>,
+    /// the names the form itself defines in `env`, so a function calling itself is a call
+    defining: HashSet<SymbolId>,
+}
+
+/// a scope a lambda or let creates when it runs
+struct Scope {
+    /// the names it binds, counting the ones defined in its body. names it might not actually
+    /// bind are harmless, a symbol found here is only looked up from here on
+    names: HashSet<SymbolId>,
+    /// a body that loads a file or declares a record type binds names that can't be known
+    /// beforehand, so no symbol is looked up past it
+    open: bool,
+    /// the symbols that were looked up past it, which a define that only shows up once macros
+    /// are expanded would have to shadow
+    passed: HashSet<SymbolId>,
+}
+
+/// what a scope's body binds in it, see `scan`
+#[derive(Default)]
+struct Defines {
+    names: HashSet<SymbolId>,
+    open: bool,
+    /// the symbols it calls. one that isn't bound may be a macro defined later, expanding to
+    /// defines of its own
+    heads: HashSet<SymbolId>,
+}
+
+/// where a symbol is looked up from
+enum Resolution {
+    /// in one of the enclosing scopes, this many scopes out
+    Local(usize),
+    /// past every enclosing scope, which are this many, in `env`
+    Outer(usize),
+}
+
+impl Optimizer<'_> {
+    fn exp(&mut self, exp: &LangExp) -> LangExp {
+        // out of stack, the rest of the form is left for evaluation to report
+        if stack::exhausted() {
+            return exp.clone();
+        }
+
+        match exp {
+            LangExp::Symbol(name) => match self.resolve(*name) {
+                Resolution::Local(0) | Resolution::Outer(0) => exp.clone(),
+                Resolution::Local(depth) | Resolution::Outer(depth) => LangExp::list(vec![
+                    LangExp::Symbol(symbol::LEXICAL),
+                    LangExp::Int(depth as i64),
+                    exp.clone(),
+                ]),
+            },
+
+            LangExp::Vector(items) => {
+                // copied out of the lock, expanding a macro in an element runs lisp code
+                let items = items.borrow().clone();
+                let optimized: Vec<LangExp> = items.iter().map(|item| self.exp(item)).collect();
+
+                if optimized.iter().zip(items.iter()).all(|(a, b)| same_form(a, b)) {
+                    exp.clone()
+                } else {
+                    LangExp::Vector(Arc::new(Lock::new(optimized)))
+                }
+            }
+
+            LangExp::List(list) if !list.is_empty() => self.list(list),
+
+            _ => exp.clone()
+        }
+    }
+
+    fn list(&mut self, list: &LangList) -> LangExp {
+        let original = LangExp::List(list.clone());
+
+        let name = match &list[0] {
+            LangExp::Symbol(name) => *name,
+            _ => return self.call(list)
+        };
+
+        // special forms take precedence over bindings, as they do in `eval`
+        let optimized = match name {
+            symbol::QUOTE => None,
+            symbol::IF => self.if_form(list),
+            symbol::COND => self.cond(list),
+            symbol::BEGIN | symbol::AND | symbol::OR => Some(self.args(list, 1)),
+            // the name `set!` rebinds is left as it is
+            symbol::SET => Some(self.args(list, 2)),
+            symbol::DEFINE => self.define(list),
+            symbol::LAMBDA => self.lambda(list, 1),
+            symbol::LET => self.let_form(list),
+            symbol::LET_STAR => self.let_star(list),
+            symbol::LETREC => self.letrec(list),
+            _ if SPECIAL_FORMS.contains(&name) => None,
+            _ => match self.resolve(name) {
+                Resolution::Outer(_) => match self.env.get(name) {
+                    Some(LangExp::Macro(_)) => expand_once(&original, self.env).ok().flatten().map(|expanded| self.exp(&expanded)),
+                    Some(LangExp::Func(native)) if FOLDABLE.contains(&&*native.name.name()) => {
+                        let call = self.call(list);
+
+                        match &call {
+                            LangExp::List(call) if call[1..].iter().all(|arg| matches!(arg, LangExp::Int(_) | LangExp::Number(_))) => {
+                                let args = &call[1..];
+
+                                // a call that fails is kept, so it fails when it runs
+                                match native.arity.accepts(args.len()) {
+                                    true => (native.f)(args, self.env).ok().or(Some(LangExp::List(call.clone()))),
+                                    false => Some(LangExp::List(call.clone()))
+                                }
+                            }
+                            _ => Some(call)
+                        }
+                    }
+                    None if self.defining.contains(&name) => Some(self.call(list)),
+                    // not bound yet, so it may turn out to be a macro defined later. the scope it
+                    // is in was made open for that, see `body`
+                    None => None,
+                    Some(_) => Some(self.call(list))
+                },
+                // a local binding that may not be there when it runs could leave a macro to expand
+                Resolution::Local(_) if matches!(self.env.get(name), Some(LangExp::Macro(_))) => None,
+                Resolution::Local(_) => Some(self.call(list)),
+            }
+        };
+
+        optimized.unwrap_or(original)
+    }
+
+    /// a call, or any form whose elements are all evaluated
+    fn call(&mut self, list: &LangList) -> LangExp {
+        self.args(list, 0)
+    }
+
+    /// the form with its elements from `from` on optimized
+    fn args(&mut self, list: &LangList, from: usize) -> LangExp {
+        let mut items = list[..from].to_vec();
+        items.extend(list[from..].iter().map(|item| self.exp(item)));
+
+        rebuild(list, items)
+    }
+
+    fn if_form(&mut self, list: &LangList) -> Option<LangExp> {
+        if list.len() < 3 || list.len() > 4 {
+            return None;
+        }
+
+        let test = self.exp(&list[1]);
+
+        match literal(&test) {
+            Some(value) if is_truthy(value) => Some(self.exp(&list[2])),
+            Some(_) => Some(list.get(3).map_or(LangExp::nil(), |alternative| self.exp(alternative))),
+            None => {
+                let mut items = vec![list[0].clone(), test];
+                items.extend(list[2..].iter().map(|branch| self.exp(branch)));
+
+                Some(rebuild(list, items))
+            }
+        }
+    }
+
+    fn cond(&mut self, list: &LangList) -> Option<LangExp> {
+        let mut items = vec![list[0].clone()];
+
+        for clause in &list[1..] {
+            let clause = match clause {
+                LangExp::List(clause) if !clause.is_empty() => clause,
+                _ => return None
+            };
+
+            // `else` is not looked up
+            let from = if clause[0] == LangExp::Symbol(symbol::ELSE) { 1 } else { 0 };
+            items.push(self.args(clause, from));
+        }
+
+        Some(rebuild(list, items))
+    }
+
+    fn define(&mut self, list: &LangList) -> Option<LangExp> {
+        match &list[1..] {
+            [LangExp::Symbol(_), _] => Some(self.args(list, 2)),
+
+            // `(define (name params...) body...)`, the rest of the signature are the parameters
+            [LangExp::List(signature), _, ..] if !signature.is_empty() => {
+                let params = LangExp::list(signature[1..].to_vec());
+                let body = self.lambda_body(&params, &list[2..])?;
+
+                let mut items = list[..2].to_vec();
+                items.extend(body);
+
+                Some(rebuild(list, items))
+            }
+
+            _ => None
+        }
+    }
+
+    /// `(lambda params body...)`, the parameter list at `params`
+    fn lambda(&mut self, list: &LangList, params: usize) -> Option<LangExp> {
+        let body = self.lambda_body(list.get(params)?, list.get(params + 1..)?)?;
+
+        let mut items = list[..params + 1].to_vec();
+        items.extend(body);
+
+        Some(rebuild(list, items))
+    }
+
+    /// the default forms are left as they are, they are rarely more than a literal
+    fn lambda_body(&mut self, params: &LangExp, body: &[LangExp]) -> Option<Vec<LangExp>> {
+        let params = match params {
+            LangExp::List(list) => parse_params(list).ok()?,
+            _ => return None
+        };
+
+        let mut names: HashSet<SymbolId> = params.required.iter().copied().collect();
+        names.extend(params.optional.iter().map(|(name, _)| *name));
+        names.extend(params.rest);
+
+        Some(self.body(names, body))
+    }
+
+    fn let_form(&mut self, list: &LangList) -> Option<LangExp> {
+        let (bindings, body) = parse_let_bindings(&list[1..]).ok()?;
+
+        let values: Vec<LangExp> = bindings.iter().map(|(_, value)| self.exp(value)).collect();
+        let body = self.body(bindings.iter().map(|(name, _)| *name).collect(), body);
+
+        Some(rebuild_let(list, &bindings, values, body))
+    }
+
+    /// each binding gets a scope of its own, and the body another one inside them
+    fn let_star(&mut self, list: &LangList) -> Option<LangExp> {
+        let (bindings, body) = parse_let_bindings(&list[1..]).ok()?;
+        let outer = self.scopes.len();
+
+        let mut values = vec![];
+
+        for (name, value) in &bindings {
+            values.push(self.exp(value));
+            self.scopes.push(Scope { names: HashSet::from([*name]), open: false, passed: HashSet::new() });
+        }
+
+        let body = self.body(HashSet::new(), body);
+        self.scopes.truncate(outer);
+
+        Some(rebuild_let(list, &bindings, values, body))
+    }
+
+    /// the values are evaluated inside the scope, like the body
+    fn letrec(&mut self, list: &LangList) -> Option<LangExp> {
+        let (bindings, body) = parse_let_bindings(&list[1..]).ok()?;
+
+        // the values are put at the front of the body so they are optimized in its scope
+        let mut forms: Vec<LangExp> = bindings.iter().map(|(_, value)| (*value).clone()).collect();
+        forms.extend_from_slice(body);
+
+        let mut optimized = self.body(bindings.iter().map(|(name, _)| *name).collect(), &forms);
+        let body = optimized.split_off(bindings.len());
+
+        Some(rebuild_let(list, &bindings, optimized, body))
+    }
+
+    /// the forms of a body run in a new scope that binds `names` and whatever the body defines.
+    /// the defines are found before optimizing, then again after, when macros are expanded. if
+    /// that finds more that matter, the body is kept as it was. a body calling a symbol that is
+    /// bound nowhere yet makes an open scope, as the call may be to a macro that defines more
+    fn body(&mut self, mut names: HashSet<SymbolId>, forms: &[LangExp]) -> Vec<LangExp> {
+        let mut defines = Defines::default();
+        forms.iter().for_each(|form| scan(form, &mut defines));
+
+        names.extend(defines.names.iter().copied());
+        let open = self.is_open(&defines, &names);
+        self.scopes.push(Scope { names, open, passed: HashSet::new() });
+
+        let optimized: Vec<LangExp> = forms.iter().map(|form| self.exp(form)).collect();
+        let scope = self.scopes.pop().expect("the body's own scope was pushed");
+
+        let mut defines = Defines::default();
+        optimized.iter().for_each(|form| scan(form, &mut defines));
+
+        let shadowed = defines.names.iter().any(|name| !scope.names.contains(name) && scope.passed.contains(name));
+
+        if shadowed || (self.is_open(&defines, &scope.names) && !scope.open && !scope.passed.is_empty()) {
+            return forms.to_vec();
+        }
+
+        optimized
+    }
+
+    /// whether a body with `defines`, in a scope binding `names`, may bind names that can't be
+    /// known beforehand
+    fn is_open(&self, defines: &Defines, names: &HashSet<SymbolId>) -> bool {
+        defines.open || defines.heads.iter().any(|head| {
+            !names.contains(head)
+                && !self.scopes.iter().any(|scope| scope.names.contains(head))
+                && !self.defining.contains(head)
+                && self.env.get(*head).is_none()
+        })
+    }
+
+    fn resolve(&mut self, name: SymbolId) -> Resolution {
+        let total = self.scopes.len();
+
+        for (depth, scope) in self.scopes.iter_mut().rev().enumerate() {
+            if scope.names.contains(&name) || scope.open {
+                return Resolution::Local(depth);
+            }
+
+            scope.passed.insert(name);
+        }
+
+        Resolution::Outer(total)
+    }
+}
+
+/// collects the names `form` defines in the scope it runs in, and the symbols it calls there.
+/// forms that create a scope of their own aren't looked into, nor are quoted ones, and of the
+/// special forms that do, only the parts evaluated in this scope are. anything else is, even where
+/// it may not be code
+fn scan(form: &LangExp, defines: &mut Defines) {
+    let list = match form {
+        LangExp::List(list) => list,
+        LangExp::Vector(items) => return items.borrow().iter().for_each(|item| scan(item, defines)),
+        _ => return
+    };
+
+    match list.first() {
+        Some(LangExp::Symbol(symbol::QUOTE | symbol::QUASIQUOTE | symbol::LAMBDA | symbol::LETREC | symbol::LEXICAL)) => {}
+
+        // their bodies are lambdas
+        Some(LangExp::Symbol(symbol::DEFMACRO | symbol::DEFTEST)) => {
+            if let Some(LangExp::Symbol(name)) = list.get(1) {
+                defines.names.insert(*name);
+            }
+        }
+
+        Some(LangExp::Symbol(symbol::DEFINE)) => match list.get(1) {
+            Some(LangExp::Symbol(name)) => {
+                defines.names.insert(*name);
+                list[2..].iter().for_each(|form| scan(form, defines));
+            }
+            // `(define (name params...) body...)`
+            Some(LangExp::List(signature)) => {
+                if let Some(LangExp::Symbol(name)) = signature.first() {
+                    defines.names.insert(*name);
+                }
+            }
+            _ => {}
+        },
+
+        // only the values are evaluated in this scope
+        Some(LangExp::Symbol(symbol::LET | symbol::LET_STAR | symbol::LOOP)) => match parse_let_bindings(&list[1..]) {
+            Ok((bindings, _)) => bindings.iter().for_each(|(_, value)| scan(value, defines)),
+            Err(_) => list[1..].iter().for_each(|form| scan(form, defines))
+        },
+
+        // the clauses are not calls
+        Some(LangExp::Symbol(symbol::COND)) => list[1..].iter().for_each(|clause| match clause {
+            LangExp::List(clause) => clause.iter().for_each(|form| scan(form, defines)),
+            _ => scan(clause, defines)
+        }),
+
+        // the clauses, the handler and the body of `dotimes` run in scopes of their own
+        Some(LangExp::Symbol(symbol::MATCH)) => list.iter().skip(1).take(1).for_each(|exp| scan(exp, defines)),
+        Some(LangExp::Symbol(symbol::TRY)) => {
+            if let Some((_, body)) = list[1..].split_last() {
+                body.iter().for_each(|form| scan(form, defines));
+            }
+        }
+        Some(LangExp::Symbol(symbol::DOTIMES)) => {
+            if let Some(LangExp::List(spec)) = list.get(1) {
+                spec.iter().skip(1).for_each(|count| scan(count, defines));
+            }
+        }
+
+        Some(LangExp::Symbol(symbol::DEFSTRUCT | symbol::LOAD | symbol::REQUIRE)) => defines.open = true,
+
+        _ => {
+            if let Some(LangExp::Symbol(head)) = list.first() {
+                if !SPECIAL_FORMS.contains(head) {
+                    defines.heads.insert(*head);
+                }
+            }
+
+            list.iter().for_each(|form| scan(form, defines));
+        }
+    }
+}
+
+/// the value of a form that evaluates to a constant, if it is one
+fn literal(exp: &LangExp) -> Option<&LangExp> {
+    match exp {
+        LangExp::Bool(_) | LangExp::Int(_) | LangExp::Number(_) | LangExp::Char(_) | LangExp::Str(_) => Some(exp),
+        LangExp::List(list) if list.is_empty() => Some(exp),
+        LangExp::List(list) => match &list[..] {
+            [LangExp::Symbol(symbol::QUOTE), value] => Some(value),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// `((name value)...)` with the values replaced, then the body
+fn rebuild_let(list: &LangList, bindings: &[(SymbolId, &LangExp)], values: Vec<LangExp>, body: Vec<LangExp>) -> LangExp {
+    let pairs: Vec<LangExp> = bindings
+        .iter()
+        .zip(values)
+        .map(|((name, _), value)| LangExp::list(vec![LangExp::Symbol(*name), value]))
+        .collect();
+
+    let unchanged = bindings.iter().zip(&pairs).all(|((_, before), after)| match after {
+        LangExp::List(pair) => same_form(before, &pair[1]),
+        _ => false
+    });
+
+    let mut items = vec![list[0].clone(), if unchanged { list[1].clone() } else { LangExp::list(pairs) }];
+    items.extend(body);
+
+    rebuild(list, items)
+}
+
+/// the list `items` make up, which is `list` itself when nothing in it changed. a new list keeps
+/// the span of the one it replaces, for backtraces
+fn rebuild(list: &LangList, items: Vec<LangExp>) -> LangExp {
+    if items.len() == list.len() && items.iter().zip(list.iter()).all(|(a, b)| same_form(a, b)) {
+        return LangExp::List(list.clone());
+    }
+
+    let new = LangList::new(items);
+    copy_list_span(list, &new);
+
+    LangExp::List(new)
+}
+
+fn same_form(a: &LangExp, b: &LangExp) -> bool {
+    match (a, b) {
+        (LangExp::List(a), LangExp::List(b)) => LangList::ptr_eq(a, b),
+        (LangExp::Vector(a), LangExp::Vector(b)) => Arc::ptr_eq(a, b),
+        _ => a == b
+    }
+}
@@ -0,0 +1,82 @@
+; the standard library functions that don't need to be written in rust. this file is compiled into
+; the interpreter and evaluated into every fresh environment, after the builtins are bound
+
+(define (empty? xs) "whether xs has no elements" (= (length xs) 0))
+
+; streams are sequences whose tail is only evaluated once it is needed, so they can be infinite.
+; the empty stream is the empty list
+(defstruct stream head tail)
+
+(defmacro cons-stream (head tail)
+  "a stream of head followed by the stream tail evaluates to, evaluated when it is first needed"
+  `(make-stream ,head (delay ,tail)))
+
+(define (stream-car s) "the first element of a stream" (stream-head s))
+
+(define (stream-cdr s) "the rest of a stream" (force (stream-tail s)))
+
+(define (stream-map f s)
+  "a stream of f applied to each element of s"
+  (if (stream? s)
+      (cons-stream (f (stream-car s)) (stream-map f (stream-cdr s)))
+      '()))
+
+(define (stream-filter pred s)
+  "a stream of the elements of s that satisfy pred"
+  (cond ((not (stream? s)) '())
+        ((pred (stream-car s)) (cons-stream (stream-car s) (stream-filter pred (stream-cdr s))))
+        (else (stream-filter pred (stream-cdr s)))))
+
+(define (integers-from n) "the infinite stream n, n + 1, n + 2 and so on" (cons-stream n (integers-from (+ n 1))))
+
+(define (second xs) "the second element of xs" (nth 1 xs))
+
+(define (third xs) "the third element of xs" (nth 2 xs))
+
+(define (last xs)
+  "the last element of a non-empty list"
+  (if (empty? (cdr xs))
+      (car xs)
+      (last (cdr xs))))
+
+(define (take n xs)
+  "the first n elements of a list or a stream as a list, or all of them if there are fewer"
+  (letrec ((build (lambda (n xs acc)
+                    (cond ((= n 0) (reverse acc))
+                          ((stream? xs) (build (- n 1) (stream-cdr xs) (cons (stream-car xs) acc)))
+                          ((empty? xs) (reverse acc))
+                          (else (build (- n 1) (cdr xs) (cons (car xs) acc)))))))
+    (build n xs '())))
+
+(define (drop n xs)
+  "a list or a stream without its first n elements"
+  (cond ((= n 0) xs)
+        ((stream? xs) (drop (- n 1) (stream-cdr xs)))
+        ((empty? xs) xs)
+        (else (drop (- n 1) (cdr xs)))))
+
+(define (range start end)
+  "the integers from start up to, but not including, end"
+  (letrec ((build (lambda (i acc)
+                    (if (< i start)
+                        acc
+                        (build (- i 1) (cons i acc))))))
+    (build (- end 1) '())))
+
+(define (count pred xs)
+  "how many elements satisfy pred"
+  (fold (lambda (n x) (if (pred x) (+ n 1) n)) 0 xs))
+
+(define (every? pred xs)
+  "whether every element satisfies pred"
+  (or (empty? xs)
+      (and (pred (car xs)) (every? pred (cdr xs)))))
+
+(define (any? pred xs)
+  "whether some element satisfies pred"
+  (and (not (empty? xs))
+       (or (pred (car xs)) (any? pred (cdr xs)))))
+
+(define (compose f g) "a function calling g, then f on its result" (lambda (x) (f (g x))))
+
+(define (identity x) "x itself" x)
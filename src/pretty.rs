@@ -0,0 +1,156 @@
+use crate::exp::LangExp;
+
+/// how far the rest of a form is indented under its head when the form has to be broken up
+const BODY_INDENT: usize = 2;
+
+impl LangExp {
+    /// prints the value like `Display` does, but breaks lists, vectors and maps that don't fit in
+    /// `width` columns over several lines, indenting their elements. what `Display` prints on one
+    /// line and this prints over several read back as the same value
+    pub fn to_pretty_string(&self, width: usize) -> String {
+        let mut out = String::new();
+        pretty(self, 0, width, &mut vec![], &mut out);
+
+        out
+    }
+}
+
+/// appends `exp` to `out`, which is at column `column`. `open` has the vectors, records and
+/// promises `exp` is inside of, one of which it prints as `...`, like `Display` does
+fn pretty(exp: &LangExp, column: usize, width: usize, open: &mut Vec<usize>, out: &mut String) {
+    let address = exp.mutable_address();
+
+    if address.is_some_and(|address| open.contains(&address)) {
+        out.push_str("...");
+        return;
+    }
+
+    let flat = exp.to_string();
+
+    // from the width on nothing fits, so breaking lines wouldn't help. every level goes at least
+    // a column further, which keeps values nested deeply from recursing far
+    if column + flat.chars().count() <= width || column >= width {
+        out.push_str(&flat);
+        return;
+    }
+
+    open.extend(address);
+    pretty_parts(exp, &flat, column, width, open, out);
+
+    if address.is_some() {
+        open.pop();
+    }
+}
+
+/// `exp`, which doesn't fit on the rest of the line, broken over several lines
+fn pretty_parts(exp: &LangExp, flat: &str, column: usize, width: usize, open: &mut Vec<usize>, out: &mut String) {
+    match exp {
+        // data made of atoms only, like a list of numbers, is packed onto as few lines as fit
+        LangExp::List(items) if items.iter().all(is_atom) => {
+            out.push('(');
+            fill(items, column + 1, width, out);
+            out.push(')');
+        }
+
+
+        LangExp::List(items) if !items.is_empty() => {
+            let (head, rest) = (&items[0], &items[1..]);
+
+            out.push('(');
+            pretty(head, column + 1, width, open, out);
+
+            // `(name first` with the rest indented under it, the way code is usually laid out
+            match head {
+                LangExp::Symbol(_) if !rest.is_empty() => {
+                    out.push(' ');
+                    pretty(&rest[0], column + 1 + flat_len(head) + 1, width, open, out);
+                    sequence(&rest[1..], column + BODY_INDENT, width, open, out);
+                }
+                _ => sequence(rest, column + 1, width, open, out)
+            }
+
+            out.push(')');
+        }
+
+        // copied out, since printing the elements can reach the vector again, and a second borrow
+        // could wait forever behind another thread's `vector-set!`
+        LangExp::Vector(items) => {
+            let items = items.borrow().clone();
+
+            out.push('[');
+
+            if items.iter().all(is_atom) {
+                fill(&items, column + 1, width, out);
+            } else if let Some((first, rest)) = items.split_first() {
+                pretty(first, column + 1, width, open, out);
+                sequence(rest, column + 1, width, open, out);
+            }
+
+            out.push(']');
+        }
+
+        LangExp::Map(map) => {
+            out.push('{');
+
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    newline(column + 1, out);
+                }
+
+                out.push_str(&k.to_string());
+                out.push(' ');
+                pretty(v, column + 1 + flat_len(k) + 1, width, open, out);
+            }
+
+            out.push('}');
+        }
+
+        // atoms can't be broken up
+        _ => out.push_str(flat)
+    }
+}
+
+/// each of `items` on a line of its own, indented to `indent`
+fn sequence(items: &[LangExp], indent: usize, width: usize, open: &mut Vec<usize>, out: &mut String) {
+    for item in items {
+        newline(indent, out);
+        pretty(item, indent, width, open, out);
+    }
+}
+
+/// `items` separated by spaces, starting a new line indented to `indent` whenever the next one
+/// wouldn't fit. the first one starts at `indent` too
+fn fill(items: &[LangExp], indent: usize, width: usize, out: &mut String) {
+    let mut column = indent;
+
+    for (i, item) in items.iter().enumerate() {
+        let flat = item.to_string();
+        let len = flat.chars().count();
+
+        if i > 0 {
+            if column + 1 + len > width {
+                newline(indent, out);
+                column = indent;
+            } else {
+                out.push(' ');
+                column += 1;
+            }
+        }
+
+        out.push_str(&flat);
+        column += len;
+    }
+}
+
+fn is_atom(exp: &LangExp) -> bool {
+    !matches!(exp, LangExp::List(_) | LangExp::Vector(_) | LangExp::Map(_))
+}
+
+fn newline(indent: usize, out: &mut String) {
+    out.push('\n');
+    out.push_str(&" ".repeat(indent));
+}
+
+fn flat_len(exp: &LangExp) -> usize {
+    exp.to_string().chars().count()
+}
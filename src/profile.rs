@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::env::LangEnv;
+use crate::lock::Lock;
+use crate::symbol::SymbolId;
+
+/// call counts and time per function while profiling is on, see [`Interpreter::set_profiling`](crate::Interpreter::set_profiling).
+/// functions are told apart by the name they were defined under, anonymous lambdas share one entry.
+/// whether it is on is checked on every call, so that is an atomic rather than behind the lock
+#[derive(Default)]
+pub(crate) struct Profiler {
+    enabled: AtomicBool,
+    stats: Lock<HashMap<Option<SymbolId>, Stats>>,
+}
+
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    time: Duration,
+}
+
+impl Profiler {
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_enabled(&self, on: bool) {
+        self.enabled.store(on, Ordering::Relaxed);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.stats.borrow_mut().clear();
+    }
+
+    /// a table of the functions called so far, the ones that took the most time first
+    pub(crate) fn report(&self) -> String {
+        let stats = self.stats.borrow();
+        let mut rows: Vec<(String, &Stats)> = stats
+            .iter()
+            .map(|(name, stats)| (name.map_or("(lambda)".to_string(), |name| name.to_string()), stats))
+            .collect();
+
+        rows.sort_by(|a, b| b.1.time.cmp(&a.1.time).then_with(|| a.0.cmp(&b.0)));
+
+        let mut res = format!("{:>10}  {:>12}  function\n", "calls", "total ms");
+
+        for (name, stats) in rows {
+            res.push_str(&format!("{:>10}  {:>12.3}  {}\n", stats.calls, stats.time.as_secs_f64() * 1000.0, name));
+        }
+
+        res
+    }
+}
+
+/// counts a call when it starts and adds the time it ran for when dropped. time is cumulative, a
+/// call includes the calls it makes, except that a tail call ends the time of its caller
+pub(crate) struct CallTimer {
+    profiler: Arc<Profiler>,
+    name: Option<SymbolId>,
+    start: Instant,
+}
+
+impl CallTimer {
+    /// starts timing a call of the function named `name` from `env`, unless profiling is off
+    pub(crate) fn start(env: &LangEnv, name: Option<SymbolId>) -> Option<CallTimer> {
+        if !env.profiler.enabled() {
+            return None;
+        }
+
+        env.profiler.stats.borrow_mut().entry(name).or_default().calls += 1;
+
+        Some(CallTimer { profiler: env.profiler.clone(), name, start: Instant::now() })
+    }
+}
+
+impl Drop for CallTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        self.profiler.stats.borrow_mut().entry(self.name).or_default().time += elapsed;
+    }
+}
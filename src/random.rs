@@ -0,0 +1,50 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    /// the state of the generator behind `random` and `rand-int`, seeded from the clock until
+    /// `seed-random` says otherwise
+    static STATE: Cell<u64> = Cell::new(clock_seed());
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// restarts the sequence, the same seed always gives the same numbers after it
+pub(crate) fn seed(seed: u64) {
+    STATE.with(|state| state.set(seed));
+}
+
+/// the next number of a splitmix64 sequence, good enough for scripts but not for cryptography
+pub(crate) fn next_u64() -> u64 {
+    STATE.with(|state| {
+        let next = state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state.set(next);
+
+        let mut z = next;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// a float in [0, 1)
+pub(crate) fn next_f64() -> f64 {
+    // the top 53 bits make every representable value in the range equally likely
+    (next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// an integer in [0, bound), `bound` must be positive
+pub(crate) fn below(bound: u64) -> u64 {
+    // rejecting the uneven tail of the range keeps small results from being more likely
+    let zone = u64::MAX - u64::MAX % bound;
+
+    loop {
+        let n = next_u64();
+
+        if n < zone {
+            return n % bound;
+        }
+    }
+}
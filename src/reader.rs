@@ -0,0 +1,652 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::num::ParseFloatError;
+use std::ops::Range;
+use std::str::Chars;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError};
+
+use crate::env::LangEnv;
+use crate::eval::apply;
+use crate::exp::{LangErr, LangExp, LangMap, Span, CHAR_NAMES};
+use crate::list::{LangList, WeakList};
+use crate::lock::Lock;
+use crate::symbol::{self, SymbolId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    /// the prefix of a reader macro from the read table, such as `'`
+    Macro(String),
+    Str(String),
+    Char(char),
+    Atom(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SpannedToken {
+    pub(crate) token: Token,
+    pub(crate) span: Span,
+}
+
+/// signature of reader macros implemented in rust: they get the form after the prefix and return
+/// the form to read in its place
+pub type ReaderFn = fn(LangExp) -> Result<LangExp, LangErr>;
+
+/// what the reader does with the form after a reader macro's prefix
+#[derive(Clone)]
+pub(crate) enum ReaderMacro {
+    /// reads `(symbol form)`, the way `'x` reads as `(quote x)`
+    Wrap(SymbolId),
+    Native(ReaderFn),
+    /// a lisp function from `set-macro-character`, called from the scope that set it
+    Lisp(LangExp, LangEnv),
+}
+
+/// the reader macros in force, see `set-macro-character`. a prefix is recognised where a token
+/// starts. one ending in an opening bracket, like `#(`, leaves the bracket to start the form after it
+#[derive(Clone)]
+pub(crate) struct ReadTable {
+    /// longest prefix first, so `,@` wins over `,`
+    macros: Vec<(String, ReaderMacro)>,
+    /// bumped by every change, so code reading ahead can tell its tokens are stale
+    version: u64,
+}
+
+impl Default for ReadTable {
+    /// quote and its relatives, and `#(...)` for vectors
+    fn default() -> ReadTable {
+        let mut table = ReadTable { macros: vec![], version: 0 };
+
+        table.set("'", ReaderMacro::Wrap(symbol::QUOTE));
+        table.set("`", ReaderMacro::Wrap(symbol::QUASIQUOTE));
+        table.set(",", ReaderMacro::Wrap(symbol::UNQUOTE));
+        table.set(",@", ReaderMacro::Wrap(symbol::UNQUOTE_SPLICING));
+        table.set("#(", ReaderMacro::Native(list_to_vector));
+
+        table
+    }
+}
+
+impl ReadTable {
+    /// makes `prefix` a reader macro, replacing any it already was
+    pub(crate) fn set(&mut self, prefix: &str, action: ReaderMacro) {
+        self.macros.retain(|(known, _)| known != prefix);
+        self.macros.push((prefix.to_string(), action));
+        self.macros.sort_by_key(|(known, _)| std::cmp::Reverse(known.len()));
+        self.version += 1;
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// the prefix `source` starts with, if any
+    fn prefix_of(&self, source: &str) -> Option<&str> {
+        self.macros.iter().map(|(prefix, _)| prefix.as_str()).find(|prefix| source.starts_with(prefix))
+    }
+
+    fn get(&self, prefix: &str) -> Option<&ReaderMacro> {
+        self.macros.iter().find(|(known, _)| known == prefix).map(|(_, action)| action)
+    }
+}
+
+/// checks that `prefix` can be given to `set-macro-character`: something that can start a token
+/// and isn't already taken by the reader itself
+pub(crate) fn check_macro_prefix(prefix: &str) -> Result<(), LangErr> {
+    let first = match prefix.chars().next() {
+        Some(c) => c,
+        None => return Err(LangErr::Reason("a reader macro needs a prefix".to_string()))
+    };
+
+    if prefix.chars().any(char::is_whitespace) || "()[]{}\";".contains(first) || prefix.starts_with("#\\") {
+        return Err(LangErr::Reason(format!("`{}` can't be a reader macro prefix", prefix)));
+    }
+
+    Ok(())
+}
+
+/// `#(1 2 3)` reads as the vector `[1 2 3]`
+fn list_to_vector(form: LangExp) -> Result<LangExp, LangErr> {
+    match &form {
+        LangExp::List(items) => Ok(LangExp::Vector(Arc::new(Lock::new(items.to_vec())))),
+        _ => Err(LangErr::type_error("a list after #", &form))
+    }
+}
+
+/// a top-level form together with where it came from, so evaluation errors can point back into the source
+pub(crate) struct Form {
+    pub(crate) exp: LangExp,
+    pub(crate) span: Span,
+    pub(crate) tokens: Range<usize>,
+}
+
+/// list addresses to spans and the source they are in, see `LIST_SPANS`
+type ListSpans = HashMap<(usize, usize), (WeakList, Span, u64)>;
+
+/// where each list read from source text was, so backtraces can point at the calls they came
+/// through. the weak reference keeps the address from being reused by a different list. it is
+/// shared by all threads, code read on one may be called on another
+static LIST_SPANS: LazyLock<Mutex<ListSpans>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// the number of the last source text `SourceGuard` was entered for
+static SOURCES: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// the source text being read and evaluated on this thread, 0 for none
+    static SOURCE: Cell<u64> = const { Cell::new(0) };
+}
+
+fn list_spans() -> MutexGuard<'static, ListSpans> {
+    LIST_SPANS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// the source span of a list read by the parser, while the source it came from is still around
+pub(crate) fn list_span(list: &LangList) -> Option<Span> {
+    list_spans().get(&list.address()).map(|(_, span, _)| *span)
+}
+
+/// the span of a list read from the source text being evaluated, which errors are rendered against
+pub(crate) fn source_span(list: &LangList) -> Option<Span> {
+    let source = SOURCE.with(|source| source.get());
+
+    match list_spans().get(&list.address()) {
+        Some((_, span, read_from)) if source != 0 && *read_from == source => Some(*span),
+        _ => None
+    }
+}
+
+fn record_list_span(list: &LangList, span: Span, source: u64) {
+    list_spans().insert(list.address(), (list.downgrade(), span, source));
+}
+
+/// gives `to`, a list rewritten from `from`, the span `from` was read from
+pub(crate) fn copy_list_span(from: &LangList, to: &LangList) {
+    let entry = list_spans().get(&from.address()).map(|(_, span, source)| (*span, *source));
+
+    if let Some((span, source)) = entry {
+        record_list_span(to, span, source);
+    }
+}
+
+/// marks the source text read and evaluated on this thread while it is kept, restoring the one
+/// before when dropped, so a file loaded in the middle of another is a source of its own
+pub(crate) struct SourceGuard(u64);
+
+impl SourceGuard {
+    /// enters new source text
+    pub(crate) fn enter() -> SourceGuard {
+        SourceGuard::set(SOURCES.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// reads text that isn't evaluated as source, such as the argument of `read-string`
+    pub(crate) fn detached() -> SourceGuard {
+        SourceGuard::set(0)
+    }
+
+    fn set(source: u64) -> SourceGuard {
+        SourceGuard(SOURCE.with(|current| current.replace(source)))
+    }
+}
+
+impl Drop for SourceGuard {
+    fn drop(&mut self) {
+        SOURCE.with(|current| current.set(self.0));
+    }
+}
+
+/// walks the source one character at a time, keeping track of the current line and column
+struct Scanner<'a> {
+    source: &'a str,
+    chars: Peekable<Chars<'a>>,
+    /// the byte offset of the next character
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Scanner<'_> {
+    /// the source from the next character on
+    fn rest(&self) -> &str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    /// span from `start` up to the current position, or a single character if the line has changed
+    fn span_from(&self, start: Span) -> Span {
+        let len = if self.line == start.line { self.column - start.column } else { 1 };
+
+        Span { len: len.max(1), ..start }
+    }
+
+    fn here(&self) -> Span {
+        Span { line: self.line, column: self.column, len: 1 }
+    }
+}
+
+/// splits source text into tokens, keeping string literals (and the whitespace inside them) intact.
+/// the prefixes of `table`'s reader macros become tokens of their own
+pub(crate) fn tokenize(exp: &str, table: &ReadTable) -> Result<Vec<SpannedToken>, LangErr> {
+    let mut tokens = vec![];
+    let mut scanner = Scanner { source: exp, chars: exp.chars().peekable(), offset: 0, line: 1, column: 1 };
+
+    while let Some(c) = scanner.peek() {
+        let start = scanner.here();
+
+        // `#\` always starts a character, whatever macros there are
+        if let Some(prefix) = table.prefix_of(scanner.rest()).filter(|_| !scanner.rest().starts_with("#\\")) {
+            let prefix = prefix.to_string();
+            let consumed = prefix.strip_suffix(['(', '[', '{']).unwrap_or(&prefix);
+
+            for _ in consumed.chars() {
+                scanner.next();
+            }
+
+            tokens.push(SpannedToken { token: Token::Macro(prefix), span: scanner.span_from(start) });
+            continue;
+        }
+
+        let token = match c {
+            '(' => {
+                scanner.next();
+                Token::LParen
+            }
+
+            ')' => {
+                scanner.next();
+                Token::RParen
+            }
+
+            '[' => {
+                scanner.next();
+                Token::LBracket
+            }
+
+            ']' => {
+                scanner.next();
+                Token::RBracket
+            }
+
+            '{' => {
+                scanner.next();
+                Token::LBrace
+            }
+
+            '}' => {
+                scanner.next();
+                Token::RBrace
+            }
+
+            '"' => {
+                scanner.next();
+                Token::Str(read_string(&mut scanner, start)?)
+            }
+
+            ';' => {
+                // line comment, skip to the end of the line
+                while let Some(c) = scanner.next() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            _ if c.is_whitespace() => {
+                scanner.next();
+                continue;
+            }
+
+            _ => {
+                let mut atom = String::new();
+
+                while let Some(c) = scanner.peek() {
+                    if c.is_whitespace() || "()[]{}\";".contains(c) {
+                        break;
+                    }
+
+                    atom.push(c);
+                    scanner.next();
+                }
+
+                match atom.strip_prefix("#\\") {
+                    Some(name) => Token::Char(read_char(name, &mut scanner, start)?),
+                    None => Token::Atom(atom)
+                }
+            }
+        };
+
+        tokens.push(SpannedToken { token, span: scanner.span_from(start) });
+    }
+
+    Ok(tokens)
+}
+
+/// the character of a `#\` literal, given what followed the backslash up to a delimiter. a
+/// delimiter right after the backslash is the character itself, as in `#\(` or `#\ `
+fn read_char(name: &str, scanner: &mut Scanner, start: Span) -> Result<char, LangErr> {
+    let mut chars = name.chars();
+
+    match (chars.next(), chars.next()) {
+        (None, _) => scanner.next().ok_or_else(|| LangErr::Parse("expected a character after `#\\`".to_string()).at(start)),
+        (Some(c), None) => Ok(c),
+        _ => CHAR_NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, c)| *c)
+            .ok_or_else(|| LangErr::Parse(format!("unknown character name `#\\{}`", name)).at(scanner.span_from(start)))
+    }
+}
+
+/// true when `source` stops partway through a form (unclosed parentheses, an open string literal
+/// or a dangling quote), so a front-end should keep reading lines before evaluating it
+pub fn is_incomplete(source: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut in_comment = false;
+    let mut dangling_quote = false;
+    let mut previous = None;
+    let mut chars = source.chars();
+
+    while let Some(c) = chars.next() {
+        // the character after `#\` is taken literally, even a parenthesis or a quote
+        if previous == Some('#') && c == '\\' && !in_string && !in_comment {
+            chars.next();
+            previous = None;
+            dangling_quote = false;
+            continue;
+        }
+
+        previous = Some(c);
+
+        if in_comment {
+            in_comment = c != '\n';
+            continue;
+        }
+
+        if in_string {
+            match c {
+                '\\' => { chars.next(); }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            ';' => in_comment = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+
+        if !c.is_whitespace() {
+            dangling_quote = matches!(c, '\'' | '`' | ',' | '@');
+        }
+    }
+
+    in_string || depth > 0 || dangling_quote
+}
+
+/// reads the body of a string literal up to the closing quote, resolving escape sequences.
+/// `start` is the opening quote, which is where an unterminated literal gets reported
+fn read_string(scanner: &mut Scanner, start: Span) -> Result<String, LangErr> {
+    let mut res = String::new();
+    let unterminated = || LangErr::Parse("unterminated string literal".to_string()).at(start);
+
+    loop {
+        let escape_start = scanner.here();
+        let c = scanner.next().ok_or_else(unterminated)?;
+
+        match c {
+            '"' => return Ok(res),
+
+            '\\' => {
+                let escaped = scanner.next().ok_or_else(unterminated)?;
+
+                res.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    '"' => '"',
+                    '\\' => '\\',
+                    _ => return Err(
+                        LangErr::Parse(format!("unknown escape sequence `\\{}`", escaped))
+                            .at(scanner.span_from(escape_start))
+                    )
+                });
+            }
+
+            _ => res.push(c)
+        }
+    }
+}
+
+/// reads every form in `source` as data, without evaluating anything. the inverse of `write`.
+/// only the standard reader macros apply, not ones an interpreter added
+pub fn read_str(source: &str) -> Result<Vec<LangExp>, LangErr> {
+    read_str_with(source, &ReadTable::default())
+}
+
+/// `read_str` with the reader macros of `table`
+pub(crate) fn read_str_with(source: &str, table: &ReadTable) -> Result<Vec<LangExp>, LangErr> {
+    let _source = SourceGuard::detached();
+    let forms = read_all(&tokenize(source, table)?, table)?;
+
+    Ok(forms.into_iter().map(|form| form.exp).collect())
+}
+
+/// reads every top-level form in the token stream
+pub(crate) fn read_all(tokens: &[SpannedToken], table: &ReadTable) -> Result<Vec<Form>, LangErr> {
+    // forget lists that have been dropped since the last read
+    list_spans().retain(|_, (list, _, _)| list.is_alive());
+
+    let mut res = vec![];
+    let mut xs = tokens;
+
+    while let Some(first) = xs.first() {
+        let (exp, rest) = parse(xs, table)?;
+
+        let start = tokens.len() - xs.len();
+        let end = tokens.len() - rest.len();
+        let last = &tokens[end - 1];
+
+        let span = span_between(first.span, last.span);
+
+        res.push(Form { exp, span, tokens: start..end });
+        xs = rest;
+    }
+
+    Ok(res)
+}
+
+/// how deeply forms may nest. the parser itself doesn't recurse, but evaluating, printing and
+/// dropping a form do, so absurdly nested input is rejected up front
+const MAX_NESTING: usize = 10_000;
+
+/// a form the parser has started but not finished
+enum Pending {
+    /// a list, vector or map waiting for `close`; `open` is reported if it never comes
+    Seq { items: Vec<LangExp>, open: Span, close: Token },
+    /// a reader macro such as `'x`, waiting for the form it expands around
+    Prefix { prefix: String, action: ReaderMacro, span: Span },
+}
+
+/// parses one form from the front of the token stream, returning it with the tokens after it.
+/// nesting is tracked on an explicit stack rather than by recursion, so deep input can't overflow
+fn parse<'a>(tokens: &'a [SpannedToken], table: &ReadTable) -> Result<(LangExp, &'a [SpannedToken]), LangErr> {
+    let mut stack: Vec<Pending> = vec![];
+    let mut xs = tokens;
+
+    loop {
+        let (token, rest) = match xs.split_first() {
+            Some(split) => split,
+            None => return Err(unfinished(stack.last()))
+        };
+
+        xs = rest;
+
+        let opened = match &token.token {
+            Token::LParen => Some(Pending::Seq { items: vec![], open: token.span, close: Token::RParen }),
+            Token::LBracket => Some(Pending::Seq { items: vec![], open: token.span, close: Token::RBracket }),
+            Token::LBrace => Some(Pending::Seq { items: vec![], open: token.span, close: Token::RBrace }),
+            Token::Macro(prefix) => match table.get(prefix) {
+                Some(action) => Some(Pending::Prefix { prefix: prefix.clone(), action: action.clone(), span: token.span }),
+                None => return Err(LangErr::Parse(format!("`{}` is not a reader macro here", prefix)).at(token.span))
+            },
+            _ => None
+        };
+
+        if let Some(pending) = opened {
+            if stack.len() == MAX_NESTING {
+                return Err(LangErr::Parse(format!("forms nested more than {} deep", MAX_NESTING)).at(token.span));
+            }
+
+            stack.push(pending);
+            continue;
+        }
+
+        let mut exp = match &token.token {
+            Token::RParen | Token::RBracket | Token::RBrace => match stack.pop() {
+                Some(Pending::Seq { items, open, close }) if close == token.token => match close {
+                    Token::RBracket => LangExp::Vector(Arc::new(Lock::new(items))),
+                    // a map literal is data: like a quoted form, its keys and values are not evaluated
+                    Token::RBrace if !items.len().is_multiple_of(2) =>
+                        return Err(LangErr::Parse("a map literal needs a value for every key".to_string())
+                            .at(span_between(open, token.span))),
+                    Token::RBrace => {
+                        let mut items = items.into_iter();
+                        let mut map = LangMap::default();
+
+                        while let (Some(k), Some(v)) = (items.next(), items.next()) {
+                            map.insert(k, v);
+                        }
+
+                        LangExp::Map(Arc::new(map))
+                    }
+                    _ => {
+                        let list = LangList::new(items);
+                        record_list_span(&list, span_between(open, token.span), SOURCE.with(|source| source.get()));
+                        LangExp::List(list)
+                    }
+                },
+                _ if token.token == Token::RBracket =>
+                    return Err(LangErr::Parse("unbalanced brackets: unexpected `]`".to_string()).at(token.span)),
+                _ if token.token == Token::RBrace =>
+                    return Err(LangErr::Parse("unbalanced braces: unexpected `}`".to_string()).at(token.span)),
+                _ => return Err(LangErr::Parse("unbalanced parentheses: unexpected `)`".to_string()).at(token.span))
+            },
+            Token::Str(s) => LangExp::Str(s.clone().into()),
+            Token::Char(c) => LangExp::Char(*c),
+            Token::Atom(atom) => parse_atom(atom),
+            _ => unreachable!("opening tokens are handled above")
+        };
+
+        // a finished form completes any shorthand waiting for it, then joins the enclosing sequence
+        loop {
+            match stack.pop() {
+                None => return Ok((exp, xs)),
+                Some(Pending::Prefix { action, span, .. }) => exp = match action {
+                    ReaderMacro::Wrap(form) => LangExp::list(vec![LangExp::Symbol(form), exp]),
+                    ReaderMacro::Native(f) => f(exp).map_err(|e| e.at(span))?,
+                    ReaderMacro::Lisp(f, env) => apply(&f, &[exp], &env).map_err(|e| e.at(span))?
+                },
+                Some(Pending::Seq { mut items, open, close }) => {
+                    items.push(exp);
+                    stack.push(Pending::Seq { items, open, close });
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// from the start of `first` to the end of `last`, or just `first` when they are on different lines
+fn span_between(first: Span, last: Span) -> Span {
+    if last.line == first.line {
+        Span { len: last.column + last.len - first.column, ..first }
+    } else {
+        first
+    }
+}
+
+/// the error for input that ends while `pending` is still open
+fn unfinished(pending: Option<&Pending>) -> LangErr {
+    match pending {
+        Some(Pending::Seq { open, close: Token::RBracket, .. }) =>
+            LangErr::Parse("unbalanced brackets: missing `]`".to_string()).at(*open),
+        Some(Pending::Seq { open, close: Token::RBrace, .. }) =>
+            LangErr::Parse("unbalanced braces: missing `}`".to_string()).at(*open),
+        Some(Pending::Seq { open, .. }) =>
+            LangErr::Parse("unbalanced parentheses: missing `)`".to_string()).at(*open),
+        Some(Pending::Prefix { prefix, span, .. }) =>
+            LangErr::Parse(format!("expected a form after `{}`", prefix)).at(*span),
+        None => LangErr::Parse("unexpected end of input".to_string())
+    }
+}
+
+/// the position of the first occurrence of `name` among the tokens of a form
+pub(crate) fn find_symbol(tokens: &[SpannedToken], name: &str) -> Option<Span> {
+    tokens
+        .iter()
+        .find(|t| matches!(&t.token, Token::Atom(atom) if atom == name))
+        .map(|t| t.span)
+}
+
+fn parse_atom(token: &str) -> LangExp {
+    match token {
+        "true" | "#t" => LangExp::Bool(true),
+        "false" | "#f" => LangExp::Bool(false),
+        "nil" => LangExp::nil(),
+        "+inf.0" => LangExp::Number(f64::INFINITY),
+        "-inf.0" => LangExp::Number(f64::NEG_INFINITY),
+        "+nan.0" => LangExp::Number(f64::NAN),
+        _ => parse_number(token).unwrap_or_else(|| LangExp::Symbol(SymbolId::intern(token)))
+    }
+}
+
+/// reads a number literal the way the reader does, as used by `string->number`
+pub(crate) fn parse_number(token: &str) -> Option<LangExp> {
+    if !looks_numeric(token) {
+        return None;
+    }
+
+    // integers are preferred, anything else that parses as a float becomes one
+    if let Ok(i) = token.parse::<i64>() {
+        return Some(LangExp::Int(i));
+    }
+
+    let potential_float: Result<f64, ParseFloatError> = token.parse();
+
+    potential_float.ok().map(LangExp::Number)
+}
+
+/// keeps words like `inf` or `nan`, which rust happily parses as floats, usable as symbols
+fn looks_numeric(token: &str) -> bool {
+    let digits = token.trim_start_matches(['+', '-']).trim_start_matches('.');
+
+    digits.starts_with(|c: char| c.is_ascii_digit())
+}
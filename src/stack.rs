@@ -0,0 +1,115 @@
+use std::cell::Cell;
+use std::hint::black_box;
+
+use crate::exp::LangErr;
+
+/// how much stack an evaluation may use when neither the embedder nor the thread says, small
+/// enough to be safe on a spawned thread's default 2 MiB stack
+const DEFAULT_STACK_LIMIT: usize = 1024 * 1024;
+
+/// the least stack kept unused when the budget is taken from the thread's own stack, for whatever
+/// runs between two checks and for reporting the error. an eighth of the stack is kept if that is more
+const MIN_HEADROOM: usize = 64 * 1024;
+
+thread_local! {
+    /// where the outermost evaluation on this thread started, and how far past it we may go
+    static BUDGET: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// an approximation of the current stack pointer
+fn stack_position() -> usize {
+    let marker = 0u8;
+    black_box(&marker) as *const u8 as usize
+}
+
+/// the lowest address of the calling thread's stack, where it is known
+#[cfg(target_os = "linux")]
+fn stack_bottom() -> Option<usize> {
+    use std::os::raw::{c_int, c_ulong, c_void};
+
+    /// room for the C library's `pthread_attr_t`, which is at most 64 bytes on linux
+    #[repr(C, align(16))]
+    struct Attr([u8; 64]);
+
+    extern "C" {
+        fn pthread_self() -> c_ulong;
+        fn pthread_getattr_np(thread: c_ulong, attr: *mut Attr) -> c_int;
+        fn pthread_attr_getstack(attr: *const Attr, addr: *mut *mut c_void, size: *mut usize) -> c_int;
+        fn pthread_attr_destroy(attr: *mut Attr) -> c_int;
+    }
+
+    let mut attr = Attr([0; 64]);
+    let mut addr = std::ptr::null_mut();
+    let mut size = 0;
+
+    // SAFETY: `attr` is big and aligned enough for a `pthread_attr_t`, which `pthread_getattr_np`
+    // initializes for the calling thread and which is only read and destroyed after that succeeded
+    unsafe {
+        if pthread_getattr_np(pthread_self(), &mut attr) != 0 {
+            return None;
+        }
+
+        let res = pthread_attr_getstack(&attr, &mut addr, &mut size);
+        pthread_attr_destroy(&mut attr);
+
+        (res == 0 && !addr.is_null()).then_some(addr as usize)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn stack_bottom() -> Option<usize> {
+    None
+}
+
+/// how much of the calling thread's stack is left below here, less some headroom
+fn available() -> Option<usize> {
+    let left = stack_position().checked_sub(stack_bottom()?)?;
+
+    Some(left - MIN_HEADROOM.max(left / 8).min(left))
+}
+
+/// marks the start of an evaluation that may use `limit` bytes of stack, or most of what the
+/// thread has left when that is `None`, unless one is already running on this thread; the budget
+/// is released when the guard is dropped
+pub(crate) struct StackGuard {
+    outermost: bool,
+}
+
+impl StackGuard {
+    pub(crate) fn enter(limit: Option<usize>) -> StackGuard {
+        let outermost = BUDGET.with(|budget| budget.get().is_none());
+
+        if outermost {
+            let limit = limit.or_else(available).unwrap_or(DEFAULT_STACK_LIMIT);
+            BUDGET.with(|budget| budget.set(Some((stack_position(), limit))));
+        }
+
+        StackGuard { outermost }
+    }
+}
+
+impl Drop for StackGuard {
+    fn drop(&mut self) {
+        if self.outermost {
+            BUDGET.with(|budget| budget.set(None));
+        }
+    }
+}
+
+/// true when the running evaluation has used up its stack budget
+pub(crate) fn exhausted() -> bool {
+    BUDGET.with(|budget| match budget.get() {
+        Some((base, limit)) => base.abs_diff(stack_position()) > limit,
+        None => false
+    })
+}
+
+/// fails once the stack budget is used up, so deep recursion becomes an error instead of a crash.
+/// like going past the depth limit, it can't be caught with `try`
+pub(crate) fn check() -> Result<(), LangErr> {
+    if exhausted() {
+        return Err(LangErr::LimitExceeded("recursion or nesting is too deep for the stack".to_string()));
+    }
+
+    Ok(())
+}
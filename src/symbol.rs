@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// an interned symbol name. symbols with the same name have the same id, so comparing and hashing
+/// them is as cheap as it is for an integer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(u32);
+
+struct Interner {
+    names: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, SymbolId>,
+    /// how many symbols `gensym` has made, to number their names
+    generated: usize,
+}
+
+/// one interner for the whole process, so a symbol means the same on every thread
+static INTERNER: LazyLock<RwLock<Interner>> = LazyLock::new(|| RwLock::new(Interner::with_well_known()));
+
+fn interner() -> RwLockReadGuard<'static, Interner> {
+    INTERNER.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn interner_mut() -> RwLockWriteGuard<'static, Interner> {
+    INTERNER.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Interner {
+    fn with_well_known() -> Interner {
+        let mut interner = Interner { names: vec![], ids: HashMap::new(), generated: 0 };
+
+        for name in WELL_KNOWN {
+            interner.intern(name);
+        }
+
+        interner
+    }
+
+    fn intern(&mut self, name: &str) -> SymbolId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+
+        let id = SymbolId(self.names.len() as u32);
+        let name: Arc<str> = name.into();
+
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+
+        id
+    }
+}
+
+impl SymbolId {
+    /// the id for `name`, the same every time it is asked for
+    pub fn intern(name: &str) -> SymbolId {
+        if let Some(id) = interner().ids.get(name) {
+            return *id;
+        }
+
+        interner_mut().intern(name)
+    }
+
+    /// a new symbol that is different from every other one, even one read later with the same
+    /// name: it gets an id of its own without being interned
+    pub fn gensym(prefix: &str) -> SymbolId {
+        let mut interner = interner_mut();
+        interner.generated += 1;
+
+        let id = SymbolId(interner.names.len() as u32);
+        let name = format!("{}{}", prefix, interner.generated);
+        interner.names.push(name.into());
+
+        id
+    }
+
+    /// whether reading the symbol's name gives back this symbol, which it doesn't for one made by `gensym`
+    pub(crate) fn is_interned(self) -> bool {
+        let interner = interner();
+
+        interner.ids.get(&interner.names[self.0 as usize]) == Some(&self)
+    }
+
+    pub fn name(self) -> Arc<str> {
+        interner().names[self.0 as usize].clone()
+    }
+}
+
+impl fmt::Display for SymbolId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// defines a constant for each name, numbered in order, and the list the interner starts out with,
+/// so matching on one of these symbols doesn't need to look anything up
+macro_rules! well_known {
+    ($($id:ident => $name:literal,)*) => {
+        const WELL_KNOWN: &[&str] = &[$($name),*];
+
+        well_known!(@consts 0, $($id,)*);
+    };
+
+    (@consts $n:expr, $id:ident, $($rest:ident,)*) => {
+        pub(crate) const $id: SymbolId = SymbolId($n);
+
+        well_known!(@consts $n + 1, $($rest,)*);
+    };
+
+    (@consts $n:expr,) => {};
+}
+
+well_known! {
+    QUOTE => "quote",
+    QUASIQUOTE => "quasiquote",
+    UNQUOTE => "unquote",
+    UNQUOTE_SPLICING => "unquote-splicing",
+    IF => "if",
+    COND => "cond",
+    ELSE => "else",
+    BEGIN => "begin",
+    AND => "and",
+    OR => "or",
+    LET => "let",
+    LET_STAR => "let*",
+    LETREC => "letrec",
+    DEFINE => "define",
+    SET => "set!",
+    LAMBDA => "lambda",
+    DEFMACRO => "defmacro",
+    MACROEXPAND => "macroexpand",
+    MACROEXPAND_1 => "macroexpand-1",
+    LOAD => "load",
+    REQUIRE => "require",
+    TRY => "try",
+    CATCH => "catch",
+    DOC => "doc",
+    MATCH => "match",
+    WILDCARD => "_",
+    CONS => "cons",
+    LIST => "list",
+    PREDICATE => "?",
+    DEFSTRUCT => "defstruct",
+    DELAY => "delay",
+    WHILE => "while",
+    DOTIMES => "dotimes",
+    LOOP => "loop",
+    RECUR => "recur",
+    TIME => "time",
+    BREAK => "break",
+    OPTIONAL => "&optional",
+    REST => "&rest",
+    LEXICAL => "%lexical",
+    ASSERT => "assert",
+    ASSERT_EQ => "assert-eq",
+    DEFTEST => "deftest",
+}
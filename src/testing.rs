@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::builtins::write_out;
+use crate::env::LangEnv;
+use crate::eval::apply;
+use crate::exp::{plural, LangErr, LangExp};
+use crate::lock::Lock;
+use crate::symbol::SymbolId;
+
+/// the tests declared with `deftest`, in the order they were first declared
+#[derive(Default)]
+pub(crate) struct Tests {
+    tests: Lock<Vec<(SymbolId, LangExp)>>,
+    /// how many failed the last time `run-tests` ran them
+    failed: AtomicUsize,
+}
+
+impl Tests {
+    /// declares a test, replacing one declared before under the same name
+    pub(crate) fn add(&self, name: SymbolId, test: LangExp) {
+        let mut tests = self.tests.borrow_mut();
+
+        match tests.iter_mut().find(|(declared, _)| *declared == name) {
+            Some(declared) => declared.1 = test,
+            None => tests.push((name, test))
+        }
+    }
+
+    /// calls each test, printing whether it passed and why it didn't, then how many did. returns
+    /// whether they all passed
+    pub(crate) fn run(&self, env: &LangEnv) -> Result<LangExp, LangErr> {
+        // a test may declare tests of its own, which run the next time
+        let tests = self.tests.borrow().clone();
+        let mut failed = 0;
+
+        for (name, test) in &tests {
+            match apply(test, &[], env) {
+                Err(err) if err.exit_code().is_some() => return Err(err),
+                Ok(_) => write_out(env, &format!("test {} ... ok\n", name))?,
+                Err(err) => {
+                    failed += 1;
+                    write_out(env, &format!("test {} ... FAILED\n  {}\n", name, err))?;
+                }
+            }
+        }
+
+        write_out(env, &format!("\n{}, {} passed, {} failed\n", plural(tests.len(), "test"), tests.len() - failed, failed))?;
+        self.failed.store(failed, Ordering::Relaxed);
+
+        Ok(LangExp::Bool(failed == 0))
+    }
+
+    pub(crate) fn failed(&self) -> usize {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::env::LangEnv;
+use crate::eval::{apply, is_callable};
+use crate::exp::{LangErr, LangExp};
+use crate::limits::{self, LimitGuard};
+use crate::stack::StackGuard;
+
+/// a thread started by `spawn`. it shares the global environment, and any vector, record or
+/// scope it was handed, with the thread that started it
+pub struct ThreadHandle {
+    state: Mutex<ThreadState>,
+}
+
+enum ThreadState {
+    Running(JoinHandle<Result<LangExp, LangErr>>),
+    /// what the thread returned, kept so joining again gives the same value
+    Finished(LangExp),
+    /// the error the thread failed with, which the first `join` raised
+    Failed(String),
+}
+
+/// an unbounded queue of values between threads. every clone of the value is the same channel,
+/// which any thread may send to and receive from
+pub struct Channel {
+    state: Mutex<ChannelState>,
+    sent: Condvar,
+    /// the threads of the interpreter that made the channel
+    threads: Arc<Threads>,
+}
+
+struct ChannelState {
+    queue: VecDeque<LangExp>,
+    /// the threads waiting in `recv!`
+    receivers: usize,
+}
+
+impl ChannelState {
+    /// how many of the waiting receivers have a value to take, and so still count as active
+    fn served(&self) -> usize {
+        self.queue.len().min(self.receivers)
+    }
+}
+
+/// the threads of an interpreter that could still send a value: the one it is driven from, and
+/// those started by `spawn` that are running, less any of them waiting in `join`, or in `recv!`
+/// with no value sent for them yet. once none is left, a receive on an empty channel can only wait
+/// forever
+pub(crate) struct Threads {
+    active: AtomicUsize,
+}
+
+impl Default for Threads {
+    fn default() -> Threads {
+        Threads { active: AtomicUsize::new(1) }
+    }
+}
+
+impl Threads {
+    fn none_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// how long a receive waits before checking whether a value can still arrive
+const RECV_POLL: Duration = Duration::from_millis(20);
+
+/// counts a spawned thread as active for as long as it is kept, even if the thread panics
+struct Running(Arc<Threads>);
+
+impl Running {
+    fn start(threads: &Arc<Threads>) -> Running {
+        threads.active.fetch_add(1, Ordering::SeqCst);
+        Running(threads.clone())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// stops counting the current thread as active while it waits for another one to finish
+struct Waiting<'a>(&'a Threads);
+
+impl Waiting<'_> {
+    fn start(threads: &Threads) -> Waiting<'_> {
+        threads.active.fetch_sub(1, Ordering::SeqCst);
+        Waiting(threads)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.active.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// calls `f` without arguments on a new thread. the thread runs under the limits in force, with
+/// the fuel that is left, and evaluates with the tree-walker whichever backend spawned it
+pub(crate) fn spawn(f: &LangExp, env: &LangEnv) -> Result<LangExp, LangErr> {
+    limits::check_io("spawn")?;
+
+    if !is_callable(f) {
+        return Err(LangErr::type_error("a function", f));
+    }
+
+    let (f, env) = (f.clone(), env.clone());
+    let limits = limits::remaining();
+    let running = Running::start(&env.threads);
+
+    let handle = thread::Builder::new()
+        .spawn(move || {
+            let _running = running;
+            let _limits = limits.map(LimitGuard::enter);
+            let _stack = StackGuard::enter(None);
+
+            apply(&f, &[], &env)
+        })
+        .map_err(|e| LangErr::Reason(format!("cannot spawn a thread: {}", e)))?;
+
+    Ok(LangExp::Thread(Arc::new(ThreadHandle { state: Mutex::new(ThreadState::Running(handle)) })))
+}
+
+impl ThreadHandle {
+    /// waits for the thread to finish and returns its value, or raises the error it failed with.
+    /// `env` is the scope of the thread joining, which isn't active while it waits
+    pub(crate) fn join(&self, env: &LangEnv) -> Result<LangExp, LangErr> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let res = match &*state {
+            ThreadState::Finished(value) => return Ok(value.clone()),
+            ThreadState::Failed(msg) => return Err(LangErr::Reason(msg.clone())),
+            ThreadState::Running(_) => match std::mem::replace(&mut *state, ThreadState::Failed(String::new())) {
+                ThreadState::Running(handle) => {
+                    let _waiting = Waiting::start(&env.threads);
+                    handle.join()
+                }
+                _ => unreachable!("the state was just matched as running")
+            }
+        };
+
+        match res {
+            Ok(Ok(value)) => {
+                *state = ThreadState::Finished(value.clone());
+                Ok(value)
+            }
+            Ok(Err(err)) => {
+                *state = ThreadState::Failed(format!("the joined thread failed: {}", err));
+                Err(err)
+            }
+            Err(_) => {
+                *state = ThreadState::Failed("the joined thread panicked".to_string());
+                Err(LangErr::Reason("the joined thread panicked".to_string()))
+            }
+        }
+    }
+}
+
+impl Channel {
+    /// a channel of the interpreter `threads` belongs to
+    pub(crate) fn new(threads: &Arc<Threads>) -> Channel {
+        let state = ChannelState { queue: VecDeque::new(), receivers: 0 };
+
+        Channel { state: Mutex::new(state), sent: Condvar::new(), threads: threads.clone() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ChannelState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// changes the state, counting a waiting receiver as active again as soon as a value is there
+    /// for it to take, before it wakes up to take it
+    fn update(&self, state: &mut ChannelState, f: impl FnOnce(&mut ChannelState)) {
+        let before = state.served();
+        f(state);
+        let after = state.served();
+
+        if after > before {
+            self.threads.active.fetch_add(after - before, Ordering::SeqCst);
+        } else {
+            self.threads.active.fetch_sub(before - after, Ordering::SeqCst);
+        }
+    }
+
+    pub(crate) fn send(&self, value: LangExp) {
+        let mut state = self.lock();
+        self.update(&mut state, |state| state.queue.push_back(value));
+        self.sent.notify_one();
+    }
+
+    /// the oldest value sent and not yet received, waiting for one if there is none. fails instead
+    /// of waiting once every other thread of the interpreter `env` belongs to is done or waiting
+    /// too, as none of them could send a value
+    pub(crate) fn recv(&self, env: &LangEnv) -> Result<LangExp, LangErr> {
+        // counted up before down, both on the way in and out, so the count never passes through
+        // zero while a value is still on its way
+        let active = &env.threads.active;
+        let mut state = self.lock();
+        self.update(&mut state, |state| state.receivers += 1);
+        active.fetch_sub(1, Ordering::SeqCst);
+
+        loop {
+            if !state.queue.is_empty() {
+                active.fetch_add(1, Ordering::SeqCst);
+
+                let mut value = None;
+                self.update(&mut state, |state| {
+                    state.receivers -= 1;
+                    value = state.queue.pop_front();
+                });
+
+                return Ok(value.expect("the queue was just checked to be non-empty"));
+            }
+
+            // a thread sends before it stops being active, and the value sent counts a receiver as
+            // active in its place, so nothing can be sent once none is
+            if env.threads.none_active() {
+                active.fetch_add(1, Ordering::SeqCst);
+                self.update(&mut state, |state| state.receivers -= 1);
+
+                return Err(LangErr::Reason("recv!: the channel is empty and no thread is left to send to it".to_string()));
+            }
+
+            state = self.sent.wait_timeout(state, RECV_POLL).unwrap_or_else(PoisonError::into_inner).0;
+        }
+    }
+}
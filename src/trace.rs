@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::exp::{LangErr, LangExp, LangLambda, Span};
+use crate::list::LangList;
+use crate::lock::Lock;
+use crate::reader::list_span;
+use crate::symbol::SymbolId;
+
+/// how many frames an error keeps; runaway recursion would otherwise produce one per call
+const MAX_FRAMES: usize = 32;
+
+/// a lisp call an error propagated through
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    /// the name the function was defined under, `None` for an anonymous lambda
+    pub name: Option<String>,
+    /// where the call was in the source it was read from, when known
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "in `{}`", name)?,
+            None => write!(f, "in a lambda")?
+        }
+
+        match self.span {
+            Some(span) => write!(f, " called at {}:{}", span.line, span.column),
+            None => Ok(())
+        }
+    }
+}
+
+/// a running call, cheap to keep around and only turned into a `TraceFrame` when an error passes through
+#[derive(Clone)]
+pub(crate) struct CallSite {
+    name: Option<SymbolId>,
+    call: Option<LangList>,
+}
+
+impl CallSite {
+    /// a call of `lambda` made by the form `call`, if it came from source code
+    pub(crate) fn new(lambda: &LangLambda, call: Option<&LangExp>) -> CallSite {
+        let call = match call {
+            Some(LangExp::List(list)) => Some(list.clone()),
+            _ => None
+        };
+
+        CallSite { name: lambda.name, call }
+    }
+
+    /// the name the called lambda was defined under
+    pub(crate) fn name(&self) -> Option<SymbolId> {
+        self.name
+    }
+
+    fn frame(&self) -> TraceFrame {
+        TraceFrame {
+            name: self.name.map(|name| name.to_string()),
+            span: self.call.as_ref().and_then(list_span),
+        }
+    }
+}
+
+impl LangErr {
+    /// records that the error propagated out of `site`, pointing it at the call if nothing in
+    /// the called body could be located, say when the lambda was read from another source
+    pub(crate) fn traced(self, site: &CallSite) -> LangErr {
+        let err = match &site.call {
+            Some(call) => self.at_list(call),
+            None => self
+        };
+
+        match err {
+            LangErr::Traced { err, mut frames, omitted } => {
+                if frames.len() < MAX_FRAMES {
+                    frames.push(site.frame());
+                    LangErr::Traced { err, frames, omitted }
+                } else {
+                    LangErr::Traced { err, frames, omitted: omitted + 1 }
+                }
+            }
+            _ => LangErr::Traced { err: Box::new(err), frames: vec![site.frame()], omitted: 0 }
+        }
+    }
+
+    /// the calls the error propagated through, innermost first
+    pub fn backtrace(&self) -> &[TraceFrame] {
+        match self {
+            LangErr::Traced { frames, .. } => frames,
+            LangErr::Located { err, .. } | LangErr::InFile { err, .. } => err.backtrace(),
+            _ => &[]
+        }
+    }
+}
+
+/// the functions `(trace f)` asked to log calls of, by the name they were defined under, and how
+/// many logged calls are running, which the log is indented by. every call asks whether anything
+/// is traced, so that is an atomic rather than behind the lock
+#[derive(Default)]
+pub(crate) struct Tracer {
+    names: Lock<HashSet<SymbolId>>,
+    tracing: AtomicBool,
+    pub(crate) depth: AtomicUsize,
+}
+
+impl Tracer {
+    /// starts logging calls of `names`, or stops when `on` is false. no names with `on` false stops
+    /// logging everything
+    pub(crate) fn update(&self, names: Vec<SymbolId>, on: bool) {
+        let mut traced = self.names.borrow_mut();
+
+        match on {
+            true => traced.extend(names),
+            false if names.is_empty() => traced.clear(),
+            false => {
+                for name in names {
+                    traced.remove(&name);
+                }
+            }
+        }
+
+        self.tracing.store(!traced.is_empty(), Ordering::Relaxed);
+    }
+
+    /// the traced names, sorted
+    pub(crate) fn names(&self) -> Vec<SymbolId> {
+        let mut names: Vec<SymbolId> = self.names.borrow().iter().copied().collect();
+        names.sort_by_key(|name| name.name());
+
+        names
+    }
+
+    /// the name `f` is traced under, if it is traced
+    pub(crate) fn traced_name(&self, f: &LangExp) -> Option<SymbolId> {
+        if !self.tracing.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let name = match f {
+            LangExp::Func(native) => native.name,
+            LangExp::Lambda(lambda) => lambda.name?,
+            _ => return None
+        };
+
+        self.names.borrow().contains(&name).then_some(name)
+    }
+}
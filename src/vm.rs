@@ -0,0 +1,245 @@
+use std::mem;
+use std::sync::Arc;
+
+use crate::compiler::{compile, compile_body, Chunk, Op};
+use crate::env::LangEnv;
+use crate::eval::{self, bind_lambda_args, is_callable, is_truthy, named, RunningCall};
+use crate::exp::{LangErr, LangExp, LangLambda};
+use crate::limits::{self, Level};
+use crate::lock::Lock;
+use crate::profile::CallTimer;
+use crate::trace::CallSite;
+
+/// a call in progress: the code being run, where in it we are and the scopes it has entered
+struct Frame {
+    chunk: Arc<Chunk>,
+    ip: usize,
+    env: LangEnv,
+    /// enclosing scopes of the `let` forms currently entered, innermost last
+    scopes: Vec<LangEnv>,
+    /// the lambda call this frame runs, `None` for the form `run` was given
+    site: Option<CallSite>,
+    /// times the call for the profiler until the frame returns or a tail call replaces it
+    _timer: Option<CallTimer>,
+}
+
+impl Frame {
+    fn new(chunk: Arc<Chunk>, env: LangEnv, site: Option<CallSite>) -> Frame {
+        let _timer = site.as_ref().and_then(|site| CallTimer::start(&env, site.name()));
+
+        Frame { chunk, ip: 0, env, scopes: vec![], site, _timer }
+    }
+}
+
+/// the bytecode backend's counterpart to `eval::eval`: compiles `exp` and runs it. lisp calls
+/// don't use the rust stack, but natives calling back into lisp (`map`, `apply`...) and forms the
+/// compiler leaves to the tree-walker do
+pub(crate) fn eval(exp: &LangExp, env: &LangEnv) -> Result<LangExp, LangErr> {
+    run(Arc::new(compile(exp, env)), env.clone())
+}
+
+/// the compiled body of a lambda, compiling it on the first call
+fn lambda_code(lambda: &LangLambda, env: &LangEnv) -> Arc<Chunk> {
+    lambda.code.get_or_init(|| Arc::new(compile_body(&lambda.body, env))).clone()
+}
+
+/// runs `chunk`, tracing errors through the calls still running when they happened
+fn run(chunk: Arc<Chunk>, env: LangEnv) -> Result<LangExp, LangErr> {
+    let mut frames = vec![Frame::new(chunk, env, None)];
+    let depth = limits::depth();
+
+    let res = run_frames(&mut frames);
+
+    // frames left behind by an error are given up all at once
+    limits::restore_depth(depth);
+
+    // pointed at the innermost form the error left, like the tree-walker does, then traced
+    res.map_err(|err| {
+        frames.iter().rev().fold(err, |err, frame| {
+            let err = match frame.ip.checked_sub(1).and_then(|at| frame.chunk.forms[at].as_ref()) {
+                Some(form) => err.at_list(form),
+                None => err
+            };
+
+            match &frame.site {
+                Some(site) => err.traced(site),
+                None => err
+            }
+        })
+    })
+}
+
+fn run_frames(frames: &mut Vec<Frame>) -> Result<LangExp, LangErr> {
+    let mut stack: Vec<LangExp> = vec![];
+
+    loop {
+        limits::step()?;
+
+        let frame = frames.last_mut().expect("a frame is running until the outermost one returns");
+        let chunk = frame.chunk.clone();
+        let op = &chunk.code[frame.ip];
+        frame.ip += 1;
+
+        match op {
+            Op::Const(exp) => stack.push(exp.clone()),
+
+            Op::Get(k) => stack.push(frame.env.get(*k).ok_or_else(|| LangErr::UnboundSymbol(k.to_string()))?),
+
+            Op::GetHead(k, call, end) => match frame.env.get(*k) {
+                Some(LangExp::Macro(_)) if matches!(chunk.code[end - 1], Op::TailCall(..)) && frame.site.is_some() => {
+                    let res = eval_in_tail(call, frames)?;
+                    stack.push(res);
+                }
+                Some(LangExp::Macro(_)) => {
+                    let res = eval::eval(call, &frame.env)?;
+                    stack.push(res);
+                    frame.ip = *end;
+                }
+                Some(f) => stack.push(f),
+                None => return Err(LangErr::UnboundSymbol(k.to_string()))
+            },
+
+            Op::Define(k) => {
+                let value = pop(&mut stack);
+                frame.env.insert(*k, named(value, *k));
+                stack.push(LangExp::Symbol(*k));
+            }
+
+            Op::Set(k) => {
+                let value = pop(&mut stack);
+
+                if !frame.env.set(*k, value.clone()) {
+                    return Err(LangErr::UnboundSymbol(k.to_string()));
+                }
+
+                stack.push(value);
+            }
+
+            Op::Pop => {
+                pop(&mut stack);
+            }
+
+            Op::Jump(target) => frame.ip = *target,
+
+            Op::JumpIfFalse(target) => {
+                if !is_truthy(&pop(&mut stack)) {
+                    frame.ip = *target;
+                }
+            }
+
+            Op::JumpIfFalseKeep(target) => {
+                if is_truthy(stack.last().expect("a test value on the stack")) {
+                    pop(&mut stack);
+                } else {
+                    frame.ip = *target;
+                }
+            }
+
+            Op::JumpIfTrueKeep(target) => {
+                if is_truthy(stack.last().expect("a test value on the stack")) {
+                    frame.ip = *target;
+                } else {
+                    pop(&mut stack);
+                }
+            }
+
+            Op::Lambda(template) => stack.push(LangExp::Lambda(Arc::new(LangLambda {
+                params: template.params.clone(),
+                body: template.body.clone(),
+                env: frame.env.clone(),
+                name: None,
+                doc: template.doc.clone(),
+                code: template.code.clone(),
+            }))),
+
+            Op::Vector(n) => {
+                let items = stack.split_off(stack.len() - n);
+                stack.push(LangExp::Vector(Arc::new(Lock::new(items))));
+            }
+
+            Op::Call(argc, call) | Op::TailCall(argc, call) => {
+                let args = stack.split_off(stack.len() - argc);
+                let f = pop(&mut stack);
+
+                // traced calls take the tree-walker's application path, which does the logging
+                let traced = frame.env.tracer.traced_name(&f);
+
+                if let Some(name) = traced {
+                    stack.push(eval::apply_traced(name, &f, &args, Some(call), &frame.env)?);
+                    continue;
+                }
+
+                match &f {
+                    LangExp::Func(native) => stack.push(native.call(&args, &frame.env)?),
+
+                    LangExp::Lambda(lambda) => {
+                        let inner = bind_lambda_args(lambda, &args)?;
+                        let site = CallSite::new(lambda, Some(call));
+                        let callee = Frame::new(lambda_code(lambda, &inner), inner, Some(site));
+
+                        // a tail call takes the place of the call it is made from, but the form
+                        // `run` was given isn't one, so calling from its tail still goes a level deeper
+                        if let (Op::TailCall(..), Some(_)) = (op, &frame.site) {
+                            *frame = callee;
+                        } else {
+                            limits::descend()?;
+                            frames.push(callee);
+                        }
+                    }
+
+                    _ if !is_callable(&f) => return Err(LangErr::type_error("a function in head position", &f)),
+
+                    _ => unreachable!("every callable value is handled above")
+                }
+            }
+
+            Op::EnterScope(names) => {
+                let values = stack.split_off(stack.len() - names.len());
+                let inner = LangEnv::new_child(&frame.env);
+
+                for (name, value) in names.iter().zip(values) {
+                    inner.insert(*name, value);
+                }
+
+                frame.scopes.push(mem::replace(&mut frame.env, inner));
+            }
+
+            Op::LeaveScope => frame.env = frame.scopes.pop().expect("LeaveScope follows an EnterScope"),
+
+            Op::Fallback(exp, true) if frame.site.is_some() => {
+                let res = eval_in_tail(exp, frames)?;
+                stack.push(res);
+            }
+
+            Op::Fallback(exp, _) => {
+                let res = eval::eval(exp, &frame.env)?;
+                stack.push(res);
+            }
+
+            Op::Return => {
+                frames.pop();
+
+                if frames.is_empty() {
+                    return Ok(pop(&mut stack));
+                }
+
+                limits::ascend();
+            }
+        }
+    }
+}
+
+/// evaluates `exp`, reached in the tail of the lambda call on top of `frames`, with the tree-walker,
+/// which takes the call over: a call from the tail of `exp` replaces it, in backtraces and in the
+/// depth, as it would on the tree-walker. the value is the call's, the frame is gone
+fn eval_in_tail(exp: &LangExp, frames: &mut Vec<Frame>) -> Result<LangExp, LangErr> {
+    let frame = frames.pop().expect("the frame whose tail is reached");
+    let site = frame.site.expect("only lambda calls are taken over");
+    let call = RunningCall { site, timer: frame._timer, level: Level::taken_over() };
+
+    eval::eval_in_tail_of(exp, &frame.env, call)
+}
+
+fn pop(stack: &mut Vec<LangExp>) -> LangExp {
+    stack.pop().expect("the compiler keeps the stack balanced")
+}
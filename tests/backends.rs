@@ -0,0 +1,167 @@
+use lisp::{Backend, Interpreter};
+
+mod common;
+
+/// what `source` evaluates to on `backend`, followed by what it printed
+fn run(backend: Backend, optimizing: bool, source: &str) -> String {
+    let mut interpreter = Interpreter::with_backend(backend);
+    interpreter.set_optimizing(optimizing);
+
+    let (res, printed) = common::run(&mut interpreter, source);
+    format!("{}\n--- printed:\n{}", res, printed)
+}
+
+/// checks that every program gives the same value, or fails with the same error, and prints the
+/// same on both backends, with and without the optimizer
+fn agree(programs: &[&str]) {
+    for source in programs {
+        for optimizing in [false, true] {
+            let tree_walker = run(Backend::TreeWalker, optimizing, source);
+            let bytecode = run(Backend::Bytecode, optimizing, source);
+
+            assert_eq!(tree_walker, bytecode, "the backends disagree on {} (optimizing: {})", source, optimizing);
+        }
+    }
+}
+
+#[test]
+fn special_forms() {
+    agree(&[
+        "(if #t 1 2)",
+        "(if #f 1)",
+        "(cond ((= 1 2) 'a) ((= 1 1) 'b) (else 'c))",
+        "(cond ((= 1 2) 'a))",
+        "(and 1 2 #f 3)",
+        "(or #f nil 3)",
+        "(begin 1 2 3)",
+        "(let ((x 1) (y 2)) (+ x y))",
+        "(let* ((x 1) (y (+ x 1))) (* x y))",
+        "(letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1))))) (odd? (lambda (n) (if (= n 0) #f (even? (- n 1)))))) (even? 100))",
+        "(define x 5) (set! x (+ x 1)) x",
+        "(set! undefined-thing 1)",
+        "(quote (1 2 3))",
+        "(let ((x 2)) `(1 ,x ,@(list 3 4)))",
+        "(match '(1 2) ((list a b) (+ a b)) (_ 0))",
+        "(match (list 1 [2 3]) ((cons 0 _) 'zero) ((list (? integer? n) [x y]) (+ n x y)))",
+        "(match 5 (\"five\" 1))",
+        "(defstruct point x y) (let ((p (make-point 1 2))) (list (point-x p) (point? p) p))",
+        "(let ((p (delay (+ 1 2)))) (list (force p) (force p)))",
+        "(define n 0) (while (< n 5) (set! n (+ n 1))) n",
+        "(define total 0) (dotimes (i 10) (set! total (+ total i))) total",
+        "(loop ((i 0) (acc nil)) (if (= i 5) acc (recur (+ i 1) (cons i acc))))",
+        "(if 1 2 3 4)",
+        "(let ((x)) x)",
+        "(loop ((i 0)) (if (< i 3) (+ 1 (recur (+ i 1))) i))",
+    ]);
+}
+
+#[test]
+fn functions_and_tail_calls() {
+    agree(&[
+        "(define (f a &optional (b 10) &rest more) (list a b more)) (list (f 1) (f 1 2) (f 1 2 3 4))",
+        "(define (count-down n) (if (= n 0) 'done (count-down (- n 1)))) (count-down 100000)",
+        "(define (make-counter) (let ((n 0)) (lambda () (set! n (+ n 1)) n))) (let ((c (make-counter))) (c) (c) (c))",
+        "(map (lambda (x) (* x x)) '(1 2 3))",
+        "(apply + 1 2 '(3 4))",
+        "(filter (lambda (n) (= (mod n 2) 1)) (range 0 10))",
+        "(fold (lambda (acc x) (cons x acc)) nil '(1 2 3))",
+        "(reduce + 0 (range 0 100))",
+        "((compose car cdr) '(1 2 3))",
+        "(define (f) \"documented\" 1) (f)",
+        "(define f (lambda (x) x)) (f 1 2)",
+        "((lambda (x) x))",
+        "(1 2 3)",
+        "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1))))) (fact 20)",
+        "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1))))) (fact 30)",
+    ]);
+}
+
+#[test]
+fn macros() {
+    agree(&[
+        "(defmacro my-unless (test &rest body) `(if ,test nil (begin ,@body))) (my-unless #f 1 2)",
+        "(defmacro swap! (a b) (let ((tmp (gensym))) `(let ((,tmp ,a)) (set! ,a ,b) (set! ,b ,tmp)))) (define x 1) (define y 2) (swap! x y) (list x y)",
+        "(defmacro twice (e) `(begin ,e ,e)) (define n 0) (twice (set! n (+ n 1))) n",
+        "(macroexpand-1 '(when #t 1))",
+        "(defmacro bad () (car 1)) (bad)",
+    ]);
+}
+
+#[test]
+fn macros_defined_after_the_calls_were_compiled() {
+    agree(&[
+        "(begin (defmacro m (x) `(+ ,x 1)) (m 1))",
+        "(define (g) (h 1)) (define (h x) x) (g) (defmacro h (x) `(list ,x)) (g)",
+        "(define (g n) (if (= n 0) 'done (k (- n 1)))) (define (k n) (g n)) (g 3) (defmacro k (n) `(list ,n)) (g 3)",
+        "(defmacro h (x) `(list ,x)) (define (g) (h 1)) (g) (define h (lambda (x) (* x 10))) (g)",
+    ]);
+}
+
+#[test]
+fn errors_and_backtraces() {
+    agree(&[
+        "(+ 1 (car nil))",
+        "(car 1)",
+        "unbound-symbol",
+        "(let ((x 5)) (+ x zz))",
+        "(define (f x)\n  (let ((y 1))\n    (+ x (car y))))\n(f 1)",
+        "(define (g n) (if (= n 0) (car 1) (+ 1 (g (- n 1)))))\n(g 50)",
+        "(define (h n) (if (= n 0) (car 1) (h (- n 1))))\n(h 10)",
+        "(/ 1 0)",
+        "(error \"custom\" 1 2)",
+        "(assert (= 1 2))",
+        "(vector-ref (vector 1 2) 5)",
+        "(string-length 5)",
+        "(exit \"not a number\")",
+        "(recur 1)",
+        "(define loop 1)",
+        // calls from the tail of forms the compiler leaves to the tree-walker replace the caller
+        "(define (f n) (letrec ((g (lambda (i) (car i)))) (g n))) (+ 1 (f 1))",
+        "(define (f n) (letrec ((g (car n))) (g n))) (+ 1 (f 1))",
+        "(defmacro m (x) x) (define (g) (car 1)) (define (f) (m (g))) (+ 1 (f))",
+        "(define (g) (car 1)) (define (f) (m (g))) (defmacro m (x) x) (+ 1 (f))",
+        // or out of a call into the prelude, which isn't located in the source given
+        "(apply vector (cons 1 (take 2 5)))",
+    ]);
+}
+
+#[test]
+fn errors_are_caught_the_same() {
+    agree(&[
+        "(try (car 1) (catch e e))",
+        "(try (error \"boom\" 42) (catch e e))",
+        "(try (raise '(a b)) (catch e e))",
+        "(try (+ 1 2) (catch e 'never))",
+        "(define (risky n) (if (< n 0) (raise 'negative) n)) (list (try (risky 1) (catch e e)) (try (risky -1) (catch e e)))",
+        "(try (try (car 1) (catch e (raise 'again))) (catch e e))",
+        "(define (inner) (car 1)) (define (outer) (+ 1 (inner))) (try (outer) (catch e (list 'caught e)))",
+    ]);
+}
+
+#[test]
+fn values_and_printing() {
+    agree(&[
+        "(print \"hi\" 1) (println 'x) (pprint '(a b)) 5",
+        "(list 1.5 -0.0 1e20 (/ 1 3) (/ 6 3) #\\a \"s\\t\" 'sym nil #t)",
+        "{:a 1 :b (list 1 2)}",
+        "(assoc (hash-map \"a\" 1) \"b\" 2)",
+        "(let ((v (vector 1 2 3))) (vector-set! v 0 'x) v)",
+        "(let ((v (vector 1))) (vector-set! v 0 v) v)",
+        "(list (mod -7 2) (mod 7 -2) (floor -3.5) (pow 2 10) (sqrt 16))",
+        "(str \"a\" 'b (number->string 12) (string-upcase \"c\"))",
+        "(string-split \"a,b,c\" \",\")",
+        "(list (take 3 (integers-from 5)) (stream-car (stream-cdr (integers-from 0))))",
+        "(list (* 9223372036854775807 2) (/ -9223372036854775808 -1) (mod -9223372036854775808 -1))",
+        "(eval '(+ 1 2))",
+        "(optimize '(lambda (x) (+ 1 2 x)))",
+        "(read-line)",
+        "(equal? (list 1 (vector 2)) (list 1 (vector 2)))",
+        "(eq? '() nil)",
+        "(let ((x (gensym))) (symbol? x))",
+        "(json-encode {:a [1 2]})",
+        "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1))))) (trace fact) (fact 3) (untrace fact) (fact 4)",
+        "(printf \"~a and ~s~%\" \"x\" \"y\") (format \"~a\" 1)",
+        "(doc car)",
+        "(deftest adds (assert-eq (+ 1 1) 2)) (run-tests)",
+    ]);
+}
@@ -0,0 +1,83 @@
+use std::fs;
+use std::process::Command;
+
+/// the exit code, stdout and stderr of running the `lisp` binary with `args`
+fn lisp(args: &[&str]) -> (i32, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_lisp")).args(args).output().unwrap();
+
+    (out.status.code().unwrap(), String::from_utf8(out.stdout).unwrap(), String::from_utf8(out.stderr).unwrap())
+}
+
+/// a script holding `source` in the temporary directory, removed again when dropped
+struct Script(String);
+
+impl Script {
+    fn new(name: &str, source: &str) -> Script {
+        let path = std::env::temp_dir().join(format!("lisp-cli-{}-{}.lisp", name, std::process::id()));
+        fs::write(&path, source).unwrap();
+        Script(path.to_str().unwrap().to_string())
+    }
+}
+
+impl Drop for Script {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn scripts_run_with_their_arguments() {
+    let script = Script::new("args", "(println (cdr *command-line-args*)) (+ 1 2)");
+
+    // the value of the last form isn't printed, only what the script prints itself
+    assert_eq!(lisp(&[&script.0, "a", "b"]), (0, "(\"a\" \"b\")\n".to_string(), String::new()));
+}
+
+#[test]
+fn a_failing_script_names_the_file_and_exits_with_1() {
+    let script = Script::new("fails", "(println \"before\")\n(car 5)\n(println \"after\")");
+    let (code, out, err) = lisp(&[&script.0]);
+
+    assert_eq!((code, out.as_str()), (1, "before\n"));
+    assert!(err.starts_with(&format!("{}:2:1: type error: expected a list, got integer", script.0)), "{}", err);
+}
+
+#[test]
+fn exit_sets_the_exit_code() {
+    let script = Script::new("exit", "(exit 3) (println \"not reached\")");
+    assert_eq!(lisp(&[&script.0]), (3, String::new(), String::new()));
+    assert_eq!(lisp(&["-e", "(exit 4)"]).0, 4);
+}
+
+#[test]
+fn expressions_given_with_e_print_their_value() {
+    assert_eq!(lisp(&["-e", "(list 1 (+ 2 3))"]), (0, "(1 5)\n".to_string(), String::new()));
+
+    let (code, _, err) = lisp(&["-e", "(car 1)"]);
+    assert_eq!(code, 1);
+    assert!(err.starts_with("1:1: type error: expected a list, got integer"), "{}", err);
+}
+
+#[test]
+fn bad_arguments_print_the_usage() {
+    let (code, _, err) = lisp(&["-x"]);
+    assert_eq!(code, 2);
+    assert!(err.starts_with("usage: lisp"), "{}", err);
+
+    assert_eq!(lisp(&["--help"]).1, lisp(&["-h"]).1);
+
+    let (code, _, err) = lisp(&["/no/such/script.lisp"]);
+    assert_eq!(code, 1);
+    assert!(err.starts_with("could not read /no/such/script.lisp"), "{}", err);
+}
+
+#[test]
+fn a_script_whose_tests_fail_exits_with_1() {
+    let failing = Script::new("tests-fail", "(deftest t (assert false))\n(run-tests)\n");
+    let (code, out, _) = lisp(&[&failing.0]);
+    assert_eq!(code, 1);
+    assert!(out.ends_with("1 test, 0 passed, 1 failed\n"), "{}", out);
+
+    let passing = Script::new("tests-pass", "(deftest t (assert true))\n(run-tests)\n");
+    assert_eq!(lisp(&[&passing.0]).0, 0);
+}
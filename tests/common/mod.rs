@@ -0,0 +1,56 @@
+//! helpers shared by the integration tests. each test crate uses a different subset of them
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use lisp::{Backend, Interpreter, Limits};
+
+pub const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Bytecode];
+
+/// sends what `interpreter` prints into the returned buffer instead of stdout
+pub fn capture_output(interpreter: &mut Interpreter) -> Arc<Mutex<String>> {
+    let printed = Arc::new(Mutex::new(String::new()));
+    let sink = printed.clone();
+
+    interpreter.set_output(move |text| {
+        sink.lock().unwrap().push_str(text);
+        Ok(())
+    });
+
+    printed
+}
+
+/// what `source` evaluates to and what it printed on the way. it reads from an empty input, not
+/// from the test runner's stdin, which may never end
+pub fn run(interpreter: &mut Interpreter, source: &str) -> (String, String) {
+    interpreter.set_input(|| Ok(None));
+    let printed = capture_output(interpreter);
+    let res = interpreter.eval_to_string(source);
+    let printed = printed.lock().unwrap().clone();
+
+    (res, printed)
+}
+
+/// what `source` evaluates to under `limits` on each backend, which should agree
+pub fn eval_with(limits: Limits, source: &str) -> String {
+    let results: Vec<String> = BACKENDS
+        .iter()
+        .map(|backend| {
+            let mut interpreter = Interpreter::with_backend(*backend);
+            interpreter.set_limits(limits);
+            interpreter.eval_to_string(source)
+        })
+        .collect();
+
+    assert_eq!(results[0], results[1], "the backends disagree on {}", source);
+    results[0].clone()
+}
+
+pub fn eval(source: &str) -> String {
+    eval_with(Limits::default(), source)
+}
+
+/// what evaluating `source` printed
+pub fn output(source: &str) -> String {
+    run(&mut Interpreter::new(), source).1
+}
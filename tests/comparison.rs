@@ -0,0 +1,49 @@
+use lisp::LangExp;
+
+mod common;
+
+use common::eval;
+
+#[test]
+fn comparisons_hold_across_every_argument() {
+    assert_eq!(eval("(list (< 1 2) (< 2 1) (< 1 2 3) (< 1 3 2) (<= 1 1 2) (> 3 2 1) (>= 3 3 1))"), "(true false true false true true true)");
+    assert_eq!(eval("(list (= 1 1 1) (= 1 1 2) (= 1) (< 1))"), "(true false true true)");
+}
+
+#[test]
+fn integers_and_floats_compare_by_value() {
+    assert_eq!(eval("(list (= 1 1.0) (< 1 1.5) (> 2.5 2) (= 0.1 0.1) (<= -0.0 0))"), "(true true true true true)");
+}
+
+#[test]
+fn comparisons_take_numbers_only() {
+    assert!(eval("(<)").contains("`<` expects at least 1 argument, got 0"));
+    assert!(eval("(< 1 'a)").contains("type error: expected a number, got symbol"));
+    assert!(eval("(= \"a\" \"a\")").contains("type error: expected a number, got string"));
+}
+
+#[test]
+fn values_of_other_types_are_compared_with_equal() {
+    assert_eq!(eval("(list (equal? \"a\" \"a\") (equal? (list 1 2) (list 1 2)) (equal? 'a 'b))"), "(true true false)");
+}
+
+#[test]
+fn eq_compares_atoms_by_value_and_everything_else_by_identity() {
+    assert_eq!(eval("(list (eq? 1 1) (eq? 1 1.0) (eq? 'a 'a) (eq? '() '()) (eq? \"a\" \"a\") (eq? '(1) '(1)) (let ((l '(1))) (eq? l l)))"), "(true false true true false false true)");
+}
+
+#[test]
+fn equal_compares_structure() {
+    assert_eq!(eval("(list (equal? 1 1.0) (equal? '(1 (2 [3])) '(1 (2 [3]))) (equal? (hash-map 'a 1) (hash-map 'a 1)) (equal? [1 2] [1 2 3]))"), "(false true true false)");
+
+    // functions are only equal to themselves, NaN is equal to itself though `=` says it isn't
+    assert_eq!(eval("(list (equal? car car) (equal? car cdr) (let ((f (lambda (x) x))) (list (equal? f f) (equal? f (lambda (x) x)))))"), "(true false (true false))");
+    assert_eq!(eval("(define nan (- (* 1e308 10) (* 1e308 10))) (list (equal? nan nan) (eq? nan nan) (= nan nan))"), "(true true false)");
+}
+
+#[test]
+fn values_compare_from_rust_the_way_equal_does() {
+    assert_eq!(LangExp::list(vec![LangExp::Int(1), LangExp::Str("a".into())]), LangExp::list(vec![LangExp::Int(1), LangExp::Str("a".into())]));
+    assert_ne!(LangExp::Int(1), LangExp::Number(1.0));
+    assert_eq!(LangExp::Number(f64::NAN), LangExp::Number(f64::NAN));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn only_false_is_false() {
+    assert_eq!(eval("(list (if true 1 2) (if false 1 2) (if '() 1 2) (if 0 1 2) (if \"\" 1 2))"), "(1 2 1 1 1)");
+    assert_eq!(eval("(list true false #t #f)"), "(true false true false)");
+    assert_eq!(eval("(if false 1)"), "()");
+}
+
+#[test]
+fn if_evaluates_only_the_branch_taken() {
+    assert_eq!(eval("(if (< 1 2) (+ 1 1) (car 1))"), "2");
+    assert_eq!(eval("(if (> 1 2) (car 1) 'else)"), "else");
+}
+
+#[test]
+fn cond_takes_the_first_clause_whose_test_holds() {
+    assert_eq!(eval("(cond ((= 1 2) 'a) ((= 1 1) 'b) (else 'c))"), "b");
+    assert_eq!(eval("(cond (false 1) (else 2 3))"), "3");
+    assert_eq!(eval("(cond ((= 1 2) 'a))"), "()");
+
+    // a clause without a body gives the value of its test
+    assert_eq!(eval("(cond (false) (5))"), "5");
+}
+
+#[test]
+fn malformed_conditionals_are_errors() {
+    for source in ["(if)", "(if 1)", "(if 1 2 3 4)"] {
+        assert!(eval(source).contains("if expects a test, a then form and an optional else form"), "{}", source);
+    }
+
+    assert!(eval("(cond 1)").contains("expected cond clauses to be lists"));
+}
+
+#[test]
+fn and_and_or_give_the_value_that_decided_them() {
+    assert_eq!(eval("(list (and) (or) (and 1 2) (and 1 false 2) (and 1 '()) (or false 2) (or false false))"), "(true false 2 false () 2 false)");
+    assert_eq!(eval("(list (not false) (not 0) (not '()))"), "(true false false)");
+}
+
+#[test]
+fn and_and_or_stop_at_the_first_value_that_decides_them() {
+    assert_eq!(eval("(and false (car 5))"), "false");
+    assert_eq!(eval("(or 1 (car 5))"), "1");
+    assert_eq!(eval("(define n 0) (and (set! n 1) false (set! n 2)) n"), "1");
+}
+
+#[test]
+fn not_takes_one_argument() {
+    assert!(eval("(not)").contains("`not` expects 1 argument, got 0"));
+    assert!(eval("(not 1 2)").contains("`not` expects 1 argument, got 2"));
+}
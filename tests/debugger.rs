@@ -0,0 +1,63 @@
+use lisp::{Backend, Interpreter};
+
+mod common;
+
+use common::{capture_output, BACKENDS};
+
+const SOURCE: &str = "(define (f x) (let ((y (* x 2))) (break) (+ x (* y 2)) (- x 1)))\n(f 5)";
+
+/// what evaluating `source` on `backend` gives with `commands` typed at the debugger prompt, and
+/// what it printed
+fn debug(backend: Backend, source: &str, commands: &[&str]) -> (String, String) {
+    let mut interpreter = Interpreter::with_backend(backend);
+    let mut commands: Vec<String> = commands.iter().rev().map(|c| c.to_string()).collect();
+    interpreter.set_input(move || Ok(commands.pop()));
+
+    let printed = capture_output(&mut interpreter);
+    let res = interpreter.eval_to_string(source);
+    let printed = printed.lock().unwrap().clone();
+
+    (res, printed)
+}
+
+#[test]
+fn break_pauses_to_inspect_the_scope_it_is_in() {
+    for backend in BACKENDS.iter() {
+        let (res, printed) = debug(*backend, SOURCE, &["locals", "(* y 10)", "undefined", "where", "c"]);
+
+        assert_eq!(res, "4");
+        assert_eq!(
+            printed,
+            "paused: (break)\ndebug >   y = 10\n  x = 5\ndebug > // => 100\ndebug > // => unbound symbol `undefined`\ndebug > paused: (break)\ndebug > "
+        );
+    }
+
+    let (res, printed) = debug(Backend::TreeWalker, "(break) 1", &["l"]);
+    assert_eq!(res, "1");
+    assert!(printed.contains("  no local bindings, this is the top level\n"), "{}", printed);
+}
+
+#[test]
+fn step_pauses_at_every_form_and_next_skips_the_ones_inside() {
+    let (_, printed) = debug(Backend::TreeWalker, SOURCE, &["s", "s", "s", "c"]);
+    assert_eq!(printed, "paused: (break)\ndebug > paused at 1:42: (+ x (* y 2))\ndebug > paused at 1:47: (* y 2)\ndebug > paused at 1:56: (- x 1)\ndebug > ");
+
+    let (_, printed) = debug(Backend::TreeWalker, SOURCE, &["n", "n", "c"]);
+    assert_eq!(printed, "paused: (break)\ndebug > paused at 1:42: (+ x (* y 2))\ndebug > paused at 1:56: (- x 1)\ndebug > ");
+}
+
+#[test]
+fn stepping_ends_with_the_evaluation() {
+    // still stepping when the input ran out, which the next interpreter's prelude mustn't see
+    let (res, _) = debug(Backend::TreeWalker, SOURCE, &["s"]);
+    assert_eq!(res, "4");
+
+    let (res, printed) = debug(Backend::TreeWalker, "(+ 1 2)", &[]);
+    assert_eq!((res.as_str(), printed.as_str()), ("3", ""));
+}
+
+#[test]
+fn help_lists_the_commands() {
+    let (_, printed) = debug(Backend::TreeWalker, "(break)", &["help"]);
+    assert!(printed.contains("  continue (c)   run on until the next (break)\n"), "{}", printed);
+}
@@ -0,0 +1,110 @@
+use std::thread;
+
+use lisp::{Interpreter, Limits};
+
+mod common;
+
+use common::{eval, eval_with};
+
+const COUNT_DOWN: &str = "(define (f n) (if (= n 0) 0 (+ 1 (f (- n 1)))))";
+
+#[test]
+fn recursion_fits_the_stack_of_a_test_thread() {
+    assert_eq!(eval(&format!("{} (f 100)", COUNT_DOWN)), "100");
+    assert_eq!(eval("(length (take 100 (integers-from 0)))"), "100");
+}
+
+#[test]
+fn recursion_goes_as_deep_as_the_thread_stack_allows() {
+    let deep = thread::Builder::new().stack_size(64 * 1024 * 1024).spawn(|| {
+        for n in [500, 2000] {
+            assert_eq!(eval(&format!("{} (f {})", COUNT_DOWN, n)), n.to_string());
+        }
+
+        assert_eq!(eval("(length (take 1000 (integers-from 0)))"), "1000");
+    });
+
+    deep.unwrap().join().unwrap();
+}
+
+#[test]
+fn both_backends_count_calls_against_the_depth_limit() {
+    let limits = Limits { depth: Some(50), ..Limits::default() };
+
+    assert_eq!(eval_with(limits, &format!("{} (f 49)", COUNT_DOWN)), "49");
+
+    let res = eval_with(limits, &format!("{} (f 50)", COUNT_DOWN));
+    assert!(res.contains("limit exceeded: calls nested more than 50 deep"), "{}", res);
+
+    // tail calls take the place of the call they are made from
+    let res = eval_with(limits, "(define (g n) (if (= n 0) 'done (g (- n 1)))) (g 1000)");
+    assert_eq!(res, "done");
+
+    // also from the tail of forms the bytecode backend leaves to the tree-walker
+    let limits = Limits { depth: Some(1), ..Limits::default() };
+    let res = eval_with(limits, "(define (g n) (letrec ((m (- n 1))) (if (< m 0) 'done (g m)))) (g 1000)");
+    assert_eq!(res, "done");
+}
+
+#[test]
+fn running_out_of_depth_or_stack_cant_be_caught() {
+    let limits = Limits { depth: Some(50), ..Limits::default() };
+    let res = eval_with(limits, &format!("{} (try (f 100) (catch e 'caught))", COUNT_DOWN));
+    assert!(res.contains("limit exceeded: calls nested more than 50 deep"), "{}", res);
+
+    let mut interpreter = Interpreter::new();
+    let res = interpreter.eval_to_string(&format!("{} (try (f 100000) (catch e 'caught))", COUNT_DOWN));
+    assert!(res.contains("limit exceeded: recursion or nesting is too deep for the stack"), "{}", res);
+}
+
+#[test]
+fn values_nested_deeper_than_the_stack_are_freed_and_printed() {
+    let nested = "(loop ((i 0) (acc nil)) (if (= i 20000) acc (recur (+ i 1) (list acc))))";
+
+    assert_eq!(eval(&format!("(string-length (str {}))", nested)), "40002");
+    assert_eq!(eval(&format!("(begin {} 'freed)", nested)), "freed");
+
+    let promises = "(loop ((i 0) (p (delay 0))) (if (= i 20000) 'freed (recur (+ i 1) (let ((q p)) (force (delay q))))))";
+    assert_eq!(eval(promises), "freed");
+
+    let closures = "(loop ((i 0) (f (lambda () 0))) (if (= i 20000) (f) (recur (+ i 1) (let ((g f)) (lambda () g)))))";
+    assert!(eval(closures).starts_with("Lambda"));
+
+    let vectors = "(loop ((i 0) (acc [])) (if (= i 20000) (vector-length acc) (recur (+ i 1) (vector acc))))";
+    assert_eq!(eval(vectors), "1");
+}
+
+#[test]
+fn values_inside_themselves_print_as_a_marker() {
+    assert_eq!(eval("(let ((v (vector 1 2))) (vector-set! v 0 v) v)"), "[... 2]");
+    assert_eq!(eval("(let ((v (vector 1)) (w (vector 2))) (vector-set! v 0 w) (vector-set! w 0 v) (list v w))"), "([[...]] [[...]])");
+
+    // a value in two places isn't inside itself
+    assert_eq!(eval("(let ((v (vector 1))) (vector v v))"), "[[1] [1]]");
+
+    let v = Interpreter::new().eval_str("(let ((v (vector 1 1000 2000))) (vector-set! v 0 v) v)").unwrap();
+    assert_eq!(v.to_pretty_string(8), "[...\n 1000\n 2000]");
+}
+
+#[test]
+fn values_nested_deeper_than_the_stack_are_compared_and_hashed() {
+    let nest = "(define (nest leaf) (loop ((i 0) (acc leaf)) (if (= i 20000) acc (recur (+ i 1) (list acc)))))";
+
+    assert_eq!(eval(&format!("{} (list (equal? (nest 1) (nest 1)) (equal? (nest 1) (nest 2)))", nest)), "(true false)");
+    assert_eq!(eval(&format!("{} (get (hash-map (nest 1) 'found) (nest 1))", nest)), "found");
+
+    let maps = "(loop ((i 0) (acc 0)) (if (= i 20000) (get (hash-map acc 'found) acc) (recur (+ i 1) (hash-map acc acc))))";
+    assert_eq!(eval(maps), "found");
+}
+
+#[test]
+fn values_inside_themselves_are_compared_and_hashed() {
+    let cycles = "(define v (vector 1 2)) (vector-set! v 0 v) (define w (vector 1 2)) (vector-set! w 0 w)";
+
+    assert_eq!(eval(&format!("{} (list (equal? v v) (equal? v w))", cycles)), "(true true)");
+    assert_eq!(eval(&format!("{} (vector-set! w 1 3) (equal? v w)", cycles)), "false");
+    assert_eq!(eval(&format!("{} (get (hash-map v 'found) w)", cycles)), "found");
+
+    // the same vector inside itself, unrolled once more
+    assert_eq!(eval("(define v (vector 0)) (vector-set! v 0 v) (define u (vector (vector 0))) (vector-set! (vector-ref u 0) 0 u) (list (equal? v u) (get (hash-map v 'found) u))"), "(true found)");
+}
@@ -0,0 +1,21 @@
+use lisp::Interpreter;
+
+mod common;
+
+use common::output;
+
+#[test]
+fn doc_prints_to_the_output_sink() {
+    assert_eq!(output("(define (f a) \"adds\" a) (doc f)"), "(f a)\n  adds\n");
+}
+
+#[test]
+fn define_documents_the_lambda_it_binds() {
+    assert_eq!(output("(define g \"g doc\" (lambda (y) y)) (doc g)"), "(g y)\n  g doc\n");
+}
+
+#[test]
+fn define_only_documents_functions_and_macros() {
+    let res = Interpreter::new().eval_to_string("(define x \"the answer\" 42)");
+    assert!(res.contains("expected a function or a macro to document, got integer"), "{}", res);
+}
@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+
+use lisp::{Arity, Interpreter, LangEnv, LangErr, LangExp, SymbolId};
+
+fn sum_squares(args: &[LangExp], _env: &LangEnv) -> Result<LangExp, LangErr> {
+    let mut sum = 0;
+
+    for arg in args {
+        match arg {
+            LangExp::Int(i) => sum += i * i,
+            other => return Err(LangErr::Type { expected: "an integer".to_string(), got: other.type_name().to_string() })
+        }
+    }
+
+    Ok(LangExp::Int(sum))
+}
+
+#[test]
+fn eval_str_returns_the_value_of_the_last_form() {
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(interpreter.eval_str("(define x 20) (+ x 1)").unwrap(), LangExp::Int(21));
+    assert_eq!(interpreter.eval_str("(list x \"s\")").unwrap().to_string(), "(20 \"s\")");
+
+    // definitions last for the session
+    assert_eq!(interpreter.eval_str("x").unwrap(), LangExp::Int(20));
+}
+
+#[test]
+fn errors_come_back_as_values_that_render_against_the_source() {
+    let mut interpreter = Interpreter::new();
+    let source = "(+ 1 (car 5))";
+    let err = interpreter.eval_str(source).unwrap_err();
+
+    assert!(matches!(err.inner(), LangErr::Type { .. }), "{:?}", err);
+    assert_eq!(err.render(source), "1:6: type error: expected a list, got integer\n    (+ 1 (car 5))\n         ^~~~~~~");
+    assert_eq!(err.exit_code(), None);
+
+    assert_eq!(interpreter.eval_str("(exit 3)").unwrap_err().exit_code(), Some(3));
+}
+
+#[test]
+fn natives_registered_from_rust_are_called_from_lisp() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register_native("sum-squares", Arity::AtLeast(1), sum_squares);
+
+    assert_eq!(interpreter.eval_to_string("(sum-squares 1 2 3)"), "14");
+    assert_eq!(interpreter.eval_to_string("(map sum-squares (list 2 3))"), "(4 9)");
+    assert!(interpreter.eval_to_string("(sum-squares)").contains("`sum-squares` expects at least 1 argument, got 0"));
+    assert!(interpreter.eval_to_string("(sum-squares 'a)").contains("type error: expected an integer, got symbol"));
+}
+
+#[test]
+fn values_are_bound_and_read_through_the_environment() {
+    let mut interpreter = Interpreter::new();
+    interpreter.env().insert(SymbolId::intern("from-rust"), LangExp::list(vec![LangExp::Int(1), LangExp::Bool(true)]));
+
+    assert_eq!(interpreter.eval_to_string("(car from-rust)"), "1");
+
+    interpreter.eval_str("(define from-lisp (* 6 7))").unwrap();
+    assert_eq!(interpreter.env().get(SymbolId::intern("from-lisp")), Some(LangExp::Int(42)));
+    assert_eq!(interpreter.env().get(SymbolId::intern("never-defined")), None);
+}
+
+#[test]
+fn reset_forgets_every_definition() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register_native("sum-squares", Arity::AtLeast(1), sum_squares);
+    interpreter.eval_str("(define x 1)").unwrap();
+
+    interpreter.reset();
+    assert!(interpreter.eval_to_string("x").contains("unbound symbol `x`"));
+    assert!(interpreter.eval_to_string("(sum-squares 1)").contains("unbound symbol `sum-squares`"));
+    assert_eq!(interpreter.eval_to_string("(+ 1 2)"), "3");
+}
+
+#[test]
+fn symbol_names_are_what_the_repl_completes() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define completion-target 1)").unwrap();
+
+    let names = interpreter.symbol_names();
+    for name in ["completion-target", "car", "lambda", "define"] {
+        assert!(names.iter().any(|n| n == name), "{}", name);
+    }
+
+    let mut sorted = names.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(names, sorted);
+}
+
+#[test]
+fn a_text_only_front_end_gets_values_errors_and_printing_as_strings() {
+    // the way the wasm wrapper in the crate docs drives the interpreter
+    let printed = Arc::new(Mutex::new(vec![]));
+    let sink = printed.clone();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(move |text| {
+        sink.lock().unwrap().push(text.to_string());
+        Ok(())
+    });
+
+    assert_eq!(interpreter.eval_to_string("(println \"hi\" 1) (+ 2 3)"), "5");
+    assert_eq!(interpreter.eval_to_string("(car 1)"), "1:1: type error: expected a list, got integer\n    (car 1)\n    ^~~~~~~");
+    assert_eq!(printed.lock().unwrap().concat(), "hi 1\n");
+}
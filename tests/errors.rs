@@ -0,0 +1,81 @@
+use lisp::Interpreter;
+
+mod common;
+
+use common::eval;
+
+#[test]
+fn errors_point_at_the_failing_subform() {
+    assert_eq!(eval("(+ 1 (car nil))"), "1:6: car of an empty list\n    (+ 1 (car nil))\n         ^~~~~~~~~");
+}
+
+#[test]
+fn errors_in_a_function_point_into_its_body() {
+    let res = eval("(define (f x)\n  (let ((y 1))\n    (+ x (car y))))\n(f 1)");
+    assert!(res.starts_with("3:10: type error: expected a list, got integer\n        (+ x (car y))))\n             ^~~~~~~"), "{}", res);
+    assert!(res.ends_with("\n  in `f` called at 4:1"), "{}", res);
+}
+
+#[test]
+fn errors_in_code_read_from_other_source_point_at_the_call() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(define (f) (car 1))").unwrap();
+
+    let res = interpreter.eval_to_string("(list 1\n  (f))");
+    assert!(res.starts_with("2:3: type error: expected a list, got integer\n      (f))\n      ^~~"), "{}", res);
+}
+
+#[test]
+fn unbound_symbols_are_found_in_the_form_that_failed() {
+    assert_eq!(eval("(let ((x 5)) (+ x zz))"), "1:19: unbound symbol `zz`\n    (let ((x 5)) (+ x zz))\n                      ^~");
+
+    let res = eval("(let ((x 5)) (eval (quote x)))");
+    assert!(res.starts_with("1:27: unbound symbol `x`"), "{}", res);
+}
+
+#[test]
+fn long_backtraces_say_how_many_calls_they_leave_out() {
+    let res = eval("(define (f n) (if (= n 0) (car 1) (+ 1 (f (- n 1)))))\n(f 50)");
+    assert!(res.ends_with("\n  ... and 19 more calls"), "{}", res);
+}
+
+#[test]
+fn type_errors_name_what_was_expected_with_an_article() {
+    assert!(eval("(memoize 1)").contains("expected a function, got integer"));
+    assert!(eval("(spawn 1)").contains("expected a function, got integer"));
+    assert!(eval("(set-macro-character \"~\" 1)").contains("expected a function, got integer"));
+    assert!(eval("(join 1)").contains("expected a thread, got integer"));
+    assert!(eval("(recv! 1)").contains("expected a channel, got integer"));
+}
+
+#[test]
+fn try_catches_what_error_and_raise_signal() {
+    assert_eq!(eval("(list (try (error \"boom\") (catch e e)) (try (error \"a\" 1 'b) (catch e e)))"), "(\"boom\" \"a 1 b\")");
+    assert_eq!(eval("(list (try (raise 'oops) (catch e e)) (try (raise (list 1 2)) (catch e (car e))))"), "(oops 1)");
+    assert_eq!(eval("(try (raise 1) (catch e (raise (+ e 1))))").lines().next(), Some("1:25: error: 2"));
+    assert_eq!(eval("(try (try (raise 1) (catch e (raise (+ e 1)))) (catch e e))"), "2");
+}
+
+#[test]
+fn errors_of_the_interpreter_are_caught_as_their_message() {
+    assert_eq!(eval("(try (car 5) (catch e e))"), "\"type error: expected a list, got integer\"");
+    assert_eq!(eval("(try (undefined-fn) (catch e e))"), "\"unbound symbol `undefined-fn`\"");
+}
+
+#[test]
+fn try_gives_the_value_of_its_body_when_nothing_is_raised() {
+    assert_eq!(eval("(try (+ 1 2) (catch e 'caught))"), "3");
+    assert_eq!(eval("(define n 0) (try (begin (set! n 1) (raise 'x) (set! n 2)) (catch e n))"), "1");
+}
+
+#[test]
+fn uncaught_errors_say_what_was_raised() {
+    assert!(eval("(error \"boom\")").starts_with("1:1: error: boom"));
+    assert!(eval("(raise 'oops)").starts_with("1:1: error: oops"));
+}
+
+#[test]
+fn malformed_trys_are_errors() {
+    assert!(eval("(try 1)").contains("try expects a body followed by a (catch name handler...) clause"));
+    assert!(eval("(try 1 (catch))").contains("catch expects a name for the error and a handler"));
+}
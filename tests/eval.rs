@@ -0,0 +1,23 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn eval_evaluates_a_form_built_at_runtime() {
+    assert_eq!(eval("(eval (list '+ 1 2))"), "3");
+    assert_eq!(eval("(define x 5) (eval 'x)"), "5");
+}
+
+#[test]
+fn eval_runs_at_the_top_level() {
+    assert!(eval("(let ((y 5)) (eval 'y))").contains("unbound symbol `y`"));
+    assert_eq!(eval("(let ((y 5)) (eval '(define z 1))) z"), "1");
+}
+
+#[test]
+fn function_values_evaluate_to_themselves() {
+    assert_eq!(eval("(eval (list + 1 2))"), "3");
+    assert_eq!(eval("(eval (list (lambda (x) (* x 2)) 4))"), "8");
+    assert_eq!(eval("(defmacro plus (a b) (list + a b)) (plus 1 2)"), "3");
+    assert_eq!(eval("(let ((f (lambda () 1))) (list (eq? (eval +) +) (eq? (eval f) f)))"), "(true true)");
+}
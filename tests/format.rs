@@ -0,0 +1,53 @@
+mod common;
+
+use common::{eval, output};
+
+#[test]
+fn directives() {
+    assert_eq!(eval(r#"(format "x=~a, y=~s~%" "hi" "hi")"#), r#""x=hi, y=\"hi\"\n""#);
+    assert_eq!(eval(r#"(format "~A ~S" #\c #\c)"#), r#""c #\\c""#);
+    assert_eq!(eval(r#"(format "~~ ~a ~~~%" 'sym)"#), r#""~ sym ~\n""#);
+    assert_eq!(eval(r#"(format "")"#), r#""""#);
+    assert_eq!(eval(r#"(format "é ~a" "ü")"#), r#""é ü""#);
+}
+
+#[test]
+fn a_shows_arguments_as_print_does_and_s_as_the_reader_writes_them() {
+    for arg in [r#""text""#, r#"#\c"#, "1.5", "'sym", r#"(list "a" #\b 1)"#, r#"(vector 1 "x")"#, "'()"] {
+        let printed = output(&format!("(print {})", arg));
+        assert_eq!(eval(&format!(r#"(format "~a" {})"#, arg)), format!("{:?}", printed), "{}", arg);
+
+        let written = eval(arg);
+        assert_eq!(eval(&format!(r#"(format "~s" {})"#, arg)), format!("{:?}", written), "{}", arg);
+    }
+}
+
+#[test]
+fn printf_writes_what_format_returns() {
+    assert_eq!(output(r#"(printf "~a+~a=~s~%" 1 2 3)"#), "1+2=3\n");
+    assert_eq!(output(r#"(printf "~s and ~a" "x" "x") (printf "!")"#), "\"x\" and x!");
+    assert_eq!(eval(r#"(printf "")"#), "()");
+}
+
+#[test]
+fn mismatched_templates_are_errors() {
+    for name in ["format", "printf"] {
+        let cases = [
+            (r#""~a""#, "format: no argument left for ~a"),
+            (r#""~a ~s" 1"#, "format: no argument left for ~s"),
+            (r#""~a" 1 2"#, "format: 1 argument more than the template has directives for"),
+            (r#""" 1 2"#, "format: 2 arguments more than the template has directives for"),
+            (r#""~q" 1"#, "format: unknown directive ~q"),
+            (r#""50~""#, "format: the template ends with a lone ~"),
+            ("1", "type error: expected a string, got integer"),
+        ];
+
+        for (args, msg) in cases.iter() {
+            let res = eval(&format!("({} {})", name, args));
+            assert!(res.contains(msg), "({} {}): {}", name, args, res);
+        }
+    }
+
+    // nothing is printed when the template doesn't match
+    assert_eq!(output(r#"(printf "~a ~a" 1)"#), "");
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn lambdas_are_called_with_their_arguments() {
+    assert_eq!(eval("((lambda (x y) (* x y)) 3 4)"), "12");
+    assert_eq!(eval("((lambda () 1 2 3))"), "3");
+    assert_eq!(eval("(map (lambda (x) (* x x)) (list 1 2 3))"), "(1 4 9)");
+}
+
+#[test]
+fn closures_keep_the_bindings_they_were_made_with() {
+    assert_eq!(eval("(define add (lambda (n) (lambda (x) (+ x n)))) ((add 2) 5)"), "7");
+    assert_eq!(eval("(define (twice f) (lambda (x) (f (f x)))) ((twice (lambda (x) (* x 3))) 2)"), "18");
+}
+
+#[test]
+fn calls_check_the_number_of_arguments() {
+    assert!(eval("((lambda (x) x))").contains("`lambda` expects 1 argument, got 0"));
+    assert!(eval("((lambda (x) x) 1 2)").contains("`lambda` expects 1 argument, got 2"));
+}
+
+#[test]
+fn malformed_lambdas_are_errors() {
+    assert!(eval("(lambda)").contains("expected a parameter list in lambda"));
+    assert!(eval("(lambda (1) 1)").contains("expected symbols in lambda parameter list"));
+    assert!(eval("(lambda (x))").contains("expected a body in lambda"));
+}
+
+#[test]
+fn tail_calls_run_in_constant_stack() {
+    // the tree-walker runs out of the stack of a test thread some 10000 calls deep
+    assert_eq!(eval("(define (count-down n) (if (= n 0) 'done (count-down (- n 1)))) (count-down 30000)"), "done");
+
+    let even = "(define (ev n) (if (= n 0) true (od (- n 1))))";
+    let odd = "(define (od n) (if (= n 0) false (ev (- n 1))))";
+    assert_eq!(eval(&format!("{} {} (ev 30001)", even, odd)), "false");
+}
+
+#[test]
+fn the_last_form_of_cond_let_and_begin_is_in_tail_position() {
+    let cases = [
+        ("(cond ((= n 0) 'cond) (else (f (- n 1))))", "cond"),
+        ("(let ((m (- n 1))) (if (< m 0) 'let (f m)))", "let"),
+        ("(begin 1 (if (= n 0) 'begin (f (- n 1))))", "begin"),
+        ("(and true (if (= n 0) 'and (f (- n 1))))", "and"),
+        ("(or false (if (= n 0) 'or (f (- n 1))))", "or"),
+    ];
+
+    for (body, value) in cases.iter() {
+        assert_eq!(eval(&format!("(define (f n) {}) (f 30000)", body)), *value, "{}", body);
+    }
+}
+
+
+#[test]
+fn higher_order_builtins() {
+    assert_eq!(eval("(list (map + '(1 2) '(10 20 30)) (map car '((1) (2))))"), "((11 22) (1 2))");
+    assert_eq!(eval("(list (filter (lambda (x) (= (mod x 2) 0)) '(1 2 3 4)) (filter (lambda (x) 0) '(1 2)))"), "((2 4) (1 2))");
+    assert_eq!(eval("(list (fold (lambda (acc x) (cons x acc)) '() '(1 2 3)) (fold - 10 '(1 2)))"), "((3 2 1) 7)");
+    assert_eq!(eval("(list (reduce + 0 '(1 2 3)) (reduce + 0 '()) (reduce + '(1 2 3)) (reduce - '(10 1 2)))"), "(6 0 6 7)");
+    assert_eq!(eval("(list (apply + 1 2 '(3 4)) (apply list '()) (apply (lambda (a b) (- a b)) '(5 3)))"), "(10 () 2)");
+}
+
+#[test]
+fn higher_order_builtins_check_their_arguments() {
+    assert!(eval("(map (lambda (x) x))").contains("`map` expects at least 2 arguments, got 1"));
+    assert!(eval("(map 1 '(1))").contains("type error: expected a function, got integer"));
+    assert!(eval("(filter (lambda (x) x) 5)").contains("type error: expected a list, got integer"));
+    assert!(eval("(apply + 1)").contains("type error: expected a list, got integer"));
+    assert!(eval("(reduce + '())").contains("reduce of an empty list needs an initial value"));
+}
+
+#[test]
+fn builtins_check_the_number_of_arguments_the_same_way() {
+    let cases = [
+        ("(car)", "`car` expects 1 argument, got 0"),
+        ("(cons 1)", "`cons` expects 2 arguments, got 1"),
+        ("(-)", "`-` expects at least 1 argument, got 0"),
+        ("(substring \"a\")", "`substring` expects 2 to 3 arguments, got 1"),
+        ("(substring \"abc\" 0 1 2)", "`substring` expects 2 to 3 arguments, got 4"),
+        // however they are called
+        ("(apply car (list 1 2))", "`car` expects 1 argument, got 2"),
+        ("(map cons (list 1))", "`cons` expects 2 arguments, got 1"),
+    ];
+
+    for (source, msg) in cases.iter() {
+        assert!(eval(source).contains(msg), "{}", source);
+    }
+
+    assert_eq!(eval("(+)"), "0");
+}
+
+#[test]
+fn optional_parameters_take_a_default_when_left_out() {
+    assert_eq!(eval("(define (f a &optional b) (list a b)) (list (f 1) (f 1 2))"), "((1 ()) (1 2))");
+    assert_eq!(eval("(define (f a &optional (b 10)) (list a b)) (list (f 1) (f 1 2))"), "((1 10) (1 2))");
+
+    // defaults can refer to the parameters before them
+    assert_eq!(eval("(define (f a &optional (b (* a 2)) &rest r) (list a b r)) (list (f 1) (f 1 5) (f 1 5 6 7))"), "((1 2 ()) (1 5 ()) (1 5 (6 7)))");
+}
+
+#[test]
+fn a_rest_parameter_takes_the_arguments_left_over() {
+    assert_eq!(eval("(define (f a &rest r) (list a r)) (list (f 1) (f 1 2 3))"), "((1 ()) (1 (2 3)))");
+    assert_eq!(eval("((lambda (&rest xs) xs))"), "()");
+}
+
+#[test]
+fn optional_and_rest_parameters_are_counted_in_arity_errors() {
+    assert!(eval("(define (f a &optional b) a) (f)").contains("`f` expects 1 to 2 arguments, got 0"));
+    assert!(eval("(define (f a &optional b) a) (f 1 2 3)").contains("`f` expects 1 to 2 arguments, got 3"));
+    assert!(eval("(define (f a &rest r) a) (f)").contains("`f` expects at least 1 argument, got 0"));
+}
+
+#[test]
+fn malformed_optional_and_rest_parameters_are_errors() {
+    for source in ["(lambda (&rest) 1)", "(lambda (&rest a b) 1)", "(lambda (&rest a &optional b) 1)"] {
+        assert!(eval(source).contains("expected a single name after &rest"), "{}", source);
+    }
+
+    assert!(eval("(lambda (&optional (1 2)) 1)").contains("expected an optional parameter to be a name or (name default)"));
+}
+
+#[test]
+fn memoized_functions_are_called_once_per_distinct_arguments() {
+    let counted = "(define n 0) (define f (memoize (lambda (&rest xs) (set! n (+ n 1)) xs)))";
+    assert_eq!(eval(&format!("{} (list (f) (f) (f 1 2) (f 1 2) (f (list 1)) (f (list 1)) n)", counted)), "(() () (1 2) (1 2) ((1)) ((1)) 3)");
+    assert_eq!(eval("(define fib (memoize (lambda (n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2))))))) (fib 80)"), "23416728348467685");
+    assert_eq!(eval("(define f (memoize car)) (list (fn? f) (f '(1)) (f '(1)))"), "(true 1 1)");
+}
+
+#[test]
+fn memoized_functions_keep_no_errors() {
+    let failing = "(define n 0) (define f (memoize (lambda (x) (set! n (+ n 1)) (car x))))";
+    assert_eq!(eval(&format!("{} (try (f 1) (catch e 1)) (try (f 1) (catch e 1)) n", failing)), "2");
+    assert!(eval("(define f (memoize (lambda (x) x))) (f)").contains("`lambda` expects 1 argument, got 0"));
+}
@@ -0,0 +1,101 @@
+use std::fs;
+
+use lisp::Interpreter;
+
+/// the image `interpreter` saves, under `name` in the temporary directory
+fn save(interpreter: &mut Interpreter, name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("lisp-image-{}-{}.lisp", name, std::process::id()));
+    let path = path.to_str().unwrap().replace('\\', "/");
+
+    interpreter.eval_str(&format!("(save-image \"{}\")", path)).unwrap();
+
+    let image = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    image
+}
+
+/// the image `source` leaves
+fn image(name: &str, source: &str) -> String {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(source).unwrap();
+    save(&mut interpreter, name)
+}
+
+/// a fresh interpreter started from `image`
+fn load(image: &str) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(image).unwrap();
+    interpreter
+}
+
+const SESSION: &str = "
+(define counter (let ((n 0)) (lambda () (set! n (+ n 1)) n)))
+(counter)
+(defmacro swap! (a b) (let ((tmp (gensym))) `(let ((,tmp ,a)) (set! ,a ,b) (set! ,b ,tmp))))
+(defstruct point x y)
+(define p (make-point 1 2))
+(define v (vector 1 \"a\" p))
+(define m (hash-map \"k\" (list 1 2) 'j [3]))
+(define (add x y) (+ x y))
+(define plus add)
+(define (adder k) (lambda (x) (+ x k)))
+(define add5 (adder 5))";
+
+#[test]
+fn images_recreate_the_definitions() {
+    let image = image("definitions", "(define (f) (quote q)) (define h (list 1 (quote a)))");
+
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(&image).unwrap();
+    assert_eq!(interpreter.eval_to_string("(list (f) h)"), "(q (1 a))");
+}
+
+#[test]
+fn images_leave_out_the_session_state() {
+    let image = image("session", "(define $1 3) (define $_ 3) (define *command-line-args* ())");
+
+    for name in ["$1", "$_", "*command-line-args*", "*last-backtrace*"] {
+        assert!(!image.contains(name), "{} is in the image:\n{}", name, image);
+    }
+}
+
+#[test]
+fn gensyms_are_not_saved() {
+    let image = image("gensyms", "(define g (gensym \"g\")) (define k (list 1 g)) (define (f) (quote q))");
+    assert!(!image.contains("(define g") && !image.contains("(define k"), "{}", image);
+}
+
+#[test]
+fn images_load_back_identically() {
+    let first = image("identical", SESSION);
+    let second = save(&mut load(&first), "identical-again");
+    assert_eq!(first, second);
+}
+
+#[test]
+fn closures_keep_what_they_captured() {
+    let mut interpreter = load(&image("closures", SESSION));
+
+    // the counter had counted once when it was saved
+    assert_eq!(interpreter.eval_to_string("(list (counter) (counter))"), "(2 3)");
+    assert_eq!(interpreter.eval_to_string("(list (add5 1) ((adder 2) 1) (plus 2 3) (add 1 1))"), "(6 3 5 2)");
+}
+
+#[test]
+fn macros_expand_as_before() {
+    let mut interpreter = load(&image("macros", SESSION));
+
+    assert_eq!(interpreter.eval_to_string("(let ((a 1) (b 2)) (swap! a b) (list a b))"), "(2 1)");
+
+    // the symbol the expansion binds is still made afresh by each expansion
+    assert_eq!(interpreter.eval_to_string("(let ((tmp 1) (b 2)) (swap! tmp b) (list tmp b))"), "(2 1)");
+}
+
+#[test]
+fn records_and_their_type_are_recreated() {
+    let mut interpreter = load(&image("records", SESSION));
+
+    assert_eq!(interpreter.eval_to_string("(list (point? p) (point-x p) (point-y (vector-ref v 2)))"), "(true 1 2)");
+    assert_eq!(interpreter.eval_to_string("(point-x (make-point 3 4))"), "3");
+    assert_eq!(interpreter.eval_to_string("(list (get m \"k\") (get m 'j))"), "((1 2) [3])");
+}
@@ -0,0 +1,47 @@
+use std::fs;
+use std::io;
+
+use lisp::Interpreter;
+
+mod common;
+
+use common::{eval, output, run, BACKENDS};
+
+#[test]
+fn print_and_println_write_their_arguments_apart() {
+    assert_eq!(output("(print \"a\" 1) (println \"b\" 'c) (print) (println \"x\\ny\")"), "a 1b c\nx\ny\n");
+    assert_eq!(eval("(list (print) (println))"), "(() ())");
+}
+
+#[test]
+fn read_line_reads_what_the_embedder_gives_it() {
+    for backend in BACKENDS.iter() {
+        let mut interpreter = Interpreter::with_backend(*backend);
+        let mut lines = vec!["second".to_string(), "first".to_string()];
+        interpreter.set_input(move || Ok(lines.pop()));
+
+        assert_eq!(interpreter.eval_to_string("(list (read-line) (read-line) (read-line))"), "(\"first\" \"second\" ())");
+
+        interpreter.set_input(|| Err(io::Error::other("closed")));
+        assert!(interpreter.eval_to_string("(read-line)").contains("read-line: closed"));
+    }
+
+    assert_eq!(run(&mut Interpreter::new(), "(read-line)").0, "()");
+}
+
+#[test]
+fn spit_writes_the_file_slurp_reads() {
+    let path = std::env::temp_dir().join(format!("lisp-io-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    assert_eq!(eval(&format!("(spit {:?} \"hi\\nthere\") (slurp {:?})", path, path)), "\"hi\\nthere\"");
+    assert_eq!(fs::read_to_string(path).unwrap(), "hi\nthere");
+
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn failing_file_operations_are_errors() {
+    assert!(eval("(slurp \"/no/such/file\")").contains("slurp: cannot read /no/such/file: "));
+    assert!(eval("(spit \"/no/such/dir/f\" \"x\")").contains("spit: cannot write /no/such/dir/f: "));
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn values_round_trip() {
+    let value = "(hash-map \"a\" (list 1 -2 2.5 true false \"x\") \"b\" (hash-map) \"c\" '() \"d\" (list (list 1) (hash-map \"e\" \"f\")))";
+    assert_eq!(eval(&format!("(define x {}) (equal? (json-parse (json-encode x)) x)", value)), "true");
+
+    assert_eq!(eval("(json-encode (json-parse \"[1,2.5,-0,true,false,null,\\\"x\\\",[],{}]\"))"), "\"[1,2.5,0,true,false,[],\\\"x\\\",[],{}]\"");
+    assert_eq!(eval("(json-encode (hash-map \"a\" (list 1)))"), "\"{\\\"a\\\":[1]}\"");
+}
+
+#[test]
+fn values_without_a_json_counterpart_are_encoded_as_the_closest_one() {
+    assert_eq!(eval("(json-encode [1 \"a\" 'b #\\c])"), "\"[1,\\\"a\\\",\\\"b\\\",\\\"c\\\"]\"");
+    assert_eq!(eval("(json-encode (hash-map 'a 1))"), "\"{\\\"a\\\":1}\"");
+    assert_eq!(eval("(json-parse \"null\")"), "()");
+}
+
+#[test]
+fn escapes() {
+    // the short escapes, and the solidus which json allows escaping but never needs to
+    assert_eq!(eval(r#"(string->list (json-parse "\"\\\"\\\\\\/\\b\\f\\n\\r\\t\""))"#), "(#\\\" #\\\\ #\\/ #\\\u{8} #\\\u{c} #\\newline #\\return #\\tab)");
+    assert_eq!(eval("(json-encode \"\\\" \\\\ / \n \r \t\")"), r#""\"\\\" \\\\ / \\n \\r \\t\"""#);
+
+    // other control characters have no short escape
+    assert_eq!(eval("(json-encode \"\u{1}\u{1f}\")"), r#""\"\\u0001\\u001f\"""#);
+    assert_eq!(eval(r#"(json-encode (json-parse "\"\\b\\f\""))"#), r#""\"\\u0008\\u000c\"""#);
+}
+
+#[test]
+fn unicode() {
+    assert_eq!(eval(r#"(json-parse "\"\\u0041\\u00e9\\u4e2d\"")"#), "\"Aé中\"");
+    assert_eq!(eval(r#"(json-parse "\"\\ud83d\\ude00\"")"#), "\"😀\"");
+    assert_eq!(eval(r#"(length (string->list (json-parse "\"\\uD83D\\uDE00\"")))"#), "1");
+
+    // written as is, json being utf-8
+    assert_eq!(eval("(json-encode \"é😀\")"), r#""\"é😀\"""#);
+    assert_eq!(eval("(json-parse \"\\\"é😀\\\"\")"), "\"é😀\"");
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(eval("(json-parse \"[0, -0, 12, -9223372036854775808, 9223372036854775807]\")"), "(0 0 12 -9223372036854775808 9223372036854775807)");
+    assert_eq!(eval("(json-parse \"[1.5e-3, 0.1, 1E+2, 2e2, -1.0]\")"), "(0.0015 0.1 100.0 200.0 -1.0)");
+
+    // past the range of integers, a float is the closest
+    assert_eq!(eval("(json-parse \"12345678901234567890\")"), "1.2345678901234567e19");
+
+    assert_eq!(eval("(json-encode (list 1.0 0.1 -0.0 1e300 9223372036854775807))"), r#""[1.0,0.1,-0.0,1e300,9223372036854775807]""#);
+    assert!(eval("(json-encode (* 1e308 10))").contains("cannot encode +inf.0 as json"));
+}
+
+#[test]
+fn parse_errors() {
+    let cases = [
+        ("", "at byte 0: unexpected end of input"),
+        ("[1,]", "at byte 3: expected a value"),
+        ("[1 2]", "at byte 4: expected `,` or `]` in an array"),
+        ("{\\\"a\\\" 1}", "at byte 5: expected `:` after the key"),
+        ("{1: 2}", "at byte 1: expected a string key"),
+        ("{\\\"a\\\": 1 \\\"b\\\": 2}", "at byte 9: expected `,` or `}` in an object"),
+        ("\\\"abc", "at byte 4: unterminated string"),
+        ("\\\"\\\\x\\\"", "at byte 3: unknown escape sequence"),
+        ("\\\"\\\\u12\\\"", "at byte 3: expected four hex digits after `\\u`"),
+        ("\\\"\\\\ud800\\\"", "at byte 7: invalid unicode escape"),
+        ("01", "at byte 1: unexpected text after the value"),
+        ("[1] x", "at byte 4: unexpected text after the value"),
+        ("-", "at byte 1: expected a digit"),
+        ("1.", "at byte 2: expected a digit after `.`"),
+        ("1e", "at byte 2: expected a digit in the exponent"),
+        ("tru", "at byte 0: expected a value"),
+    ];
+
+    for (text, msg) in cases.iter() {
+        let res = eval(&format!("(json-parse \"{}\")", text));
+        assert!(res.contains(&format!("parse error: invalid json {}", msg)), "{}: {}", text, res);
+    }
+}
+
+#[test]
+fn encode_errors() {
+    assert!(eval("(json-encode (hash-map 1 2))").contains("expected a string or symbol key, got integer"));
+    assert!(eval("(json-encode (lambda (x) x))").contains("cannot encode a lambda as json"));
+    assert!(eval("(json-encode (list (chan)))").contains("cannot encode a channel as json"));
+
+    // a vector inside itself would be endless
+    assert!(eval("(let ((v (vector 1))) (vector-set! v 0 v) (json-encode v))").contains("too deep"));
+}
@@ -0,0 +1,54 @@
+use lisp::{Interpreter, LangErr, Limits};
+
+mod common;
+
+use common::{eval_with, run};
+
+#[test]
+fn running_out_of_fuel_stops_endless_code() {
+    let limits = Limits { fuel: Some(1000), ..Limits::default() };
+
+    assert!(eval_with(limits, "(define (spin) (spin)) (spin)").contains("limit exceeded: ran out of fuel after 1000 steps"));
+    assert!(eval_with(limits, "(try (loop () (recur)) (catch e 'caught))").contains("ran out of fuel"));
+    assert_eq!(eval_with(limits, "(+ 1 2)"), "3");
+}
+
+#[test]
+fn each_evaluation_gets_a_full_tank() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(Limits { fuel: Some(1000), ..Limits::default() });
+
+    let err = interpreter.eval_str("(define (spin) (spin)) (spin)").unwrap_err();
+    assert!(matches!(err.inner(), LangErr::LimitExceeded(_)), "{:?}", err);
+    assert_eq!(interpreter.eval_str("(+ 1 2)").unwrap().to_string(), "3");
+}
+
+#[test]
+fn values_over_the_size_limit_are_refused() {
+    let limits = Limits { size: Some(10), ..Limits::default() };
+
+    assert_eq!(eval_with(limits, "(length (range 0 10))"), "10");
+    assert!(eval_with(limits, "(range 0 11)").contains("limit exceeded: a list of 11 elements is over the size limit of 10"));
+    assert!(eval_with(limits, "(str \"aaaaaa\" \"bbbbbb\")").contains("a string of 12 characters is over the size limit of 10"));
+}
+
+#[test]
+fn io_can_be_turned_off() {
+    let limits = Limits { io: false, ..Limits::default() };
+
+    for name in ["slurp", "spit", "getenv", "exit", "load", "spawn"] {
+        let source = match name {
+            "spit" => "(spit \"x\" \"y\")".to_string(),
+            "exit" => "(exit 1)".to_string(),
+            "spawn" => "(spawn (lambda () 1))".to_string(),
+            _ => format!("({} \"x\")", name),
+        };
+
+        assert!(eval_with(limits, &source).contains(&format!("`{}` is disabled: this interpreter doesn't allow io", name)), "{}", source);
+    }
+
+    // printing goes to the interpreter's sink, which the embedder owns
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(limits);
+    assert_eq!(run(&mut interpreter, "(println 1)"), ("()".to_string(), "1\n".to_string()));
+}
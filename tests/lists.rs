@@ -0,0 +1,55 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn cons_and_cdr_share_the_list_they_are_given() {
+    assert_eq!(eval("(define xs (cons 1 (cons 2 '()))) (eq? (cdr (cons 0 xs)) xs)"), "true");
+    assert_eq!(eval("(define xs (list 1 2 3)) (eq? (cdr xs) (cdr xs))"), "true");
+    assert_eq!(eval("(define xs (list 1 2 3)) (eq? (cdr (cons 0 xs)) (cons 0 xs))"), "false");
+}
+
+#[test]
+fn consing_twice_onto_a_list_leaves_it_and_the_first_result_alone() {
+    assert_eq!(eval("(define xs (list 1 2)) (define a (cons 'a xs)) (define b (cons 'b xs)) (list a b xs)"), "((a 1 2) (b 1 2) (1 2))");
+    assert_eq!(eval("(define xs (cdr (list 1 2 3))) (define a (cons 'a xs)) (list a (cons 'b xs) (cons 'c a))"), "((a 2 3) (b 2 3) (c a 2 3))");
+    assert_eq!(eval("(define xs (cdr (list 1))) (list (cons 1 xs) (cons 2 xs) xs)"), "((1) (2) ())");
+}
+
+#[test]
+fn lists_built_one_cons_at_a_time_are_built_in_linear_time() {
+    // quadratic, this would copy over a billion elements
+    let source = "(define (build i acc) (if (= i 0) acc (build (- i 1) (cons i acc)))) (define xs (build 50000 '()))";
+    assert_eq!(eval(&format!("{} (list (length xs) (car xs) (nth 49999 xs))", source)), "(50000 1 50000)");
+
+    let source = "(define (walk xs n) (if (empty? xs) n (walk (cdr xs) (+ n 1)))) (walk (build 50000 '()) 0)";
+    assert_eq!(eval(&format!("(define (build i acc) (if (= i 0) acc (build (- i 1) (cons i acc)))) {}", source)), "50000");
+}
+
+#[test]
+fn threads_consing_onto_the_same_list_each_get_their_own() {
+    let res = eval(
+        "(define xs (list 0))
+         (define ts (map (lambda (i) (spawn (lambda () (fold (lambda (acc j) (cons (+ i j) acc)) xs (list 1 2 3))))) (list 10 20 30 40)))
+         (list (map join ts) xs)",
+    );
+    assert_eq!(res, "(((13 12 11 0) (23 22 21 0) (33 32 31 0) (43 42 41 0)) (0))");
+}
+
+#[test]
+fn list_primitives() {
+    assert_eq!(eval("(list (cons 1 '(2 3)) (car '(1 2)) (cdr '(1 2)) (cdr '(1)) (list) (length '(1 2 3)))"), "((1 2 3) 1 (2) () () 3)");
+    assert_eq!(eval("(list (append '(1) '() '(2 3)) (append) (reverse '(1 2 3)) (nth 1 '(a b c)))"), "((1 2 3) () (3 2 1) b)");
+    assert_eq!(eval("(list (list? '()) (list? 1) (null? '()) (null? '(1)))"), "(true false true false)");
+}
+
+#[test]
+fn list_primitives_check_their_arguments() {
+    assert!(eval("(car '())").contains("car of an empty list"));
+    assert!(eval("(cdr '())").contains("cdr of an empty list"));
+    assert!(eval("(cons 1 2)").contains("type error: expected a list, got integer"));
+    assert!(eval("(append '(1) 2)").contains("type error: expected a list, got integer"));
+    assert!(eval("(nth 5 '(1 2))").contains("index 5 out of range for a list of length 2"));
+    assert!(eval("(nth -1 '(1 2))").contains("type error: expected a non-negative integer, got integer"));
+    assert!(eval("(car 1 2)").contains("`car` expects 1 argument, got 2"));
+}
@@ -0,0 +1,52 @@
+use std::fs;
+
+use lisp::Interpreter;
+
+/// writes `source` to a file named after `name` in the temporary directory, returning its path
+fn file(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("lisp-load-{}-{}.lisp", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    path.to_str().unwrap().replace('\\', "/")
+}
+
+#[test]
+fn load_evaluates_a_file_each_time() {
+    let path = file("counter", "(set! n (+ n 1)) n");
+    let mut interpreter = Interpreter::new();
+
+    let res = interpreter.eval_to_string(&format!("(define n 0) (load \"{0}\") (load \"{0}\")", path));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(res, "2");
+}
+
+#[test]
+fn require_evaluates_a_file_once() {
+    let path = file("once", "(define loaded-once 1)");
+    let mut interpreter = Interpreter::new();
+
+    let res = interpreter.eval_to_string(&format!("(list (require \"{0}\") (require \"{0}\") loaded-once)", path));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(res, "(true false 1)");
+}
+
+#[test]
+fn errors_point_into_the_loaded_file() {
+    let path = file("error", "(define a 1)\n(car a)");
+    let res = Interpreter::new().eval_to_string(&format!("(load \"{}\")", path));
+    fs::remove_file(&path).unwrap();
+
+    assert!(res.contains(&format!("{}:2:1: type error: expected a list, got integer", path)), "{}", res);
+}
+
+#[test]
+fn a_file_that_failed_is_required_again() {
+    let path = file("retry", "(define attempts (+ attempts 1)) (car ready)");
+    let mut interpreter = Interpreter::new();
+
+    interpreter.eval_str("(define attempts 0) (define ready 1)").unwrap();
+    assert!(interpreter.eval_str(&format!("(require \"{}\")", path)).is_err());
+
+    let res = interpreter.eval_to_string(&format!("(set! ready '(1)) (list (require \"{}\") attempts)", path));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(res, "(true 2)");
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn recur_in_tail_position_starts_the_loop_over() {
+    assert_eq!(eval("(loop ((i 0) (acc 0)) (if (< i 5) (recur (+ i 1) (+ acc i)) acc))"), "10");
+    assert_eq!(eval("(loop ((i 0)) (cond ((< i 3) (let ((j (+ i 1))) (recur j))) (else i)))"), "3");
+}
+
+#[test]
+fn recur_loops_without_growing_the_stack() {
+    assert_eq!(eval("(loop ((i 0)) (if (< i 100000) (recur (+ i 1)) i))"), "100000");
+}
+
+#[test]
+fn recur_outside_the_tail_position_is_an_error() {
+    let res = eval("(loop ((i 0)) (if (< i 3) (+ 100 (recur (+ i 1))) i))");
+    assert!(res.contains("recur used outside the tail position of a loop"), "{}", res);
+}
+
+#[test]
+fn recur_as_an_argument_does_not_spin() {
+    let res = eval("(loop ((i 0)) (+ 1 (recur 1)))");
+    assert!(res.contains("recur used outside the tail position of a loop"), "{}", res);
+}
+
+#[test]
+fn recur_in_a_lambda_called_from_the_loop_is_an_error() {
+    let res = eval("(loop ((i 0)) ((lambda () (recur 1))))");
+    assert!(res.contains("recur used outside the tail position of a loop"), "{}", res);
+}
+
+#[test]
+fn recur_expects_a_value_per_binding() {
+    let res = eval("(loop ((i 0)) (recur))");
+    assert!(res.contains("`recur` expects 1 argument, got 0"), "{}", res);
+}
+
+#[test]
+fn special_forms_cannot_be_defined() {
+    let res = eval("(define (loop n) n)");
+    assert!(res.contains("cannot define loop, it names a special form"), "{}", res);
+}
+
+#[test]
+fn malformed_loops_are_reported_as_loop() {
+    let res = eval("(loop 5)");
+    assert!(res.contains("loop expects a list of (name value) bindings"), "{}", res);
+}
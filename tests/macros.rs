@@ -0,0 +1,47 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn macros_are_expanded_then_evaluated_in_place() {
+    assert_eq!(eval("(defmacro unless (c &rest body) `(if ,c '() (begin ,@body))) (list (unless false 1 2) (unless true 1))"), "(2 ())");
+    assert_eq!(eval("(defmacro inc! (x) `(set! ,x (+ ,x 1))) (define n 1) (inc! n) (inc! n) n"), "3");
+    assert_eq!(eval("(defmacro m (x) x)"), "m");
+}
+
+#[test]
+fn macro_arguments_are_not_evaluated() {
+    assert_eq!(eval("(defmacro my-if (c a b) `(cond (,c ,a) (else ,b))) (my-if true 1 (car 5))"), "1");
+    assert_eq!(eval("(defmacro quoted (x) `',x) (quoted (car 5))"), "(car 5)");
+}
+
+#[test]
+fn macroexpand_shows_the_expansion() {
+    assert_eq!(eval("(defmacro twice (e) `(begin ,e ,e)) (macroexpand '(twice (print 1)))"), "(begin (print 1) (print 1))");
+
+    // until the head is no longer a macro
+    assert_eq!(eval("(defmacro m (x) x) (macroexpand '(m (m 5)))"), "5");
+    assert_eq!(eval("(macroexpand '(+ 1 2))"), "(+ 1 2)");
+}
+
+#[test]
+fn macro_errors() {
+    assert!(eval("(defmacro)").contains("defmacro expects a name, a parameter list and a body"));
+    assert!(eval("(defmacro m 1 2)").contains("expected lambda parameters to be a list"));
+    assert!(eval("(defmacro m (x) x) (m)").contains("`m` expects 1 argument, got 0"));
+
+    // an error while expanding points into the macro
+    assert!(eval("(defmacro m (x) (car 5)) (m 1)").starts_with("1:17: type error: expected a list, got integer"));
+}
+
+#[test]
+fn gensym_makes_symbols_nothing_else_is_eq_to() {
+    assert_eq!(eval("(list (symbol? (gensym)) (eq? (gensym) (gensym)) (eq? (gensym \"g\") 'g1))"), "(true false false)");
+    assert!(eval("(gensym 1)").contains("type error: expected a string, got integer"));
+}
+
+#[test]
+fn gensyms_keep_macros_from_capturing_the_callers_names() {
+    let swap = "(defmacro swap! (a b) (let ((t (gensym))) `(let ((,t ,a)) (set! ,a ,b) (set! ,b ,t))))";
+    assert_eq!(eval(&format!("{} (define t 1) (define u 2) (swap! t u) (list t u)", swap)), "(2 1)");
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn values_are_found_by_their_key() {
+    let map = "(define m (hash-map \"a\" 1 'b 2 1.5 3 (list 1 2) 4))";
+    assert_eq!(eval(&format!("{} (list (get m \"a\") (get m 'b) (get m 1.5) (get m (list 1 2)))", map)), "(1 2 3 4)");
+    assert_eq!(eval(&format!("{} (list (get m \"b\") (get m 'c 'default) (contains? m 'b) (contains? m \"b\"))", map)), "(() default true false)");
+
+    // keys are compared with equal?, which keeps integers and floats apart
+    assert_eq!(eval("(get (hash-map 1 'x) 1.0)"), "()");
+}
+
+#[test]
+fn assoc_and_dissoc_make_new_maps() {
+    assert_eq!(eval("(define m (hash-map 1 'one)) (list (keys m) (vals m) (dissoc m 1) m)"), "((1) (one) {} {1 one})");
+    assert_eq!(eval("(define m (assoc (hash-map 'a 1) 'b 2 'a 3)) (list (get m 'a) (get m 'b) (length (keys m)) (reduce + (vals m)))"), "(3 2 2 5)");
+    assert_eq!(eval("(equal? (hash-map \"a\" 1 'b 2) (assoc (hash-map 'b 2) \"a\" 1))"), "true");
+}
+
+#[test]
+fn malformed_map_calls_are_errors() {
+    assert!(eval("(hash-map 1)").contains("hash-map expects an even number of arguments"));
+    assert!(eval("(assoc (hash-map) 1)").contains("assoc expects a map followed by keys and values"));
+    assert!(eval("(get 5 1)").contains("type error: expected a map, got integer"));
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn division_stays_exact_without_a_remainder() {
+    assert_eq!(eval("(/ 6 3)"), "2");
+    assert_eq!(eval("(/ 7 2)"), "3.5");
+}
+
+#[test]
+fn division_overflowing_an_integer_gives_a_float() {
+    assert_eq!(eval("(/ -9223372036854775808 -1)"), "9.223372036854776e18");
+}
+
+#[test]
+fn modulo_takes_the_sign_of_the_divisor() {
+    assert_eq!(eval("(mod -7 2)"), "1");
+    assert_eq!(eval("(mod 7 -2)"), "-1");
+}
+
+#[test]
+fn modulo_of_the_overflowing_division_is_zero() {
+    assert_eq!(eval("(mod -9223372036854775808 -1)"), "0");
+}
+
+#[test]
+fn arithmetic_takes_any_number_of_arguments() {
+    assert_eq!(eval("(list (+) (+ 1) (+ 1 2 3) (- 5) (- 10 1 2) (*) (* 2 3 4) (/ 2) (/ 8 2 2))"), "(0 1 6 -5 7 1 24 0.5 2)");
+    assert_eq!(eval("(list (+ 1 2.5) (* 2 0.5) (- 0.5 0.5))"), "(3.5 1.0 0.0)");
+    assert!(eval("(-)").contains("`-` expects at least 1 argument, got 0"));
+    assert!(eval("(+ 1 'a)").contains("type error: expected a number, got symbol"));
+}
+
+#[test]
+fn the_rest_of_the_arithmetic_builtins() {
+    assert_eq!(eval("(list (pow 2 10) (pow 2 -1) (sqrt 16) (min 3 1 2) (max 3 1 2.5) (abs -3) (abs -2.5))"), "(1024 0.5 4.0 1 3 3 2.5)");
+    assert_eq!(eval("(list (floor -3.5) (ceil -3.5) (round 2.5) (round -2.5) (truncate -3.7) (floor 3) (exact->inexact 3))"), "(-4 -3 3 -3 -3 3 3.0)");
+    assert!(eval("(sqrt -1)").contains("sqrt expects a non-negative number"));
+}
+
+#[test]
+fn dividing_by_zero_is_an_error() {
+    for source in ["(/ 1 0)", "(/ 1.0 0)", "(mod 1 0)"] {
+        assert!(eval(source).contains("division by zero"), "{}", source);
+    }
+}
+
+#[test]
+fn integers_stay_integers_until_a_float_is_involved() {
+    assert_eq!(eval("(list 1 1.5 -2 1e3 .5 -0.0)"), "(1 1.5 -2 1000.0 0.5 -0.0)");
+    assert_eq!(eval("(list (+ 1 1) (+ 1 1.0) (* 2 2.5) (/ 4 2) (/ 1 3))"), "(2 2.0 5.0 2 0.3333333333333333)");
+    assert_eq!(eval("(list (integer? 1) (integer? 1.0) (number? 1.5) (number? 'a))"), "(true false true false)");
+    assert_eq!(eval("(list (exact->inexact 1) (round 1.5) (floor 1.5))"), "(1.0 2 1)");
+}
+
+#[test]
+fn integers_overflowing_become_floats() {
+    let res = eval("(list (+ 9223372036854775807 1) (* 9223372036854775807 2) (- -9223372036854775808 1) (- -9223372036854775808) (abs -9223372036854775808))");
+    assert_eq!(res, "(9.223372036854776e18 1.8446744073709552e19 -9.223372036854776e18 9.223372036854776e18 9.223372036854776e18)");
+
+    // and so do literals too large to be integers
+    assert_eq!(eval("(list 9223372036854775807 9223372036854775808 -9223372036854775809)"), "(9223372036854775807 9.223372036854776e18 -9.223372036854776e18)");
+}
+
+#[test]
+fn numbers_are_read_from_and_printed_to_strings() {
+    assert_eq!(eval("(list (string->number \"12\") (string->number \"1.5\") (string->number \"x\") (number->string 2.0))"), "(12 1.5 false \"2.0\")");
+}
@@ -0,0 +1,41 @@
+use lisp::Interpreter;
+
+/// what `source` evaluates to with and without the optimizer, which should agree
+fn eval(source: &str) -> String {
+    let plain = Interpreter::new().eval_to_string(source);
+
+    let mut optimizing = Interpreter::new();
+    optimizing.set_optimizing(true);
+    let optimized = optimizing.eval_to_string(source);
+
+    assert_eq!(plain, optimized, "optimizing changes what {} evaluates to", source);
+    plain
+}
+
+#[test]
+fn constants_are_folded() {
+    assert_eq!(Interpreter::new().eval_to_string("(optimize (quote (+ 1 (* 2 3))))"), "7");
+    assert_eq!(eval("(define (f) (if (< 1 2) (+ 1 (* 2 3)) 0)) (f)"), "7");
+}
+
+#[test]
+fn locals_are_found_with_lexical_addresses() {
+    let res = Interpreter::new().eval_to_string("(define z 1) (optimize (quote (lambda (a) (let ((b 1)) (list a b z)))))");
+    assert_eq!(res, "(lambda (a) (let ((b 1)) ((%lexical 2 list) (%lexical 1 a) b (%lexical 2 z))))");
+}
+
+#[test]
+fn a_macro_defined_later_may_define_into_a_scope() {
+    let source = "(define x 1)
+        (define (main) (let ((y 2)) (when-def) x))
+        (defmacro when-def () (quote (define x 8)))
+        (main)";
+
+    assert_eq!(eval(source), "8");
+}
+
+#[test]
+fn calls_of_parameters_leave_the_scope_closed() {
+    let res = Interpreter::new().eval_to_string("(define z 1) (optimize (quote (lambda (f) (let ((b 1)) (f z)))))");
+    assert_eq!(res, "(lambda (f) (let ((b 1)) ((%lexical 1 f) (%lexical 2 z))))");
+}
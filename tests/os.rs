@@ -0,0 +1,37 @@
+use lisp::Limits;
+
+mod common;
+
+use common::{eval, eval_with};
+
+#[test]
+fn setenv_sets_what_getenv_and_commands_see() {
+    assert_eq!(eval("(setenv \"LISP_OS_TEST\" \"set\") (list (getenv \"LISP_OS_TEST\") (get (sh \"echo $LISP_OS_TEST\") \"out\"))"), "(\"set\" \"set\\n\")");
+    assert_eq!(std::env::var("LISP_OS_TEST").unwrap(), "set");
+    assert_eq!(eval("(getenv \"LISP_OS_TEST_NEVER_SET\")"), "()");
+}
+
+#[test]
+fn sh_gives_the_status_and_output_of_the_command() {
+    let res = eval("(define r (sh \"echo out; echo err >&2; exit 3\")) (list (get r \"status\") (get r \"out\") (get r \"err\"))");
+    assert_eq!(res, "(3 \"out\\n\" \"err\\n\")");
+
+    // no status when the command was killed
+    assert_eq!(eval("(get (sh \"kill -9 $$\") \"status\")"), "()");
+}
+
+#[test]
+fn os_builtins_check_their_arguments() {
+    assert!(eval("(sh 1)").contains("type error: expected a string, got integer"));
+    assert!(eval("(setenv \"A\" 1)").contains("type error: expected a string, got integer"));
+    assert!(eval("(exit \"x\")").contains("type error: expected an integer, got string"));
+}
+
+#[test]
+fn os_builtins_are_off_without_io() {
+    let limits = Limits { io: false, ..Limits::default() };
+
+    assert!(eval_with(limits, "(sh \"echo hi\")").contains("`sh` is disabled"));
+    assert!(eval_with(limits, "(setenv \"LISP_OS_TEST_OFF\" \"x\")").contains("`setenv` is disabled"));
+    assert!(std::env::var("LISP_OS_TEST_OFF").is_err());
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn the_first_matching_clause_is_taken() {
+    assert_eq!(eval("(match '(1 2) ((list a) 'one) ((list a b) (+ a b)) (_ 'other))"), "3");
+    assert_eq!(eval("(match 5 (\"five\" 's) (5 'n))"), "n");
+    assert_eq!(eval("(match 'x ('y 1) ('x 2))"), "2");
+}
+
+#[test]
+fn list_and_vector_patterns_destructure() {
+    assert_eq!(eval("(match '(1 2 3) ((cons h t) (list h t)))"), "(1 (2 3))");
+    assert_eq!(eval("(match (list 1 [2 3]) ((list n [x y]) (+ n x y)))"), "6");
+    assert_eq!(eval("(match [1 2] ([a] 'short) ([a b c] 'long) (_ 'neither))"), "neither");
+}
+
+#[test]
+fn predicate_patterns_guard_the_clause() {
+    assert_eq!(eval("(match 4 ((? string?) 's) ((? integer? n) (* n n)))"), "16");
+}
+
+#[test]
+fn no_matching_clause_is_an_error() {
+    assert!(eval("(match 5 (\"five\" 1))").contains("no match clause matches 5"));
+    assert!(eval("(match 5 ((a b) 1))").contains("(a b) is not a valid pattern"));
+}
+
+#[test]
+fn a_guard_may_write_to_the_vector_being_matched() {
+    let src = "(define u (vector 1 2)) (match u ([a (? (lambda (x) (vector-set! u 0 7) #t) b)] (list a b u)))";
+    assert_eq!(eval(src), "(1 2 [7 2])");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn list_functions() {
+    assert_eq!(eval("(list (second '(1 2 3)) (third '(1 2 3)) (last '(1 2 3)) (last '(1)))"), "(2 3 3 1)");
+    assert_eq!(eval("(list (take 2 '(1 2 3)) (take 5 '(1 2)) (take 0 '(1 2)))"), "((1 2) (1 2) ())");
+    assert_eq!(eval("(list (drop 2 '(1 2 3)) (drop 5 '(1 2)) (drop 0 '(1 2)))"), "((3) () (1 2))");
+    assert_eq!(eval("(list (range 0 5) (range 3 4) (range 2 2) (range 5 0))"), "((0 1 2 3 4) (3) () ())");
+    assert_eq!(eval("(list (empty? '()) (empty? '(1)))"), "(true false)");
+}
+
+#[test]
+fn predicates_over_lists() {
+    assert_eq!(eval("(count (lambda (x) (> x 1)) '(1 2 3))"), "2");
+    assert_eq!(eval("(list (every? (lambda (x) (> x 0)) '(1 2)) (every? (lambda (x) (> x 1)) '(1 2)) (every? (lambda (x) false) '()))"), "(true false true)");
+    assert_eq!(eval("(list (any? (lambda (x) (> x 1)) '(1 2)) (any? (lambda (x) (> x 2)) '(1 2)) (any? (lambda (x) true) '()))"), "(true false false)");
+}
+
+#[test]
+fn functions_of_functions() {
+    assert_eq!(eval("((compose (lambda (x) (* x 2)) (lambda (x) (+ x 1))) 3)"), "8");
+    assert_eq!(eval("(identity 'x)"), "x");
+}
+
+#[test]
+fn streams() {
+    assert_eq!(eval("(take 3 (integers-from 5))"), "(5 6 7)");
+    assert_eq!(eval("(take 3 (stream-map (lambda (x) (* x x)) (integers-from 1)))"), "(1 4 9)");
+    assert_eq!(eval("(take 3 (stream-filter (lambda (x) (= (mod x 2) 0)) (integers-from 1)))"), "(2 4 6)");
+    assert_eq!(eval("(stream-car (drop 10 (integers-from 0)))"), "10");
+}
+
+#[test]
+fn long_lists_take_linear_time() {
+    // each of these conses or cdrs once per element, so took quadratic time while those copied.
+    // `take` calls itself in tail position, so it isn't limited by the stack either
+    assert_eq!(eval("(length (range 0 20000))"), "20000");
+    assert_eq!(eval("(length (take 20000 (range 0 20000)))"), "20000");
+    assert_eq!(eval("(drop 19999 (range 0 20000))"), "(19999)");
+    assert_eq!(eval("(last (range 0 20000))"), "19999");
+}
+
+#[test]
+fn promises_are_evaluated_once_when_first_forced() {
+    assert_eq!(eval("(define n 0) (define p (delay (begin (set! n (+ n 1)) n))) (list n (promise? p) (force p) (force p) n)"), "(0 true 1 1 1)");
+    assert_eq!(eval("(list (force 5) (promise? 5))"), "(5 false)");
+    assert_eq!(eval("(define s (cons-stream 1 (car 5))) (stream-car s)"), "1");
+    assert!(eval("(delay)").contains("delay expects exactly one form"));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::{eval, output};
+use lisp::{read_str, LangExp};
+
+fn read(source: &str) -> LangExp {
+    read_str(source).unwrap().remove(0)
+}
+
+#[test]
+fn values_that_fit_print_on_one_line() {
+    for source in ["(1 2 3)", "(define (f x) (* x x))", "[1 (2) {:a 1}]"] {
+        assert_eq!(read(source).to_pretty_string(80), read(source).to_string());
+        assert_eq!(read(source).to_pretty_string(source.len()), read(source).to_string());
+    }
+
+    // atoms can't be broken up, however little room there is
+    assert_eq!(read("\"a long string\"").to_pretty_string(5), "\"a long string\"");
+    assert_eq!(read("a-long-symbol").to_pretty_string(0), "a-long-symbol");
+}
+
+#[test]
+fn atoms_are_packed_onto_as_few_lines_as_fit() {
+    let list = read("(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19)");
+    assert_eq!(list.to_pretty_string(20), "(0 1 2 3 4 5 6 7 8 9\n 10 11 12 13 14 15\n 16 17 18 19)");
+
+    let vector = read("[4 5 6 7 8 9 10 11 12]");
+    assert_eq!(vector.to_pretty_string(10), "[4 5 6 7 8\n 9 10 11\n 12]");
+}
+
+#[test]
+fn code_is_indented_under_its_head() {
+    let code = read("(define (f x) (let ((y (* x x)) (z 2)) (if (> y 10) (list y z) (f (+ x 1)))))");
+    let expected = "\
+(define (f x)
+  (let ((y (* x x)) (z 2))
+    (if (> y 10)
+      (list y z)
+      (f (+ x 1)))))";
+
+    assert_eq!(code.to_pretty_string(30), expected);
+}
+
+#[test]
+fn nested_data_is_broken_up_level_by_level() {
+    assert_eq!(read("[1 (2 3) [4 5 6 7 8 9 10 11 12]]").to_pretty_string(12), "[1\n (2 3)\n [4 5 6 7 8\n  9 10 11 12]]");
+    assert_eq!(read("((a b c d e f) (g h i j k l))").to_pretty_string(5), "((a b\n  c d\n  e f)\n (g h\n  i j\n  k l))");
+    assert_eq!(read("{\"a\" (0 1 2 3 4 5 6 7 8 9)}").to_pretty_string(15), "{\"a\" (0 1 2 3 4\n      5 6 7 8 9)}");
+}
+
+#[test]
+fn what_is_printed_over_several_lines_reads_back_equal() {
+    let source = "(define (f x) {:k [1 2 (3 \"four\" #\\5)] :l ((a b) (c d))} (let ((y (* x x))) (if (> y 10) 'big \"small\")))";
+    let value = read(source);
+
+    for width in [0, 5, 10, 20, 40, 80] {
+        let pretty = value.to_pretty_string(width);
+        assert_eq!(read_str(&pretty).unwrap(), vec![value.clone()], "at width {}:\n{}", width, pretty);
+    }
+}
+
+#[test]
+fn values_inside_themselves_are_cut_short() {
+    let printed = output("(let ((v (vector 1 2 3 4 5 6 7 8))) (vector-set! v 0 v) (pprint (list v v) 10))");
+    assert_eq!(printed, format!("({}\n {})\n", "[...\n  2\n  3\n  4\n  5\n  6\n  7\n  8]", "[...\n  2\n  3\n  4\n  5\n  6\n  7\n  8]"));
+}
+
+#[test]
+fn pprint_writes_the_value_broken_at_a_width() {
+    // 80 columns unless it is given another width
+    assert_eq!(output("(pprint (range 0 30))"), "(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29)\n");
+    assert_eq!(
+        output("(pprint (range 0 40))"),
+        "(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29\n 30 31 32 33 34 35 36 37 38 39)\n"
+    );
+    assert_eq!(output("(pprint (range 0 12) 12)"), "(0 1 2 3 4 5\n 6 7 8 9 10\n 11)\n");
+
+    assert_eq!(eval("(pprint 1)"), "()");
+    assert!(eval("(pprint 1 -1)").contains("type error: expected a non-negative integer, got integer"));
+    assert!(eval("(pprint 1 'x)").contains("type error: expected a non-negative integer, got symbol"));
+    assert!(eval("(pprint)").contains("pprint"));
+}
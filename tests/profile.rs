@@ -0,0 +1,55 @@
+use lisp::Interpreter;
+
+mod common;
+
+use common::BACKENDS;
+
+const FIB: &str = "(define (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))";
+
+/// the call count of each function in a profile report, by name
+fn calls(report: &str) -> Vec<(String, u64)> {
+    let mut rows: Vec<(String, u64)> = report
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            (fields[2].to_string(), fields[0].parse().unwrap())
+        })
+        .collect();
+
+    rows.sort();
+    rows
+}
+
+#[test]
+fn calls_of_natives_and_lambdas_are_counted() {
+    for backend in BACKENDS.iter() {
+        let mut interpreter = Interpreter::with_backend(*backend);
+        interpreter.eval_str(FIB).unwrap();
+
+        interpreter.set_profiling(true);
+        interpreter.eval_str("(fib 10) (map (lambda (x) x) '(1 2))").unwrap();
+        interpreter.set_profiling(false);
+
+        let report = interpreter.profile_report();
+        assert!(report.starts_with("     calls      total ms  function\n"), "{}", report);
+
+        let rows = calls(&report);
+        for expected in [("fib", 177), ("<", 177), ("+", 88), ("-", 176), ("map", 1), ("(lambda)", 2)] {
+            assert!(rows.contains(&(expected.0.to_string(), expected.1)), "{:?} on {:?}: {}", expected, backend, report);
+        }
+    }
+}
+
+#[test]
+fn nothing_is_counted_while_profiling_is_off_or_after_clearing() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(FIB).unwrap();
+    interpreter.eval_str("(fib 5)").unwrap();
+    assert_eq!(calls(&interpreter.profile_report()), vec![]);
+
+    interpreter.set_profiling(true);
+    interpreter.eval_str("(fib 5)").unwrap();
+    interpreter.clear_profile();
+    assert_eq!(calls(&interpreter.profile_report()), vec![]);
+}
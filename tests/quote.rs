@@ -0,0 +1,34 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn quoted_forms_are_not_evaluated() {
+    assert_eq!(eval("(list 'a '(1 b \"c\") (quote x) ''a)"), "(a (1 b \"c\") x (quote a))");
+    assert_eq!(eval("`x"), "x");
+}
+
+#[test]
+fn quasiquote_evaluates_what_is_unquoted() {
+    assert_eq!(eval("(let ((x 1) (xs (list 2 3))) `(a ,x ,@xs b))"), "(a 1 2 3 b)");
+    assert_eq!(eval("`(1 ,(+ 1 1) ,@(list 3 4))"), "(1 2 3 4)");
+    assert_eq!(eval("`(1 ,@'() 2)"), "(1 2)");
+    assert_eq!(eval("`(a . ,(+ 1 2))"), "(a . 3)");
+}
+
+#[test]
+fn nested_quasiquotes_only_evaluate_the_innermost_level() {
+    assert_eq!(eval("`(nested `(a ,(b ,(+ 1 2))))"), "(nested (quasiquote (a (unquote (b 3)))))");
+}
+
+#[test]
+fn malformed_quotes_are_errors() {
+    for source in ["(quote)", "(quote 1 2)"] {
+        assert!(eval(source).contains("quote expects exactly one form"), "{}", source);
+    }
+
+    assert!(eval("(let ((x 5)) `(,@x))").contains("unquote-splicing expects a list"));
+
+    // outside a quasiquote, unquote is just a symbol
+    assert!(eval(",x").contains("unbound symbol `unquote`"));
+}
@@ -0,0 +1,126 @@
+use lisp::{read_str, write, Interpreter, LangExp, SymbolId};
+
+/// `exp` written and read back
+fn reread(exp: &LangExp) -> LangExp {
+    let text = write(exp);
+    let mut forms = read_str(&text).unwrap_or_else(|e| panic!("{} doesn't read back: {}", text, e));
+
+    assert_eq!(forms.len(), 1, "{} reads back as {} forms", text, forms.len());
+    forms.remove(0)
+}
+
+#[test]
+fn written_atoms_read_back_equal() {
+    let atoms = [
+        LangExp::Bool(true),
+        LangExp::Bool(false),
+        LangExp::Int(0),
+        LangExp::Int(i64::MIN),
+        LangExp::Int(i64::MAX),
+        LangExp::Number(0.1),
+        LangExp::Number(3.0),
+        LangExp::Number(-0.0),
+        LangExp::Number(1e-7),
+        LangExp::Number(1e300),
+        LangExp::Number(f64::INFINITY),
+        LangExp::Number(f64::NEG_INFINITY),
+        LangExp::Str("".into()),
+        LangExp::Str("a \"b\" \\c\nd\te\r\u{1}é😀".into()),
+        LangExp::Char(' '),
+        LangExp::Char('\n'),
+        LangExp::Char('\u{0}'),
+        LangExp::Char('\u{7}'),
+        LangExp::Char('('),
+        LangExp::Char('"'),
+        LangExp::Char('\\'),
+        LangExp::Char('é'),
+        LangExp::Symbol(SymbolId::intern("foo-bar?")),
+        LangExp::Symbol(SymbolId::intern(":key")),
+        LangExp::Symbol(SymbolId::intern("1/2")),
+    ];
+
+    for atom in atoms.iter() {
+        assert_eq!(&reread(atom), atom, "written as {}", write(atom));
+    }
+
+    // not a number, but still the same value
+    let nan = reread(&LangExp::Number(f64::NAN));
+    assert!(matches!(nan, LangExp::Number(n) if n.is_nan()), "{}", write(&nan));
+}
+
+#[test]
+fn written_collections_read_back_equal() {
+    let sources = [
+        "()",
+        "(1 (2 (3)) \"x\" #\\y z)",
+        "(a . b)",
+        "[1 [2 []] (3)]",
+        "{:a 1 \"b\" [2 {}] 3 (4)}",
+        "'x",
+        "`(a ,b ,@c)",
+        "((((((((((1))))))))))",
+    ];
+
+    for source in sources.iter() {
+        let exp = read_str(source).unwrap().remove(0);
+        assert_eq!(reread(&exp), exp, "{} written as {}", source, write(&exp));
+    }
+}
+
+#[test]
+fn written_text_is_stable() {
+    // writing what was read gives the same text again
+    for source in ["(1 2.5 \"a\\nb\" #\\space [x {:k ()}])", "(quote (quasiquote (unquote x)))"] {
+        let once = write(&read_str(source).unwrap()[0]);
+        let twice = write(&read_str(&once).unwrap()[0]);
+        assert_eq!(once, twice);
+    }
+
+    assert_eq!(write(&read_str("(1 2 3)").unwrap()[0]), "(1 2 3)");
+    assert_eq!(write(&read_str("'x").unwrap()[0]), "(quote x)");
+}
+
+#[test]
+fn reading_reads_every_form_without_evaluating_it() {
+    let forms = read_str("(+ 1 2) x ; a comment\n\"s\"").unwrap();
+    assert_eq!(forms.iter().map(write).collect::<Vec<_>>(), ["(+ 1 2)", "x", "\"s\""]);
+
+    assert!(read_str("").unwrap().is_empty());
+    assert!(read_str("(1 2").is_err());
+    assert!(read_str(")").is_err());
+}
+
+#[test]
+fn comments_run_to_the_end_of_the_line() {
+    let forms = read_str("; before\n(1 ; inside\n 2) ; after").unwrap();
+    assert_eq!(forms.iter().map(write).collect::<Vec<_>>(), ["(1 2)"]);
+
+    assert!(read_str("; only a comment").unwrap().is_empty());
+    assert_eq!(write(&read_str("\"; not a comment\"").unwrap()[0]), "\"; not a comment\"");
+}
+
+#[test]
+fn a_source_may_hold_several_forms_and_gives_the_value_of_the_last() {
+    assert_eq!(read_str("1 (2) [3]\n\n4").unwrap().len(), 4);
+    assert_eq!(Interpreter::new().eval_to_string("(define x 1) (+ x 1) (* x 3)"), "3");
+    assert_eq!(Interpreter::new().eval_to_string("  "), "()");
+}
+
+#[test]
+fn unbalanced_brackets_are_found_where_they_are() {
+    let cases = [
+        ("(+ 1 (* 2 3)", "1:1: parse error: unbalanced parentheses: missing `)`"),
+        (")", "1:1: parse error: unbalanced parentheses: unexpected `)`"),
+        ("(+ 1 2))", "1:8: parse error: unbalanced parentheses: unexpected `)`"),
+        ("(1\n 2))", "2:4: parse error: unbalanced parentheses: unexpected `)`"),
+        ("[1 2)", "1:5: parse error: unbalanced parentheses: unexpected `)`"),
+        ("{1 2", "1:1: parse error: unbalanced braces: missing `}`"),
+        ("'", "1:1: parse error: expected a form after `'`"),
+    ];
+
+    for (source, msg) in cases.iter() {
+        let res = Interpreter::new().eval_to_string(source);
+        assert!(res.starts_with(msg), "{}: {}", source, res);
+        assert!(read_str(source).is_err(), "{}", source);
+    }
+}
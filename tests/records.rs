@@ -0,0 +1,33 @@
+mod common;
+
+use common::eval;
+
+const POINT: &str = "(defstruct point x y)";
+
+#[test]
+fn defstruct_defines_a_constructor_a_predicate_and_accessors() {
+    let source = format!("{} (define p (make-point 1 2)) (list (point? p) (point? 5) (point-x p) (point-y p) p)", POINT);
+    assert_eq!(eval(&source), "(true false 1 2 #<point x: 1, y: 2>)");
+
+    let source = format!("{} (list (record? (make-point 1 2)) (record? '(1 2)) (equal? (make-point 1 2) (make-point 1 2)))", POINT);
+    assert_eq!(eval(&source), "(true false true)");
+}
+
+#[test]
+fn setters_replace_a_field() {
+    assert_eq!(eval(&format!("{} (define p (make-point 1 2)) (set-point-x! p 10) (list (point-x p) (point-y p))", POINT)), "(10 2)");
+}
+
+#[test]
+fn accessors_take_records_of_their_own_type_only() {
+    assert!(eval(&format!("{} (defstruct other x y) (point-x (make-other 1 2))", POINT)).contains("type error: expected a point, got record"));
+    assert!(eval(&format!("{} (point-x 5)", POINT)).contains("type error: expected a point, got integer"));
+    assert!(eval(&format!("{} (make-point 1)", POINT)).contains("`make-point` expects 2 arguments, got 1"));
+}
+
+#[test]
+fn malformed_defstructs_are_errors() {
+    for source in ["(defstruct)", "(defstruct 1 x)", "(defstruct p 1)"] {
+        assert!(eval(source).contains("defstruct expects a name followed by field names"), "{}", source);
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use lisp::is_incomplete;
+
+/// what the repl prints for `input` typed at it
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lisp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+    String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+}
+
+#[test]
+fn results_are_bound_in_order() {
+    let out = repl("(+ 1 2)\n(* $1 10)\n(list $1 $2 $_)\n");
+    assert!(out.contains("// => (3 30 30)"), "{}", out);
+}
+
+#[test]
+fn reset_starts_the_numbering_over() {
+    let out = repl("(+ 1 2)\n(+ 3 4)\n:reset\n(+ 5 6)\n(list $1 $_)\n");
+    assert!(out.contains("// => (11 11)"), "{}", out);
+}
+
+#[test]
+fn results_wider_than_the_width_are_pretty_printed() {
+    // 80 columns at first, the continuation lines lined up under the value
+    let out = repl("(range 0 30)\n");
+    assert!(out.contains("// => (0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27\n       28 29)"), "{}", out);
+
+    let out = repl(":width 20\n(range 0 12)\n(list 1 2)\n");
+    assert!(out.contains("// results wider than 20 columns are pretty-printed"), "{}", out);
+    assert!(out.contains("// => (0 1 2 3 4 5 6\n       7 8 9 10 11)"), "{}", out);
+    assert!(out.contains("// => (1 2)"), "{}", out);
+
+    let out = repl(":width wide\n");
+    assert!(out.contains("// :width needs a number of columns"), "{}", out);
+}
+
+#[test]
+fn forms_left_open_are_continued_on_the_next_lines() {
+    let out = repl("(+ 1\n2\n3)\n(list \"a\nb\")\n(define (f x)\n  ; a comment (\n  (* x 2))\n(f 4)\n");
+    assert!(out.contains("// => 6\n"), "{}", out);
+    assert!(out.contains("// => (\"a\\nb\")\n"), "{}", out);
+    assert!(out.contains("// => 8\n"), "{}", out);
+
+    // a stray closing bracket is an error rather than something to wait for more of
+    let out = repl(")\n(+ 1 1)\n");
+    assert!(out.contains("unbalanced parentheses: unexpected `)`") && out.contains("// => 2"), "{}", out);
+}
+
+#[test]
+fn incomplete_input_is_told_apart_from_finished_or_broken_input() {
+    for source in ["(+ 1", "(a (b)", "[1 2", "{:a", "\"open", "'", "(f ; comment )"] {
+        assert!(is_incomplete(source), "{}", source);
+    }
+
+    for source in ["", "(+ 1 2)", "x", "\"a (\"", "; (", ")", "(a))"] {
+        assert!(!is_incomplete(source), "{}", source);
+    }
+}
+
+#[test]
+fn type_names_the_type_of_a_value() {
+    let out = repl("(define x 5)\n:type x\n:type (car 1)\n:type\n");
+    assert!(out.contains("// => integer\n"), "{}", out);
+    assert!(out.contains("// => 1:1: type error: expected a list, got integer"), "{}", out);
+    assert!(out.contains("// :type needs an argument, see :help"), "{}", out);
+}
+
+#[test]
+fn env_lists_the_global_bindings_with_their_types() {
+    let out = repl("(define x 5)\n(define (sq n) (* n n))\n:env\n:env x\n");
+    for line in ["x: integer\n", "sq: lambda\n", "car: function\n"] {
+        assert!(out.contains(line), "{}", out);
+    }
+    assert!(out.contains("// :env takes no argument"), "{}", out);
+}
+
+#[test]
+fn load_runs_a_file_in_the_session() {
+    let path = std::env::temp_dir().join(format!("lisp-repl-{}.lisp", std::process::id()));
+    std::fs::write(&path, "(define loaded 7)").unwrap();
+
+    let out = repl(&format!(":load {}\n(* loaded 2)\n:load /no/such/file\n", path.display()));
+    assert!(out.contains("// => loaded\n") && out.contains("// => 14\n"), "{}", out);
+    assert!(out.contains("// could not read /no/such/file: "), "{}", out);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn help_quit_and_unknown_commands() {
+    let out = repl(":help\n:bogus\n:quit\n(+ 20 22)\n");
+    assert!(out.contains(":load <file>   evaluate a file in the current session"), "{}", out);
+    assert!(out.contains("// unknown command :bogus, see :help"), "{}", out);
+    assert!(!out.contains("42"), "{}", out);
+}
+
+#[test]
+fn profile_counts_calls_while_it_is_on() {
+    let out = repl(":profile on\n(car '(1))\n:profile off\n(cdr '(1))\n:profile\n:profile clear\n:profile sideways\n");
+    assert!(out.contains("// profiling on") && out.contains("// profiling off") && out.contains("// profile cleared"), "{}", out);
+    assert!(out.contains("  car\n") && !out.contains("  cdr\n"), "{}", out);
+    assert!(out.contains("// :profile takes on, off or clear, or nothing to show the report"), "{}", out);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn functions_see_the_scope_they_were_defined_in() {
+    assert_eq!(eval("(define x 1) (define (f) x) (let ((x 2)) (f))"), "1");
+    assert_eq!(eval("(define (outer y) (lambda () y)) (define y 5) ((outer 3))"), "3");
+}
+
+#[test]
+fn inner_bindings_shadow_outer_ones() {
+    assert_eq!(eval("(let ((x 1)) (let ((x 2)) x))"), "2");
+    assert_eq!(eval("(let ((x 1)) (let ((y 2)) (+ x y)))"), "3");
+    assert_eq!(eval("(define x 1) (let ((x 2)) (set! x 3)) x"), "1");
+}
+
+#[test]
+fn each_call_gets_a_scope_of_its_own() {
+    let source = "(define (make) (let ((n 0)) (lambda () (set! n (+ n 1)) n))) (define a (make)) (define b (make))";
+    assert_eq!(eval(&format!("{} (a) (a) (list (a) (b))", source)), "(3 1)");
+}
+
+#[test]
+fn scopes_end_with_their_form() {
+    assert!(eval("(let ((x 1)) (define y 2)) y").contains("unbound symbol `y`"));
+    assert!(eval("(define (f) (define inner 5) inner) (f) inner").contains("unbound symbol `inner`"));
+    assert!(eval("(set! undefined-thing 1)").contains("unbound symbol `undefined-thing`"));
+}
+
+#[test]
+fn define_binds_names_at_the_top_level_and_in_bodies() {
+    assert_eq!(eval("(define x 1)"), "x");
+    assert_eq!(eval("(define x 5) (define x 6) x"), "6");
+    assert_eq!(eval("(define (f a b) (+ a b)) (f 1 2)"), "3");
+    assert_eq!(eval("(define (f x) (define (g) (* x 2)) (g)) (f 4)"), "8");
+    assert_eq!(eval("(define (f) (define a 1) (define a 2) a) (f)"), "2");
+}
+
+#[test]
+fn malformed_defines_are_errors() {
+    assert!(eval("(define)").contains("expected first form in define"));
+    assert!(eval("(define 1 2)").contains("expected a symbol or a signature as the first form in define"));
+
+    for source in ["(define x)", "(define x 1 2)"] {
+        assert!(eval(source).contains("define expects a name, an optional docstring and a value"), "{}", source);
+    }
+}
+
+#[test]
+fn let_binds_each_value_in_the_outer_scope() {
+    assert_eq!(eval("(let ((x 1) (y 2)) (+ x y))"), "3");
+    assert_eq!(eval("(let ((x 1)) (let ((x 2) (y x)) y))"), "1");
+    assert_eq!(eval("(list (let () 5) (let ((x 1))))"), "(5 ())");
+}
+
+#[test]
+fn let_star_binds_each_value_in_the_scope_of_the_ones_before() {
+    assert_eq!(eval("(let* ((x 1) (y (+ x 1))) (list x y))"), "(1 2)");
+}
+
+#[test]
+fn letrec_binds_functions_that_call_each_other() {
+    let even = "(even? (lambda (n) (if (= n 0) true (odd? (- n 1)))))";
+    let odd = "(odd? (lambda (n) (if (= n 0) false (even? (- n 1)))))";
+    assert_eq!(eval(&format!("(letrec ({} {}) (list (even? 10) (odd? 7)))", even, odd)), "(true true)");
+}
+
+#[test]
+fn malformed_lets_are_errors() {
+    assert!(eval("(let x 1)").contains("expected a binding list in let"));
+
+    for source in ["(let ((1 2)) 3)", "(let ((x)) x)", "(let* ((x 1 2)) x)"] {
+        assert!(eval(source).contains("expected let bindings of the form (name value)"), "{}", source);
+    }
+}
+
+#[test]
+fn set_changes_the_binding_where_it_was_made() {
+    assert_eq!(eval("(define x 1) (set! x (+ x 1))"), "2");
+    assert_eq!(eval("(define x 1) (define (f) (set! x 10)) (f) x"), "10");
+    assert_eq!(eval("(let ((v 1)) (let ((g (lambda () (set! v (* v 5))))) (g) (g) v))"), "25");
+}
+
+#[test]
+fn malformed_sets_are_errors() {
+    for source in ["(set! 1 2)", "(set! x)", "(define x 1) (set! x 1 2)"] {
+        assert!(eval(source).contains("set! expects a symbol and a value"), "{}", source);
+    }
+}
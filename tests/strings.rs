@@ -0,0 +1,62 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn string_literals_read_with_their_escapes() {
+    assert_eq!(eval(r#""hello world""#), r#""hello world""#);
+    assert_eq!(eval(r#"(string-length "a \"quoted\" back\\slash")"#), "21");
+    assert_eq!(eval(r#"(string->list "\n\t")"#), r#"(#\newline #\tab)"#);
+    assert_eq!(eval(r#"(list "" (string? "x") (string? 'x))"#), r#"("" true false)"#);
+}
+
+#[test]
+fn string_literals_hold_what_would_otherwise_be_syntax() {
+    assert_eq!(eval(r#"(string-length "with ( parens ) and ; semicolon")"#), "31");
+    assert_eq!(eval("(string->list \"a\nb\")"), r#"(#\a #\newline #\b)"#);
+    assert_eq!(eval(r#"(list "a" 'a)"#), r#"("a" a)"#);
+}
+
+#[test]
+fn malformed_string_literals_are_errors() {
+    assert!(eval(r#""unterminated"#).contains("parse error: unterminated string literal"));
+    assert!(eval(r#""bad \q escape""#).contains("parse error: unknown escape sequence `\\q`"));
+}
+
+#[test]
+fn string_builtins() {
+    assert_eq!(eval("(list (str \"a\" 1 'b 2.5) (str) (str \"x\" '(1 2)))"), r#"("a1b2.5" "" "x(1 2)")"#);
+    assert_eq!(eval("(list (string-length \"\") (string-length \"héllo\") (substring \"hello\" 1 3) (substring \"hello\" 2) (substring \"héllo\" 1 2))"), r#"(0 5 "el" "llo" "é")"#);
+    assert_eq!(eval("(list (string-split \"a,b,,c\" \",\") (string-split \"abc\" \"\") (string-join (list \"a\" \"b\") \"-\") (string-join '() \",\"))"), r#"(("a" "b" "" "c") ("a" "b" "c") "a-b" "")"#);
+    assert_eq!(eval("(list (string-upcase \"abC\") (string-downcase \"ABc\"))"), r#"("ABC" "abc")"#);
+    assert_eq!(eval("(list (string->number \"12\") (string->number \"2.5\") (string->number \"x\") (number->string 12) (number->string 1.5))"), r#"(12 2.5 false "12" "1.5")"#);
+}
+
+#[test]
+fn string_builtins_check_their_arguments() {
+    assert!(eval("(substring \"hello\" 3 10)").contains("substring range 3..10 is out of bounds for a string of length 5"));
+    assert!(eval("(substring \"hello\" 3 1)").contains("substring range 3..1 is out of bounds"));
+    assert!(eval("(string-length 5)").contains("type error: expected a string, got integer"));
+}
+
+#[test]
+fn character_literals_read_as_themselves() {
+    let chars = r#"(list #\space #\newline #\tab #\return #\nul #\a #\λ #\( #\" #\;)"#;
+    assert_eq!(eval(chars), r#"(#\space #\newline #\tab #\return #\nul #\a #\λ #\( #\" #\;)"#);
+    assert_eq!(eval("(list (char? #\\a) (char? \"a\") (eq? #\\a #\\a) (equal? #\\a #\\b))"), "(true false true false)");
+    assert!(eval("#\\bogus").contains("parse error: unknown character name `#\\bogus`"));
+}
+
+#[test]
+fn strings_are_taken_apart_and_put_together_by_character() {
+    assert_eq!(eval("(list (string->list \"aλ\") (string->list \"\") (list->string (list #\\a #\\b)) (list->string '()))"), r#"((#\a #\λ) () "ab" "")"#);
+    assert_eq!(eval("(list (char->int #\\A) (char->int #\\nul) (int->char 955))"), "(65 0 #\\λ)");
+}
+
+#[test]
+fn character_builtins_check_their_arguments() {
+    assert!(eval("(int->char -1)").contains("no character has the code point -1"));
+    assert!(eval("(int->char 1114112)").contains("no character has the code point 1114112"));
+    assert!(eval("(list->string (list #\\a 1))").contains("type error: expected a character, got integer"));
+    assert!(eval("(char->int \"a\")").contains("type error: expected a character, got string"));
+}
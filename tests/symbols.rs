@@ -0,0 +1,30 @@
+use std::thread;
+
+use lisp::SymbolId;
+
+mod common;
+
+use common::eval;
+
+#[test]
+fn interning_a_name_twice_gives_the_same_symbol() {
+    let id = SymbolId::intern("symbols-test-name");
+
+    assert_eq!(SymbolId::intern("symbols-test-name"), id);
+    assert_ne!(SymbolId::intern("symbols-test-other"), id);
+    assert_eq!(&*id.name(), "symbols-test-name");
+    assert_eq!(id.to_string(), "symbols-test-name");
+}
+
+#[test]
+fn symbols_are_the_same_on_every_thread() {
+    let here = SymbolId::intern("symbols-test-shared");
+    let there = thread::spawn(|| SymbolId::intern("symbols-test-shared")).join().unwrap();
+
+    assert_eq!(here, there);
+}
+
+#[test]
+fn symbols_read_with_the_same_name_are_eq() {
+    assert_eq!(eval("(list (eq? 'a 'a) (eq? 'a 'b) (eq? 'A 'a) (eq? 'a (eval ''a)) (eq? 'a (car '(a))))"), "(true false false true true)");
+}
@@ -0,0 +1,48 @@
+use lisp::Interpreter;
+
+mod common;
+
+use common::{eval, output, run};
+
+const TESTS: &str = "(deftest adds (assert-eq (+ 1 1) 2)) (deftest fails (assert-eq (+ 1 1) 3)) (deftest breaks (car 1))";
+
+#[test]
+fn run_tests_reports_each_test_and_a_summary() {
+    let report = output(&format!("{} (run-tests)", TESTS));
+    assert_eq!(
+        report,
+        "test adds ... ok\ntest fails ... FAILED\n  1:53: assertion failed: (+ 1 1) is 2, expected 3\ntest breaks ... FAILED\n  1:92: type error: expected a list, got integer\n\n3 tests, 1 passed, 2 failed\n"
+    );
+
+    assert_eq!(run(&mut Interpreter::new(), &format!("{} (run-tests)", TESTS)).0, "false");
+    assert_eq!(run(&mut Interpreter::new(), "(deftest adds (assert true)) (run-tests)").0, "true");
+    assert_eq!(output("(run-tests)"), "\n0 tests, 0 passed, 0 failed\n");
+}
+
+#[test]
+fn a_test_defined_again_replaces_the_first() {
+    assert_eq!(output("(deftest adds (assert true)) (deftest adds (assert false)) (run-tests)"), "test adds ... FAILED\n  1:44: assertion failed: false\n\n1 test, 0 passed, 1 failed\n");
+}
+
+#[test]
+fn the_embedder_is_told_how_many_tests_failed() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.failed_tests(), 0);
+
+    run(&mut interpreter, &format!("{} (run-tests)", TESTS));
+    assert_eq!(interpreter.failed_tests(), 2);
+}
+
+#[test]
+fn assertions_fail_with_the_form_and_the_values() {
+    assert_eq!(eval("(list (assert (= 1 1)) (assert-eq (list 1) (list 1)))"), "(true true)");
+    assert!(eval("(assert (= 1 2))").starts_with("1:1: assertion failed: (= 1 2)"));
+    assert!(eval("(assert-eq 1 2)").starts_with("1:1: assertion failed: 1 is 1, expected 2"));
+}
+
+#[test]
+fn malformed_tests_are_errors() {
+    for source in ["(deftest)", "(deftest t)"] {
+        assert!(eval(source).contains("deftest expects a name and a body"), "{}", source);
+    }
+}
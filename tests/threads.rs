@@ -0,0 +1,55 @@
+use lisp::Interpreter;
+
+fn eval(source: &str) -> String {
+    Interpreter::new().eval_to_string(source)
+}
+
+#[test]
+fn values_sent_from_a_thread_are_received() {
+    assert_eq!(eval("(let ((c (chan))) (spawn (lambda () (dotimes (i 1000) i) (send! c 5))) (recv! c))"), "5");
+    assert_eq!(eval("(let ((c (chan)) (d (chan))) (spawn (lambda () (send! d (recv! c)))) (send! c 7) (recv! d))"), "7");
+}
+
+#[test]
+fn receiving_with_no_thread_left_to_send_fails() {
+    let res = eval("(recv! (chan))");
+    assert!(res.contains("recv!: the channel is empty and no thread is left to send to it"), "{}", res);
+
+    let res = eval("(let ((c (chan))) (join (spawn (lambda () 1))) (recv! c))");
+    assert!(res.contains("recv!: the channel is empty and no thread is left to send to it"), "{}", res);
+}
+
+#[test]
+fn a_spawned_thread_receiving_with_no_thread_left_to_send_fails() {
+    let res = eval("(join (spawn (lambda () (recv! (chan)))))");
+    assert!(res.contains("recv!: the channel is empty and no thread is left to send to it"), "{}", res);
+
+    // the joining thread can't send while it waits for the receiving one
+    let res = eval("(let ((c (chan))) (join (spawn (lambda () (recv! c)))))");
+    assert!(res.contains("recv!: the channel is empty and no thread is left to send to it"), "{}", res);
+}
+
+#[test]
+fn threads_of_another_interpreter_dont_keep_a_receive_waiting() {
+    let mut first = Interpreter::new();
+    first.eval_str("(define c (chan)) (define t (spawn (lambda () (recv! c))))").unwrap();
+
+    let res = eval("(recv! (chan))");
+    assert!(res.contains("recv!: the channel is empty and no thread is left to send to it"), "{}", res);
+
+    // the thread waiting in the first interpreter is still waiting for it to send
+    assert_eq!(first.eval_to_string("(send! c 3) (join t)"), "3");
+}
+
+#[test]
+fn a_value_handed_back_and_forth_keeps_its_receivers_active() {
+    // each side sends and then waits at once, before the other has woken up to take the value
+    let res = eval(
+        "(define ping (chan))
+         (define pong (chan))
+         (spawn (lambda () (dotimes (i 200) (send! pong (+ (recv! ping) 1)))))
+         (define (rally i n) (if (= i 200) n (begin (send! ping n) (rally (+ i 1) (recv! pong)))))
+         (rally 0 0)",
+    );
+    assert_eq!(res, "200");
+}
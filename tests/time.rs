@@ -0,0 +1,39 @@
+mod common;
+
+use common::{eval, output};
+
+#[test]
+fn now_ms_is_the_time_since_the_epoch() {
+    assert_eq!(eval("(let ((t (now-ms))) (list (integer? t) (> t 1600000000000)))"), "(true true)");
+}
+
+#[test]
+fn time_prints_how_long_its_form_took() {
+    assert_eq!(eval("(time (+ 1 2))"), "3");
+
+    let printed = output("(time (+ 1 2))");
+    assert!(printed.starts_with("elapsed time: ") && printed.ends_with(" ms\n"), "{}", printed);
+
+    assert!(eval("(time)").contains("time expects exactly one form"));
+}
+
+#[test]
+fn random_numbers_stay_in_their_range() {
+    assert_eq!(eval("(every? (lambda (r) (and (number? r) (not (integer? r)) (>= r 0) (< r 1))) (map (lambda (i) (random)) (range 0 200)))"), "true");
+    assert_eq!(eval("(every? (lambda (x) (and (integer? x) (>= x 0) (< x 3))) (map (lambda (i) (rand-int 3)) (range 0 200)))"), "true");
+    assert_eq!(eval("(rand-int 1)"), "0");
+}
+
+#[test]
+fn a_seed_gives_the_same_numbers_every_time() {
+    let draw = "(list (random) (rand-int 100) (rand-int 1000000))";
+    assert_eq!(eval(&format!("(seed-random 42) (define a {}) (seed-random 42) (equal? a {})", draw, draw)), "true");
+    assert_eq!(eval(&format!("(seed-random 1) (define a {}) (seed-random 2) (equal? a {})", draw, draw)), "false");
+}
+
+#[test]
+fn random_builtins_check_their_arguments() {
+    assert!(eval("(rand-int 0)").contains("type error: expected a positive integer, got integer"));
+    assert!(eval("(rand-int -1)").contains("type error: expected a positive integer, got integer"));
+    assert!(eval("(seed-random 1.5)").contains("type error: expected an integer, got float"));
+}
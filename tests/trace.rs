@@ -0,0 +1,31 @@
+mod common;
+
+use common::{eval, output};
+
+const FACT: &str = "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1)))))";
+
+#[test]
+fn traced_calls_are_logged_with_their_arguments_and_values() {
+    let log = output(&format!("{} (trace fact) (fact 2)", FACT));
+    assert_eq!(log, "(fact 2)\n  (fact 1)\n    (fact 0)\n    => 1\n  => 1\n=> 2\n");
+
+    assert_eq!(output("(trace car) (car '(1 2))"), "(car (1 2))\n=> 1\n");
+}
+
+#[test]
+fn calls_that_fail_are_logged_with_the_error() {
+    let log = output("(define (f x) (car x)) (trace f) (try (f 1) (catch e 'caught))");
+    assert!(log.starts_with("(f 1)\n!! ") && log.contains("type error: expected a list, got integer"), "{}", log);
+}
+
+#[test]
+fn untrace_stops_logging() {
+    assert_eq!(eval("(define (f x) x) (define (g x) x) (list (trace f g) (untrace f) (untrace) (trace))"), "((f g) (g) () ())");
+    assert_eq!(output(&format!("{} (trace fact) (untrace fact) (fact 2)", FACT)), "");
+    assert_eq!(output(&format!("{} (trace fact) (untrace) (fact 2)", FACT)), "");
+}
+
+#[test]
+fn only_functions_are_traced() {
+    assert!(eval("(trace 1)").contains("type error: expected a function, got integer"));
+}
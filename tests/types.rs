@@ -0,0 +1,32 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn each_value_answers_to_the_predicate_of_its_type() {
+    let predicates = "(lambda (x) (list (number? x) (integer? x) (string? x) (symbol? x) (list? x) (null? x) (fn? x) (bool? x) (map? x)))";
+    let cases = [
+        ("1", "(true true false false false false false false false)"),
+        ("1.5", "(true false false false false false false false false)"),
+        ("\"s\"", "(false false true false false false false false false)"),
+        ("'a", "(false false false true false false false false false)"),
+        ("'(1)", "(false false false false true false false false false)"),
+        ("'()", "(false false false false true true false false false)"),
+        ("car", "(false false false false false false true false false)"),
+        ("(lambda (x) x)", "(false false false false false false true false false)"),
+        ("false", "(false false false false false false false true false)"),
+        ("(hash-map)", "(false false false false false false false false true)"),
+    ];
+
+    for (value, answers) in cases.iter() {
+        assert_eq!(eval(&format!("({} {})", predicates, value)), *answers, "{}", value);
+    }
+
+    assert_eq!(eval("(list (fn? 'car) (list? (vector 1)) (null? false))"), "(false false false)");
+}
+
+#[test]
+fn predicates_take_one_argument() {
+    assert!(eval("(number?)").contains("`number?` expects 1 argument, got 0"));
+    assert!(eval("(map? 1 2)").contains("`map?` expects 1 argument, got 2"));
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn bracket_literals_evaluate_their_elements() {
+    assert_eq!(eval("(let ((x 2)) [1 x (+ x 1)])"), "[1 2 3]");
+    assert_eq!(eval("[]"), "[]");
+}
+
+#[test]
+fn vectors_are_updated_in_place() {
+    assert_eq!(eval("(define v (vector 1 2 3)) (define w v) (vector-set! w 1 'x) v"), "[1 x 3]");
+    assert_eq!(eval("(let ((v [1 2])) (list (vector-ref v 1) (vector-length v) (vector->list v)))"), "(2 2 (1 2))");
+}
+
+#[test]
+fn each_evaluation_of_a_literal_makes_a_fresh_vector() {
+    assert_eq!(eval("(define (f) [1 2]) (vector-set! (f) 0 9) (f)"), "[1 2]");
+}
+
+#[test]
+fn an_element_may_write_to_the_vector_being_evaluated() {
+    let src = "(define src (vector 0 '(vector-set! src 0 1))) (list (eval src) src)";
+    assert_eq!(eval(src), "([0 1] [1 (vector-set! src 0 1)])");
+}